@@ -1,8 +1,9 @@
 use crate::read_extension::ReadExt;
-use crate::tmf::SectionType;
+use crate::tmf::{CompressionType, SectionType};
 use crate::{FloatType, IndexType, TMFImportError, Vector4, MAX_SEG_SIZE};
+/// Named custom mesh data, which can be saved together with a [`crate::TMFMeshRef`].
 #[derive(Clone, Debug)]
-pub(crate) struct CustomDataSegment {
+pub struct CustomDataSegment {
     name: Vec<u8>,
     name_len: u8,
     data: CustomData,
@@ -33,6 +34,15 @@ impl CustomDataSegment {
     pub(crate) fn name_bytes(&self) -> &[u8] {
         &self.name
     }
+    /// Creates custom data named *name*.
+    /// ```
+    /// # use tmf::{CustomData, CustomDataSegment};
+    /// let floats = [0.1, 0.5, 0.7];
+    /// let segment = CustomDataSegment::new(CustomData::from(&floats[..]), "weights")
+    ///     .expect("Could not create custom data!");
+    /// ```
+    /// # Errors
+    /// Returns a [`DataSegmentError`] if *name* is empty, or longer than 255 bytes.
     pub fn new(data: CustomData, name: &str) -> Result<Self, DataSegmentError> {
         let bytes = name.as_bytes();
         let len = name.len();
@@ -49,11 +59,14 @@ impl CustomDataSegment {
 }
 /// Custom mesh data.
 #[derive(Clone, Debug)]
+#[non_exhaustive]
 pub enum CustomData {
     CustomIndex(Box<[IndexType]>, usize),
     CustomIntiger(Box<[IndexType]>, usize),
     CustomFloat(Box<[FloatType]>, FloatType),
     CustomColorRGBA(Box<[Vector4]>, FloatType),
+    /// Floats saved exactly, so they are read back bit for bit the same. See [`Self::lossless_float`].
+    CustomLosslessFloat(Box<[FloatType]>),
 }
 impl CustomData {
     /// Returns the index data if custom segment is an index segment. Returns the index array and max index.
@@ -69,9 +82,11 @@ impl CustomData {
             _ => None,
         }
     }
+    /// Returns the float data if custom segment is a float segment. Returns the float array and the largest deviation of saved floats, which is 0 for floats saved exactly.
     pub fn as_float(&self) -> Option<(&[FloatType], FloatType)> {
         match self {
             Self::CustomFloat(array, prec) => Some((array, *prec)),
+            Self::CustomLosslessFloat(array) => Some((array, 0.0)),
             _ => None,
         }
     }
@@ -81,8 +96,22 @@ impl CustomData {
             _ => None,
         }
     }
-    fn write<W: std::io::Write>(&self, target: &mut W) -> std::io::Result<()> {
+    /// Creates custom float data, which is saved exactly, so it is read back bit for bit the same.
+    /// ```
+    /// # use tmf::CustomData;
+    /// let data = CustomData::lossless_float(&[0.1, 1e-30, -7.5]);
+    /// assert_eq!(data.as_float(), Some((&[0.1, 1e-30, -7.5][..], 0.0)));
+    /// ```
+    pub fn lossless_float(floats: &[FloatType]) -> Self {
+        Self::CustomLosslessFloat(floats.into())
+    }
+    /// Writes this data, and returns compression it was written with.
+    fn write<W: std::io::Write>(&self, target: &mut W) -> std::io::Result<CompressionType> {
         match self {
+            Self::CustomLosslessFloat(data) => {
+                crate::lossless::save_floats(data, target)?;
+                return Ok(CompressionType::Lossless);
+            }
             Self::CustomIndex(data, max_index) => {
                 crate::vertices::save_triangles(data, *max_index, target)
             }
@@ -99,9 +128,9 @@ impl CustomData {
                 }
                 let span = max - min;
                 let prec = (span / prec).log2().ceil() as u8;
-                //Ensure precision is never 0(messes up the reader/writer);
-                let prec = prec.max(1);
-                let mul = ((1 << prec) - 1) as FloatType;
+                //Ensure precision is never 0(messes up the reader/writer), and never more than floats can represent(e.g. for a precision of 0).
+                let prec = prec.clamp(1, FloatType::MANTISSA_DIGITS as u8);
+                let mul = ((1_u64 << prec) - 1) as FloatType;
                 target.write_all(&(data.len() as u64).to_le_bytes())?;
                 target.write_all(&(min as f64).to_le_bytes())?;
                 target.write_all(&(max as f64).to_le_bytes())?;
@@ -135,13 +164,15 @@ impl CustomData {
                 Ok(())
             }
         }?;
-        Ok(())
+        Ok(CompressionType::None)
     }
     fn section_type(&self) -> SectionType {
         match self {
             Self::CustomIndex(_, _) => SectionType::CustomIndexSegment,
-            Self::CustomIntiger(_, _) => SectionType::CustomIntigerSegment,
-            Self::CustomFloat(_, _) => SectionType::CustomFloatSegment,
+            Self::CustomIntiger(_, _) => SectionType::CustomIntegerSegment,
+            Self::CustomFloat(_, _) | Self::CustomLosslessFloat(_) => {
+                SectionType::CustomFloatSegment
+            }
             Self::CustomColorRGBA(_, _) => SectionType::CustomColorRGBASegment,
         }
     }
@@ -182,16 +213,21 @@ impl From<&[Vector4]> for CustomData {
     }
 }
 impl CustomDataSegment {
-    pub(crate) fn encode<W: std::io::Write>(&self, target: &mut W) -> std::io::Result<SectionType> {
+    /// Writes this segment, and returns its type and compression.
+    pub(crate) fn encode<W: std::io::Write>(
+        &self,
+        target: &mut W,
+    ) -> std::io::Result<(SectionType, CompressionType)> {
         target.write_all(&[self.name_len])?;
         target.write_all(&self.name[..(self.name_len as usize)])?;
-        self.data.write(target)?;
-        Ok(self.data.section_type())
+        let compression = self.data.write(target)?;
+        Ok((self.data.section_type(), compression))
     }
-    //return Err(std::io::Error::new(std::io::ErrorKind::Other,format!("Invalid custom se"),)),
+    //return Err(std::io::Error::other(format!("Invalid custom se"),)),
     pub(crate) fn read<R: std::io::Read>(
         mut src: R,
         kind: SectionType,
+        compression: CompressionType,
         ctx: &crate::tmf_importer::TMFImportContext,
     ) -> Result<Self, TMFImportError> {
         let name_len = src.read_u8()?;
@@ -209,7 +245,7 @@ impl CustomDataSegment {
                     name_len,
                 ))
             }
-            SectionType::CustomIntigerSegment => {
+            SectionType::CustomIntegerSegment => {
                 let result = crate::vertices::read_triangles(&mut src, ctx)?;
                 Ok(Self::new_raw(
                     CustomData::new_intiger(&result, None),
@@ -217,6 +253,14 @@ impl CustomDataSegment {
                     name_len,
                 ))
             }
+            SectionType::CustomFloatSegment if compression == CompressionType::Lossless => {
+                let result = crate::lossless::read_floats(&mut src)?;
+                Ok(Self::new_raw(
+                    CustomData::lossless_float(&result),
+                    name,
+                    name_len,
+                ))
+            }
             SectionType::CustomFloatSegment => {
                 use crate::unaligned_rw::{UnalignedRWMode, UnalignedReader};
                 let len = src.read_u64()?;
@@ -234,10 +278,11 @@ impl CustomDataSegment {
                 let span = max - min;
                 let prec = UnalignedRWMode::precision_bits(prec);
                 let mut reader = UnalignedReader::new(src);
-                let mut res = vec![0.0; len as usize];
-                for float in &mut res {
+                let mut res = Vec::new();
+                crate::reserve_declared(&mut res, len as usize);
+                for _ in 0..len {
                     let val = reader.read_unaligned(prec)?;
-                    *float = (((val as f64) / div) * span + min) as FloatType;
+                    res.push((((val as f64) / div) * span + min) as FloatType);
                 }
                 let prec = (((max - min) / ((1_u64 << prec_bits) as f64)) as FloatType) * 0.99999;
                 Ok(Self::new_raw(
@@ -249,20 +294,27 @@ impl CustomDataSegment {
             SectionType::CustomColorRGBASegment => {
                 use crate::unaligned_rw::{UnalignedRWMode, UnalignedReader};
                 let len = src.read_u64()?;
+                if len > MAX_SEG_SIZE as u64 {
+                    return Err(TMFImportError::SegmentTooLong);
+                }
                 let prec_bits = src.read_u8()?;
+                if prec_bits >= u64::BITS as u8 {
+                    return Err(TMFImportError::InvalidPrecision(prec_bits));
+                }
                 let prec = UnalignedRWMode::precision_bits(prec_bits);
                 let mut reader = UnalignedReader::new(src);
-                let mut res = vec![(0.0, 0.0, 0.0, 0.0); len as usize];
+                let mut res = Vec::new();
+                crate::reserve_declared(&mut res, len as usize);
                 let div = ((1_u64 << prec_bits) - 1) as f64;
-                for vec4 in &mut res {
+                for _ in 0..len {
                     let (r, g) = reader.read2_unaligned(prec)?;
                     let (b, a) = reader.read2_unaligned(prec)?;
-                    *vec4 = (
+                    res.push((
                         ((r as f64) / div) as FloatType,
                         ((g as f64) / div) as FloatType,
                         ((b as f64) / div) as FloatType,
                         ((a as f64) / div) as FloatType,
-                    );
+                    ));
                 }
                 let prec = ((1.0 / ((1_u64 << prec_bits) as f64)) as FloatType) * 0.99999;
                 Ok(Self::new_raw(
@@ -271,7 +323,7 @@ impl CustomDataSegment {
                     name_len,
                 ))
             }
-            _ => panic!("InternalError: Invalid custom section type, must be custom!"),
+            _ => Err(TMFImportError::UnexpectedSegmentType(kind)),
         }
     }
 }
@@ -406,3 +458,33 @@ fn color_rgba_data() {
         );
     }
 }
+#[test]
+#[cfg(test)]
+fn zero_precision_float_data() {
+    use crate::{TMFMesh, TMFPrecisionInfo};
+    let float_data: [FloatType; 4] = [-7.0, 1.9, 3.7867, 9.54];
+    let mut tmf_mesh = TMFMesh::empty();
+    // Precision of 0 asks for the most precise quantization, not for lossless floats.
+    tmf_mesh
+        .add_custom_data(
+            CustomData::CustomFloat(float_data[..].into(), 0.0),
+            "custom_float",
+        )
+        .unwrap();
+    let mut out = Vec::new();
+    tmf_mesh
+        .write_tmf_one(&mut out, &TMFPrecisionInfo::default(), "floats")
+        .unwrap();
+    let info = crate::inspect(&out[..]).unwrap();
+    assert_eq!(
+        info.meshes[0].segments[0].compression_type,
+        CompressionType::None
+    );
+    let (r_mesh, _) = TMFMesh::read_tmf_one(&mut &out[..]).unwrap();
+    let read_floats = r_mesh.lookup_custom_data("custom_float").unwrap();
+    assert!(matches!(read_floats, CustomData::CustomFloat(..)));
+    let (read_floats, _) = read_floats.as_float().unwrap();
+    for (read, saved) in read_floats.iter().zip(float_data) {
+        assert!((read - saved).abs() <= 1e-5, "{read} != {saved}");
+    }
+}
@@ -0,0 +1,401 @@
+use crate::range_coder::{RangeDecoder, RangeEncoder, ValueModel, PROB_INIT};
+use crate::read_extension::ReadExt;
+use crate::{IndexType, TMFImportError, MAX_SEG_SIZE};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+/// Setting dictating how triangle connectivity of a mesh is stored.
+///```
+/// # use tmf::{ConnectivityMode, TMFPrecisionInfo};
+/// // Store vertex triangles as a traversal of the mesh, reordering its triangles and vertices.
+/// let prec = TMFPrecisionInfo {
+///     connectivity_mode: ConnectivityMode::Edgebreaker,
+///     ..TMFPrecisionInfo::default()
+/// };
+///```
+pub enum ConnectivityMode {
+    /// Triangles are stored as arrays of indices, preserving their order exactly.
+    Indices,
+    /// Vertex triangles are stored as a traversal of the mesh, using about 2 bits per triangle for manifold meshes. Non-manifold parts of the mesh are stored as separate triangles, and segments which can be stored more efficiently as indices are stored as indices.
+    /// Order of triangles and vertices will change, since the mesh is reordered to follow its traversal. Meshes with custom data are not reordered, because custom data may refer to vertices or triangles.
+    Edgebreaker,
+}
+impl Default for ConnectivityMode {
+    /// Stores triangles as indices by default.
+    ///```
+    /// # use tmf::ConnectivityMode;
+    /// assert!(ConnectivityMode::default() == ConnectivityMode::Indices);
+    ///```
+    fn default() -> Self {
+        Self::Indices
+    }
+}
+/// Operation describing how a triangle is attached to the mesh decoded so far.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Op {
+    /// Triangle attached to current gate, with a vertex never used before.
+    Create = 0,
+    /// Triangle attached to current gate, with a vertex at the end of an open gate starting at the end of current gate.
+    Left = 1,
+    /// Triangle attached to current gate, with a vertex at the start of an open gate ending at the start of current gate.
+    Right = 2,
+    /// Triangle attached to current gate, with an explicitly stored vertex.
+    Join = 3,
+    /// Current gate is closed without attaching a triangle.
+    Skip = 4,
+    /// Triangle not attached to any gate, stored explicitly.
+    New = 5,
+}
+const OP_BITS: u32 = 3;
+const OP_COUNT: usize = 6;
+impl Op {
+    fn from_u64(op: u64) -> Result<Self, TMFImportError> {
+        match op {
+            0 => Ok(Self::Create),
+            1 => Ok(Self::Left),
+            2 => Ok(Self::Right),
+            3 => Ok(Self::Join),
+            4 => Ok(Self::Skip),
+            5 => Ok(Self::New),
+            _ => Err(TMFImportError::IO(std::io::Error::other(
+                "Invalid connectivity operation.",
+            ))),
+        }
+    }
+}
+/// How many open gates below the current one are checked for a triangle before it is stored explicitly.
+const MAX_SKIP_DEPTH: usize = 16;
+type Edge = (IndexType, IndexType);
+fn remove_last(ends: Option<&mut Vec<IndexType>>, end: IndexType) {
+    if let Some(ends) = ends {
+        if let Some(pos) = ends.iter().rposition(|curr| *curr == end) {
+            ends.remove(pos);
+        }
+    }
+}
+/// Directed edges of already decoded triangles whose neighbouring triangle was not decoded yet.
+#[derive(Default)]
+struct Gates {
+    stack: Vec<Edge>,
+    open: HashMap<Edge, usize>,
+    outgoing: HashMap<IndexType, Vec<IndexType>>,
+    incoming: HashMap<IndexType, Vec<IndexType>>,
+}
+impl Gates {
+    /// Opens *edge*, or closes its twin if it is already open.
+    fn push(&mut self, edge: Edge) {
+        let twin = (edge.1, edge.0);
+        if self.open.contains_key(&twin) {
+            self.close(twin);
+        } else {
+            *self.open.entry(edge).or_insert(0) += 1;
+            self.outgoing.entry(edge.0).or_default().push(edge.1);
+            self.incoming.entry(edge.1).or_default().push(edge.0);
+            self.stack.push(edge);
+        }
+    }
+    fn close(&mut self, edge: Edge) {
+        match self.open.get_mut(&edge) {
+            Some(count) if *count > 1 => *count -= 1,
+            _ => {
+                self.open.remove(&edge);
+            }
+        }
+        remove_last(self.outgoing.get_mut(&edge.0), edge.1);
+        remove_last(self.incoming.get_mut(&edge.1), edge.0);
+    }
+    /// Returns the most recently opened gate which is still open.
+    fn top(&mut self) -> Option<Edge> {
+        while let Some(edge) = self.stack.last() {
+            if self.open.contains_key(edge) {
+                return Some(*edge);
+            }
+            self.stack.pop();
+        }
+        None
+    }
+    fn pop(&mut self) -> Option<Edge> {
+        let edge = self.top()?;
+        self.stack.pop();
+        self.close(edge);
+        Some(edge)
+    }
+    /// Checks if *triangle* is attached to any of the first [`MAX_SKIP_DEPTH`] open gates.
+    fn is_near(&self, triangle: [IndexType; 3]) -> bool {
+        self.stack
+            .iter()
+            .rev()
+            .filter(|edge| self.open.contains_key(edge))
+            .take(MAX_SKIP_DEPTH)
+            .any(|(u, v)| attachment(triangle, (*u, *v)).is_some())
+    }
+    fn left(&self, v: IndexType) -> Option<IndexType> {
+        self.outgoing.get(&v)?.last().copied()
+    }
+    fn right(&self, u: IndexType) -> Option<IndexType> {
+        self.incoming.get(&u)?.last().copied()
+    }
+    /// Attaches triangle `(v, u, w)` to gate `(u, v)`, which must be already popped.
+    fn attach(&mut self, (u, v): Edge, w: IndexType) {
+        self.push((w, v));
+        self.push((u, w));
+    }
+    fn add_new(&mut self, triangle: [IndexType; 3]) {
+        self.push((triangle[0], triangle[1]));
+        self.push((triangle[1], triangle[2]));
+        self.push((triangle[2], triangle[0]));
+    }
+}
+/// If *triangle* contains twin of *gate*, returns rotation `k` such that rotating *triangle* by `k` gives `(v, u, w)`.
+fn attachment(triangle: [IndexType; 3], (u, v): Edge) -> Option<usize> {
+    (0..3).find(|k| triangle[*k] == v && triangle[(k + 1) % 3] == u)
+}
+fn rotate(triangle: [IndexType; 3], by: usize) -> [IndexType; 3] {
+    [
+        triangle[by % 3],
+        triangle[(by + 1) % 3],
+        triangle[(by + 2) % 3],
+    ]
+}
+/// State of a mesh traversal, shared by the encoder and the decoder.
+struct Traversal {
+    gates: Gates,
+    /// Index of the next vertex never used before.
+    next_vertex: u64,
+    prev_op: Op,
+    ops: [[u16; 1 << OP_BITS]; OP_COUNT],
+    rotation: [u16; 4],
+    join: ValueModel,
+    new: ValueModel,
+}
+impl Traversal {
+    fn new() -> Self {
+        Self {
+            gates: Gates::default(),
+            next_vertex: 0,
+            prev_op: Op::New,
+            ops: [[PROB_INIT; 1 << OP_BITS]; OP_COUNT],
+            rotation: [PROB_INIT; 4],
+            join: ValueModel::new(),
+            new: ValueModel::new(),
+        }
+    }
+    fn use_vertex(&mut self, index: IndexType) {
+        self.next_vertex = self.next_vertex.max(u64::from(index).saturating_add(1));
+    }
+    fn encode_op<W: Write>(
+        &mut self,
+        encoder: &mut RangeEncoder<W>,
+        op: Op,
+    ) -> std::io::Result<()> {
+        encoder.encode_tree(&mut self.ops[self.prev_op as usize], OP_BITS, op as u64)?;
+        self.prev_op = op;
+        Ok(())
+    }
+    fn decode_op<R: Read>(&mut self, decoder: &mut RangeDecoder<R>) -> Result<Op, TMFImportError> {
+        let op = Op::from_u64(decoder.decode_tree(&mut self.ops[self.prev_op as usize], OP_BITS)?)?;
+        self.prev_op = op;
+        Ok(op)
+    }
+    fn encode_triangle<W: Write>(
+        &mut self,
+        encoder: &mut RangeEncoder<W>,
+        triangle: [IndexType; 3],
+    ) -> std::io::Result<()> {
+        while let Some(gate) = self.gates.top() {
+            if let Some(k) = attachment(triangle, gate) {
+                let (u, v) = gate;
+                self.gates.pop();
+                let w = triangle[(k + 2) % 3];
+                let op = if u64::from(w) == self.next_vertex {
+                    Op::Create
+                } else if self.gates.left(v) == Some(w) {
+                    Op::Left
+                } else if self.gates.right(u) == Some(w) {
+                    Op::Right
+                } else {
+                    Op::Join
+                };
+                self.encode_op(encoder, op)?;
+                encoder.encode_tree(&mut self.rotation, 2, ((3 - k) % 3) as u64)?;
+                if op == Op::Join {
+                    self.join
+                        .encode_delta(encoder, u64::from(w), u64::from(u))?;
+                }
+                self.gates.attach(gate, w);
+                self.use_vertex(w);
+                return Ok(());
+            }
+            if !self.gates.is_near(triangle) {
+                break;
+            }
+            self.encode_op(encoder, Op::Skip)?;
+            self.gates.pop();
+        }
+        self.encode_op(encoder, Op::New)?;
+        for index in triangle {
+            self.new
+                .encode_delta(encoder, u64::from(index), self.next_vertex)?;
+            self.use_vertex(index);
+        }
+        self.gates.add_new(triangle);
+        Ok(())
+    }
+    fn decode_triangle<R: Read>(
+        &mut self,
+        decoder: &mut RangeDecoder<R>,
+    ) -> Result<[IndexType; 3], TMFImportError> {
+        fn to_index(index: u64) -> Result<IndexType, TMFImportError> {
+            IndexType::try_from(index).map_err(|_| {
+                TMFImportError::IO(std::io::Error::other(
+                    "Decoded index exceeds the range of index type.",
+                ))
+            })
+        }
+        fn no_gate() -> TMFImportError {
+            TMFImportError::IO(std::io::Error::other(
+                "Connectivity operation refers to a nonexistent gate.",
+            ))
+        }
+        loop {
+            let op = self.decode_op(decoder)?;
+            match op {
+                Op::Skip => {
+                    self.gates.pop().ok_or_else(no_gate)?;
+                }
+                Op::New => {
+                    let mut triangle = [0; 3];
+                    for index in &mut triangle {
+                        *index = to_index(self.new.decode_delta(decoder, self.next_vertex)?)?;
+                        self.use_vertex(*index);
+                    }
+                    self.gates.add_new(triangle);
+                    return Ok(triangle);
+                }
+                Op::Create | Op::Left | Op::Right | Op::Join => {
+                    let gate = self.gates.pop().ok_or_else(no_gate)?;
+                    let (u, v) = gate;
+                    let rotation = decoder.decode_tree(&mut self.rotation, 2)? as usize;
+                    if rotation >= 3 {
+                        return Err(TMFImportError::IO(std::io::Error::other(
+                            "Invalid triangle rotation.",
+                        )));
+                    }
+                    let w = match op {
+                        Op::Create => to_index(self.next_vertex)?,
+                        Op::Left => self.gates.left(v).ok_or_else(no_gate)?,
+                        Op::Right => self.gates.right(u).ok_or_else(no_gate)?,
+                        _ => to_index(self.join.decode_delta(decoder, u64::from(u))?)?,
+                    };
+                    self.gates.attach(gate, w);
+                    self.use_vertex(w);
+                    return Ok(rotate([v, u, w], rotation));
+                }
+            }
+        }
+    }
+}
+/// Saves *triangles* as a sequence of operations traversing the mesh. Length of *triangles* must be divisible by 3.
+pub(crate) fn save_triangles_eb<W: Write>(
+    triangles: &[IndexType],
+    writer: &mut W,
+) -> std::io::Result<()> {
+    debug_assert_eq!(triangles.len() % 3, 0);
+    writer.write_all(&((triangles.len() / 3) as u64).to_le_bytes())?;
+    let mut encoder = RangeEncoder::new(writer);
+    let mut traversal = Traversal::new();
+    for triangle in triangles.chunks_exact(3) {
+        traversal.encode_triangle(&mut encoder, [triangle[0], triangle[1], triangle[2]])?;
+    }
+    encoder.finish()
+}
+pub(crate) fn read_triangles_eb<R: Read>(
+    mut src: R,
+    data: &mut Vec<IndexType>,
+) -> Result<(), TMFImportError> {
+    let count = src.read_u64()?;
+    if count > (MAX_SEG_SIZE / 3) as u64 {
+        return Err(TMFImportError::SegmentTooLong);
+    }
+    let mut decoder = RangeDecoder::new(src)?;
+    let mut traversal = Traversal::new();
+    crate::reserve_declared(data, (count as usize).saturating_mul(3));
+    for _ in 0..count {
+        data.extend(traversal.decode_triangle(&mut decoder)?);
+    }
+    Ok(())
+}
+/// Returns positions of indices in *triangles*, ordered so that triangles follow a traversal of the mesh, and each triangle is rotated to begin at the gate it is attached to.
+pub(crate) fn traversal_order(triangles: &[IndexType]) -> Box<[usize]> {
+    let tri_count = triangles.len() / 3;
+    let mut edges: HashMap<Edge, Vec<usize>> = HashMap::with_capacity(triangles.len());
+    for (corner, index) in triangles.iter().enumerate().take(tri_count * 3) {
+        let next = triangles[(corner / 3) * 3 + (corner + 1) % 3];
+        edges.entry((*index, next)).or_default().push(corner);
+    }
+    let mut visited = vec![false; tri_count];
+    let mut order = Vec::with_capacity(tri_count * 3);
+    let mut gates = Gates::default();
+    for start in 0..tri_count {
+        if visited[start] {
+            continue;
+        }
+        visited[start] = true;
+        order.extend((start * 3)..(start * 3 + 3));
+        let triangle = [
+            triangles[start * 3],
+            triangles[start * 3 + 1],
+            triangles[start * 3 + 2],
+        ];
+        gates.add_new(triangle);
+        while let Some((u, v)) = gates.pop() {
+            let Some(corner) = edges
+                .get(&(v, u))
+                .and_then(|corners| corners.iter().find(|corner| !visited[**corner / 3]))
+            else {
+                continue;
+            };
+            let base = (corner / 3) * 3;
+            visited[corner / 3] = true;
+            let corners = [corner % 3, (corner + 1) % 3, (corner + 2) % 3].map(|k| base + k);
+            order.extend(corners);
+            gates.attach((u, v), triangles[corners[2]]);
+        }
+    }
+    order.into()
+}
+#[cfg(test)]
+fn roundtrip(triangles: &[IndexType]) -> usize {
+    let mut compressed = Vec::new();
+    save_triangles_eb(triangles, &mut compressed).unwrap();
+    let mut r_triangles = Vec::new();
+    read_triangles_eb(&compressed[..], &mut r_triangles).unwrap();
+    assert_eq!(triangles, r_triangles);
+    compressed.len()
+}
+#[test]
+#[cfg(feature = "obj_import")]
+fn rw_susan_connectivity() {
+    let (tmf_mesh, _) = crate::susan();
+    let triangles = tmf_mesh.get_vertex_triangles().unwrap();
+    // Any triangle order must survive, even if it is not stored efficiently.
+    roundtrip(triangles);
+    let order = traversal_order(triangles);
+    let reordered: Box<[_]> = order.iter().map(|corner| triangles[*corner]).collect();
+    let (reordered, _) =
+        crate::utilis::optimize_triangle_indices(&reordered, tmf_mesh.get_vertices().unwrap());
+    let size = roundtrip(&reordered);
+    let bits_per_triangle = (size * 8) as f64 / (reordered.len() / 3) as f64;
+    assert!(
+        bits_per_triangle < 4.0,
+        "Reordered mesh used {bits_per_triangle} bits per triangle."
+    );
+}
+#[test]
+fn rw_non_manifold_connectivity() {
+    // Fan of triangles sharing one edge, duplicated and flipped triangles, and a disconnected triangle.
+    let triangles: Vec<IndexType> = vec![
+        0, 1, 2, 1, 0, 3, 1, 0, 4, 0, 1, 5, 0, 1, 2, 2, 1, 0, 100, 7, 3, 2, 3, 4, 4, 3, 2,
+    ];
+    roundtrip(&triangles);
+}
@@ -0,0 +1,369 @@
+use crate::read_extension::ReadExt;
+use crate::tmf::{shared_triangle_types, CompressionType, EncodedSegment, SectionType};
+use crate::tmf_importer::{read_string, TMFImportContext};
+use crate::{
+    FloatType, IndexType, TMFImportError, Tangent, Vector2, Vector3, Vector4, MAX_SEG_SIZE,
+};
+use std::borrow::Borrow;
+use std::io::Read;
+/// Amount of bytes at the beginning of each segment read to find its element count and precision. Enough to hold the longest custom data name, followed by the longest header.
+const PREFIX_LEN: u64 = 1 + u8::MAX as u64 + 64;
+/// Information about a .tmf file, returned by [`inspect`].
+#[derive(Debug, Clone)]
+pub struct TMFFileInfo {
+    /// Major version of format used to save the file.
+    pub major: u16,
+    /// Minor version of format used to save the file.
+    pub minor: u16,
+    /// Major version of format reader required to read the file.
+    pub min_major: u16,
+    /// Minor version of format reader required to read the file.
+    pub min_minor: u16,
+    /// Meshes in this file, in order they are saved in.
+    pub meshes: Vec<TMFMeshInfo>,
+}
+/// Information about a mesh within a .tmf file.
+#[derive(Debug, Clone)]
+pub struct TMFMeshInfo {
+    /// Name of the mesh.
+    pub name: String,
+    /// Segments of this mesh, in order they are saved in.
+    pub segments: Vec<TMFSegmentInfo>,
+}
+impl TMFMeshInfo {
+    /// Returns the total size of data of all segments of this mesh, in bytes.
+    pub fn byte_size(&self) -> u64 {
+        self.segments.iter().map(|seg| seg.byte_size).sum()
+    }
+    /// Returns sizes of arrays of this mesh, once it is loaded.
+    pub fn sizes(&self) -> TMFMeshSizes {
+        TMFMeshSizes::of_segments(&self.segments)
+    }
+}
+/// Sizes of arrays of a mesh, allowing buffers to be allocated before the mesh is decoded into them (see [`crate::TMFMeshBuffers`]).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TMFMeshSizes {
+    /// Amount of vertices.
+    pub vertices: usize,
+    /// Amount of vertex triangle indices.
+    pub vertex_triangles: usize,
+    /// Amount of normals.
+    pub normals: usize,
+    /// Amount of normal triangle indices.
+    pub normal_triangles: usize,
+    /// Amount of UVs.
+    pub uvs: usize,
+    /// Amount of UV triangle indices.
+    pub uv_triangles: usize,
+    /// Amount of tangents.
+    pub tangents: usize,
+    /// Amount of tangent triangle indices.
+    pub tangent_triangles: usize,
+}
+impl TMFMeshSizes {
+    pub(crate) fn of_segments<S: Borrow<TMFSegmentInfo>>(
+        segments: impl IntoIterator<Item = S>,
+    ) -> Self {
+        let mut sizes = Self::default();
+        let mut omitted = Vec::new();
+        for seg in segments {
+            let seg = seg.borrow();
+            if seg.compression_type == CompressionType::Omitted {
+                omitted.push(seg.segment_type);
+                continue;
+            }
+            let count = seg.element_count.unwrap_or(0).min(MAX_SEG_SIZE as u64) as usize;
+            let seg_types = match seg.segment_type {
+                SectionType::SharedTriangleSegment => &seg.shared_types[..],
+                _ => std::slice::from_ref(&seg.segment_type),
+            };
+            for seg_type in seg_types {
+                if let Some(size) = sizes.size_mut(*seg_type) {
+                    *size = size.saturating_add(count);
+                }
+            }
+        }
+        // Omitted triangles index all elements of their data array.
+        for seg_type in omitted {
+            let count = match seg_type {
+                SectionType::VertexTriangleSegment => sizes.vertices,
+                SectionType::NormalTriangleSegment => sizes.normals,
+                SectionType::UvTriangleSegment => sizes.uvs,
+                SectionType::TangentTriangleSegment => sizes.tangents,
+                _ => 0,
+            };
+            if let Some(size) = sizes.size_mut(seg_type) {
+                *size = size.saturating_add(count);
+            }
+        }
+        sizes
+    }
+    /// Returns the size of array stored in segments of *seg_type*, or `None` if its size is not tracked.
+    fn size_mut(&mut self, seg_type: SectionType) -> Option<&mut usize> {
+        Some(match seg_type {
+            SectionType::VertexSegment => &mut self.vertices,
+            SectionType::VertexTriangleSegment => &mut self.vertex_triangles,
+            SectionType::NormalSegment => &mut self.normals,
+            SectionType::NormalTriangleSegment => &mut self.normal_triangles,
+            SectionType::UvSegment => &mut self.uvs,
+            SectionType::UvTriangleSegment => &mut self.uv_triangles,
+            SectionType::TangentSegment => &mut self.tangents,
+            SectionType::TangentTriangleSegment => &mut self.tangent_triangles,
+            _ => return None,
+        })
+    }
+}
+/// Information about a segment of a mesh.
+#[derive(Debug, Clone)]
+pub struct TMFSegmentInfo {
+    /// Type of data stored in this segment.
+    pub segment_type: SectionType,
+    /// How data in this segment is compressed.
+    pub compression_type: CompressionType,
+    /// Name of custom data stored in this segment, if it is a custom data segment.
+    pub custom_name: Option<String>,
+    /// Types of triangles sharing this segment, if it is a shared triangle segment.
+    pub shared_types: Vec<SectionType>,
+    /// Amount of elements (vertices, normals, indices, etc.) in this segment. `None` if it is not stored in the segment (for example because the segment is omitted).
+    pub element_count: Option<u64>,
+    /// Size in bits of each component of saved elements. Empty if elements are not saved as fixed size values.
+    pub precision_bits: Vec<u8>,
+    /// Size of segment data in bytes, excluding the segment header.
+    pub byte_size: u64,
+}
+impl TMFSegmentInfo {
+    /// Reads the segment header and the beginning of its data, skipping the rest of it.
+    fn read<R: Read>(ctx: &TMFImportContext, src: &mut R) -> Result<Self, TMFImportError> {
+        let segment_type = SectionType::from_u16(ctx.segment_type_width().read(src)?);
+        let data_length = ctx.segment_length_width().read(src)?;
+        if data_length > MAX_SEG_SIZE {
+            return Err(TMFImportError::SegmentTooLong);
+        }
+        let compression_type = CompressionType::of_segment(segment_type, src.read_u8()?)?;
+        let _checksum = ctx.read_checksum(src)?;
+        let mut data_src = src.take(data_length as u64);
+        let mut prefix = Vec::new();
+        (&mut data_src).take(PREFIX_LEN).read_to_end(&mut prefix)?;
+        let skipped = std::io::copy(&mut data_src, &mut std::io::sink())?;
+        if prefix.len() as u64 + skipped < data_length as u64 {
+            return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+        }
+        Ok(Self::from_prefix(
+            segment_type,
+            compression_type,
+            data_length as u64,
+            &prefix,
+            ctx,
+        ))
+    }
+    /// Size in bytes of mesh data decoded from this segment, or `None` if its element count is not stored in the segment.
+    pub(crate) fn decoded_size(&self) -> Option<u64> {
+        use std::mem::size_of;
+        let element_size = match self.segment_type {
+            SectionType::VertexSegment | SectionType::NormalSegment => size_of::<Vector3>(),
+            SectionType::UvSegment => size_of::<Vector2>(),
+            SectionType::TangentSegment => size_of::<Tangent>(),
+            SectionType::CustomFloatSegment => size_of::<FloatType>(),
+            SectionType::CustomColorRGBASegment => size_of::<Vector4>(),
+            // Shared indices are copied into each kind of triangles sharing them.
+            SectionType::SharedTriangleSegment => {
+                size_of::<IndexType>() * self.shared_types.len().max(1)
+            }
+            SectionType::Invalid => return None,
+            _ => size_of::<IndexType>(),
+        };
+        self.element_count
+            .map(|count| count.saturating_mul(element_size as u64))
+    }
+    pub(crate) fn from_encoded(seg: &EncodedSegment<'_>, ctx: &TMFImportContext) -> Self {
+        let data = seg.data();
+        Self::from_prefix(
+            seg.seg_type(),
+            seg.compresion_type(),
+            data.len() as u64,
+            data,
+            ctx,
+        )
+    }
+    /// Creates information about a segment, using *prefix* - the beginning of its data.
+    fn from_prefix(
+        segment_type: SectionType,
+        compression_type: CompressionType,
+        byte_size: u64,
+        prefix: &[u8],
+        ctx: &TMFImportContext,
+    ) -> Self {
+        let mut info = Self {
+            segment_type,
+            compression_type,
+            custom_name: None,
+            shared_types: Vec::new(),
+            element_count: None,
+            precision_bits: Vec::new(),
+            byte_size,
+        };
+        // Segments too short to contain their header are reported without element count.
+        let _ = info.read_prefix(prefix, ctx);
+        info
+    }
+    fn read_prefix(&mut self, mut prefix: &[u8], ctx: &TMFImportContext) -> std::io::Result<()> {
+        let src = &mut prefix;
+        if self.compression_type == CompressionType::Omitted {
+            return Ok(());
+        }
+        match self.segment_type {
+            SectionType::CustomIndexSegment
+            | SectionType::CustomIntegerSegment
+            | SectionType::CustomFloatSegment
+            | SectionType::CustomColorRGBASegment => {
+                let mut name = vec![0; src.read_u8()? as usize];
+                src.read_exact(&mut name)?;
+                self.custom_name = Some(String::from_utf8_lossy(&name).into_owned());
+            }
+            SectionType::SharedTriangleSegment => {
+                self.shared_types = shared_triangle_types(src.read_u8()?).into_vec();
+            }
+            _ => (),
+        }
+        match (self.segment_type, self.compression_type) {
+            (_, CompressionType::Sequence) => {
+                let _start = src.read_u64()?;
+                self.element_count = Some(src.read_u64()?);
+            }
+            (SectionType::VertexSegment, CompressionType::None) => {
+                self.element_count = Some(src.read_u64()?);
+                // Bounding box
+                for _ in 0..6 {
+                    src.read_f64()?;
+                }
+                let mut precision = [0; 3];
+                src.read_exact(&mut precision)?;
+                self.precision_bits = precision.into();
+            }
+            (SectionType::VertexSegment, CompressionType::Parallelogram)
+            | (_, CompressionType::RangeCoded) => self.element_count = Some(src.read_u64()?),
+            (_, CompressionType::Lossless) => {
+                self.element_count = Some(src.read_u64()?);
+                // Size of saved floats
+                self.precision_bits = vec![src.read_u8()?];
+            }
+            (_, CompressionType::Edgebreaker) => {
+                self.element_count = Some(src.read_u64()?.saturating_mul(3));
+            }
+            (SectionType::NormalSegment | SectionType::TangentSegment, _)
+            | (SectionType::CustomFloatSegment, _)
+            | (SectionType::CustomColorRGBASegment, _) => {
+                self.element_count = Some(src.read_u64()?);
+                if self.segment_type == SectionType::CustomFloatSegment {
+                    // Minimum and maximum value
+                    src.read_f64()?;
+                    src.read_f64()?;
+                }
+                self.precision_bits = vec![src.read_u8()?];
+            }
+            (SectionType::UvSegment, _) => {
+                self.precision_bits = vec![src.read_u8()?];
+                self.element_count = Some(src.read_u64()?);
+            }
+            (SectionType::Invalid, _) => (),
+            // Triangles and custom indices
+            _ => {
+                self.precision_bits = vec![src.read_u8()?];
+                self.element_count = Some(src.read_u64()?);
+                ctx.read_traingle_min(src)?;
+            }
+        }
+        Ok(())
+    }
+}
+/// Reads information about a .tmf file and all of its meshes, without decoding mesh data. Useful for tracking sizes of saved data.
+/// # Example
+/// ```
+/// # use std::fs::File;
+/// let file = File::open("testing/susan.tmf").expect("Could not open .tmf file");
+/// let info = tmf::inspect(file).expect("Could not read .tmf file!");
+/// for mesh in &info.meshes {
+///     println!("{}: {} bytes", mesh.name, mesh.byte_size());
+///     for segment in &mesh.segments {
+///         println!(
+///             "    {:?} {:?}: {} bytes",
+///             segment.segment_type, segment.compression_type, segment.byte_size
+///         );
+///     }
+/// }
+/// ```
+/// # Errors
+/// Returns: an IO error if it occurs, `NotTMFFile` if not tmf file, `NewerVersionRequired` if a newer importer is required for importing the file, and other errors for malformed tmf files.
+pub fn inspect<R: Read>(src: R) -> Result<TMFFileInfo, TMFImportError> {
+    inspect_file(src).map_err(TMFImportError::located)
+}
+fn inspect_file<R: Read>(mut src: R) -> Result<TMFFileInfo, TMFImportError> {
+    let (ctx, header) = runtime_agnostic_block_on!(TMFImportContext::read_header(&mut src))?;
+    let mut meshes = Vec::with_capacity((u16::MAX as usize).min(header.mesh_count as usize));
+    for _ in 0..header.mesh_count {
+        let name = read_string(&mut src)?;
+        let segment_count = src.read_u16()?;
+        let segments = (0..segment_count)
+            .map(|_| TMFSegmentInfo::read(&ctx, &mut src))
+            .collect::<Result<_, _>>()?;
+        meshes.push(TMFMeshInfo { name, segments });
+    }
+    Ok(TMFFileInfo {
+        major: header.major,
+        minor: header.minor,
+        min_major: header.min_major,
+        min_minor: header.min_minor,
+        meshes,
+    })
+}
+#[cfg(test)]
+#[test]
+#[cfg(feature = "obj_import")]
+fn inspect_susan() {
+    use crate::{IndexType, TMFPrecisionInfo};
+    let (mut tmf_mesh, name) = crate::susan();
+    let indices: [IndexType; 4] = [0, 1, 2, 3];
+    tmf_mesh
+        .add_custom_data(indices[..].into(), "indices")
+        .unwrap();
+    let mut out = Vec::new();
+    tmf_mesh
+        .write_tmf_one(&mut out, &TMFPrecisionInfo::default(), name.as_str())
+        .unwrap();
+    let info = inspect(&out[..]).unwrap();
+    assert_eq!(
+        (info.major, info.minor),
+        (crate::TMF_MAJOR, crate::TMF_MINOR)
+    );
+    assert_eq!(info.meshes.len(), 1);
+    let mesh = &info.meshes[0];
+    assert_eq!(mesh.name, name);
+    let header_size = crate::tmf_exporter::header_size_of(&out)
+        + std::mem::size_of::<u16>() * 2
+        + name.len()
+        + mesh.segments.len() * 6;
+    assert_eq!(mesh.byte_size() as usize + header_size, out.len());
+    let find = |seg_type| {
+        mesh.segments
+            .iter()
+            .filter(move |seg| seg.segment_type == seg_type)
+    };
+    // Data may be split into multiple segments.
+    let count = |seg_type| -> u64 { find(seg_type).map(|seg| seg.element_count.unwrap()).sum() };
+    assert_eq!(
+        count(SectionType::VertexSegment),
+        tmf_mesh.get_vertices().unwrap().len() as u64
+    );
+    assert!(find(SectionType::VertexSegment).all(|seg| seg.precision_bits.len() == 3));
+    assert_eq!(
+        count(SectionType::NormalSegment),
+        tmf_mesh.get_normals().unwrap().len() as u64
+    );
+    assert_eq!(
+        count(SectionType::UvSegment),
+        tmf_mesh.get_uvs().unwrap().len() as u64
+    );
+    let custom = find(SectionType::CustomIndexSegment).next().unwrap();
+    assert_eq!(custom.custom_name.as_deref(), Some("indices"));
+    assert_eq!(custom.element_count, Some(4));
+}
@@ -24,18 +24,26 @@ macro_rules! runtime_agnostic_block_on {
         }
     }};
 }
+mod checksum;
 #[doc(hidden)]
 pub mod custom_data;
-#[allow(dead_code)]
+mod edgebreaker;
+mod inspect;
+mod lossless;
+mod lz77;
 mod material;
+mod mesh_buffers;
+mod mesh_ref;
 #[cfg(feature = "model_importer")]
 mod model_importer;
 mod normals;
 #[cfg(feature = "obj_import")]
 mod obj;
+mod range_coder;
 mod read_extension;
 #[allow(dead_code)]
 mod reorder_triangles;
+mod report;
 #[doc(hidden)]
 pub mod tangents;
 mod tmf;
@@ -49,16 +57,27 @@ mod utilis;
 mod uv;
 mod verify;
 mod vertices;
-// Unfinished
-#[allow(dead_code)]
-mod lz77;
 
 const TMF_MAJOR: u16 = 0;
-const TMF_MINOR: u16 = 2;
+const TMF_MINOR: u16 = 3;
 const MIN_TMF_MAJOR: u16 = 0;
 const MIN_TMF_MINOR: u16 = 2;
+/// Minimum minor version of reader required to read files with header flags. Files requiring older readers have no flags.
+const FLAGS_TMF_MINOR: u16 = 3;
+/// Minimum minor version of reader required to read files with segment checksums, which are enabled by [`CHECKSUMS_FLAG`].
+const CHECKSUM_TMF_MINOR: u16 = FLAGS_TMF_MINOR;
+/// Bit of header flags, set if segments of the file have checksums.
+const CHECKSUMS_FLAG: u8 = 1;
 pub(crate) const MAX_SEG_SIZE: usize = 0x80_00_00_00; //Standard
                                                       //pub(crate) const MAX_SEG_SIZE: usize = 2_00_00;// for fuzzing!
+/// Most elements reserved up front for a segment being decoded. Element counts stored in files are not trusted, so arrays of bigger segments only grow as their elements are decoded.
+pub(crate) const MAX_SEG_RESERVE: usize = 0x1_00_00;
+/// Reserves space for *count* elements a segment claims to store, but no more than [`MAX_SEG_RESERVE`].
+pub(crate) fn reserve_declared<T>(data: &mut Vec<T>, count: usize) {
+    data.reserve(count.min(MAX_SEG_RESERVE));
+}
+/// Marks the end of a table of contents, placed after all meshes of a file.
+pub(crate) const TOC_MAGIC: [u8; 4] = *b"TMFC";
 /// Index type used for representing triangle indices.
 #[cfg(not(any(feature = "long_indices", feature = "short_indices")))]
 pub type IndexType = u32;
@@ -79,19 +98,34 @@ pub type Vector4 = (FloatType, FloatType, FloatType, FloatType);
 pub type Vector3 = (FloatType, FloatType, FloatType);
 /// Type used for representing 2d floating-point vectors
 pub type Vector2 = (FloatType, FloatType);
-use crate::custom_data::CustomDataSegment;
 #[doc(inline)]
-pub use crate::custom_data::{CustomData, DataSegmentError};
+pub use crate::custom_data::{CustomData, CustomDataSegment, DataSegmentError};
+#[doc(inline)]
+pub use crate::edgebreaker::ConnectivityMode;
 //#[doc(inline)]
 //use crate::material::MaterialInfo;
 #[doc(inline)]
+pub use crate::inspect::{inspect, TMFFileInfo, TMFMeshInfo, TMFMeshSizes, TMFSegmentInfo};
+#[doc(inline)]
+pub use crate::mesh_buffers::TMFMeshBuffers;
+#[doc(inline)]
+pub use crate::mesh_ref::TMFMeshRef;
+#[doc(inline)]
 pub use crate::normals::NormalPrecisionMode;
 #[doc(inline)]
+pub use crate::report::{EncodeReport, MeshEncodeReport, SegmentEncodeReport};
+#[doc(inline)]
 pub use crate::tangents::*;
 #[doc(inline)]
+pub use crate::tmf::{CompressionType, SectionType, TMFRawSegment};
+#[doc(inline)]
+pub use crate::tmf_exporter::TMFWriter;
+#[doc(inline)]
+pub use crate::tmf_importer::{TMFFile, TMFPartialImport, TMFReader};
+#[doc(inline)]
 pub use crate::uv::UvPrecisionMode;
 #[doc(inline)]
-pub use crate::vertices::VertexPrecisionMode;
+pub use crate::vertices::{VertexPrecisionMode, VertexPredictionMode};
 use std::io::{Read, Write};
 use thiserror::Error;
 #[doc(inline)]
@@ -103,7 +137,7 @@ lazy_static::lazy_static! {
 }
 /// Settings for saving of a TMF mesh.
 pub struct TMFPrecisionInfo {
-    /// How much can the position of any vertex deviate, as a portion of the shortest edge in the model.
+    /// How much can the position of any vertex deviate, as a portion of the shortest edge in the model, or in units of the model.
     pub vertex_precision: VertexPrecisionMode,
     /// How much can normal angle deviate, as an angle in degrees.
     pub normal_precision: NormalPrecisionMode,
@@ -111,6 +145,14 @@ pub struct TMFPrecisionInfo {
     pub uv_prec: crate::UvPrecisionMode,
     /// How much can saved tangents deviate
     pub tangent_prec: TangentPrecisionMode,
+    /// How triangle connectivity is stored.
+    pub connectivity_mode: ConnectivityMode,
+    /// Should positions of vertices be predicted from already saved vertices.
+    pub vertex_prediction: VertexPredictionMode,
+    /// Should each segment be saved with a CRC-32 checksum, allowing readers to detect corrupted data. Files with checksums can't be read by readers older than 0.3.
+    pub checksums: bool,
+    /// Should each mesh be checked with [`TMFMesh::verify`] before it is saved. Invalid meshes are then rejected with [`TMFExportError::InvalidMesh`], instead of being saved as invalid data, or causing a panic.
+    pub verify: bool,
 }
 impl Default for TMFPrecisionInfo {
     /// Returns the default, middle-ground settings for saving meshes. Should be indistinguishable by human eye, but the LOD may be not enough for some rare cases (eg. procedural generation).
@@ -120,9 +162,124 @@ impl Default for TMFPrecisionInfo {
             normal_precision: NormalPrecisionMode::default(),
             uv_prec: crate::UvPrecisionMode::default(),
             tangent_prec: TangentPrecisionMode::default(),
+            connectivity_mode: ConnectivityMode::default(),
+            vertex_prediction: VertexPredictionMode::default(),
+            checksums: false,
+            verify: false,
         }
     }
 }
+/// Selects which custom data segments are loaded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CustomDataFilter {
+    /// Load all custom data.
+    All,
+    /// Load only custom data with one of those names. An empty list loads no custom data.
+    Named(Vec<String>),
+}
+impl CustomDataFilter {
+    pub(crate) fn selects(&self, name: &[u8]) -> bool {
+        match self {
+            Self::All => true,
+            Self::Named(names) => names.iter().any(|n| n.as_bytes() == name),
+        }
+    }
+}
+/// Settings for loading of a TMF mesh. Segments which are not needed are skipped over without being decoded.
+/// # Example
+/// ```
+/// # use std::fs::File;
+/// # use tmf::{CustomDataFilter, TMFImportOptions, TMFMesh};
+/// let mut file = File::open("testing/susan.tmf").expect("Could not open .tmf file");
+/// // Only load positions of vertices, for example to build a collision mesh.
+/// let options = TMFImportOptions {
+///     normals: false,
+///     uvs: false,
+///     tangents: false,
+///     custom_data: CustomDataFilter::Named(Vec::new()),
+///     ..Default::default()
+/// };
+/// let meshes = TMFMesh::read_tmf_with_options(&mut file, &options).expect("Could not load .tmf mesh!");
+/// ```
+#[derive(Clone, Debug)]
+pub struct TMFImportOptions {
+    /// Should vertices and vertex triangles be loaded.
+    pub vertices: bool,
+    /// Should normals and normal triangles be loaded.
+    pub normals: bool,
+    /// Should UVs and UV triangles be loaded.
+    pub uvs: bool,
+    /// Should tangents and tangent triangles be loaded.
+    pub tangents: bool,
+    /// Which custom data should be loaded.
+    pub custom_data: CustomDataFilter,
+    /// Limits on resources used by the loaded file. No limits are set by default.
+    pub limits: ImportLimits,
+}
+impl Default for TMFImportOptions {
+    /// Returns settings loading all mesh data.
+    fn default() -> Self {
+        Self {
+            vertices: true,
+            normals: true,
+            uvs: true,
+            tangents: true,
+            custom_data: CustomDataFilter::All,
+            limits: ImportLimits::default(),
+        }
+    }
+}
+/// Limits on resources used by a loaded file, allowing untrusted files to be loaded safely. Files exceeding a limit are rejected with [`TMFImportError::LimitExceeded`], before any memory is allocated for data exceeding it.
+/// # Example
+/// ```
+/// # use tmf::{ImportLimits, TMFImportOptions, TMFMesh};
+/// let file = std::fs::read("testing/susan.tmf").expect("Could not read .tmf file");
+/// let options = TMFImportOptions {
+///     limits: ImportLimits {
+///         max_total_bytes: 64 * 1024 * 1024,
+///         max_vertices: 1_000_000,
+///         max_meshes: 16,
+///         ..Default::default()
+///     },
+///     ..Default::default()
+/// };
+/// let meshes = TMFMesh::read_tmf_with_options(&mut &file[..], &options).expect("Could not load .tmf mesh!");
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ImportLimits {
+    /// Maximum total size of data of all loaded segments, in bytes. Skipped segments are not counted.
+    /// Mesh data decoded from loaded segments is counted separately, and must not exceed this limit either, so segments decoding to more data than they store (for example index sequences) are limited too.
+    pub max_total_bytes: u64,
+    /// Maximum amount of vertices in a single mesh.
+    pub max_vertices: u64,
+    /// Maximum amount of meshes in a file.
+    pub max_meshes: u32,
+    /// Maximum amount of segments in a single mesh.
+    pub max_segments: u16,
+}
+impl Default for ImportLimits {
+    /// Returns limits which allow any valid file to be loaded.
+    fn default() -> Self {
+        Self {
+            max_total_bytes: u64::MAX,
+            max_vertices: u64::MAX,
+            max_meshes: u32::MAX,
+            max_segments: u16::MAX,
+        }
+    }
+}
+/// A limit set by [`ImportLimits`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImportLimit {
+    /// [`ImportLimits::max_total_bytes`]
+    TotalBytes,
+    /// [`ImportLimits::max_vertices`]
+    Vertices,
+    /// [`ImportLimits::max_meshes`]
+    Meshes,
+    /// [`ImportLimits::max_segments`]
+    Segments,
+}
 /// Representation of a TMF mesh. Can be loaded from disk, imported from diffrent format, saved to disk, and exported to a diffrent format, or created using special functions. Any mesh created at run time *should* but does not *have to* be checked before saving with [`Self::verify`] call. If the mesh is known to be OK before saving this step can be skipped(even tough it is still advised).
 #[derive(Clone)]
 pub struct TMFMesh {
@@ -136,6 +293,7 @@ pub struct TMFMesh {
     tangent_triangles: Option<Vec<IndexType>>,
     //materials: Option<MaterialInfo>,
     custom_data: Vec<CustomDataSegment>,
+    raw_segments: Vec<TMFRawSegment>,
 }
 impl Default for TMFMesh {
     /// Creates default, empty [`TMFMesh`]. Equivalent to [`TMFMesh::empty`] call.
@@ -640,7 +798,7 @@ impl TMFMesh {
     /// # Errors
     /// Returns a [`TMFIntegrityStatus`] if mesh is invalid.
     pub fn verify(&self) -> std::result::Result<(), TMFIntegrityStatus> {
-        verify::verify_tmf_mesh(self)
+        verify::verify_tmf_mesh(&TMFMeshRef::from(self))
     }
     /// Reads tmf meshes from a .obj file in *reader*
     /// In order to enable triangulation while importing .obj files feature triangulation must be used. It is still highly experimental so read documentation before enabling. It is highly encouraged to just triangulate `.obj` files before importing them.  
@@ -685,18 +843,14 @@ impl TMFMesh {
         match meshes.next() {
             Some(mesh) => {
                 if meshes.next().is_some() {
-                    Err(std::io::Error::new(
-                        std::io::ErrorKind::Other,
+                    Err(std::io::Error::other(
                         "More than one mesh present in .obj file while only one expected.",
                     ))
                 } else {
                     Ok(mesh)
                 }
             }
-            None => Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "No meshes present in .obj file",
-            )),
+            None => Err(std::io::Error::other("No meshes present in .obj file")),
         }
     }
     /// Writes this TMF  mesh to a .obj file.
@@ -747,14 +901,56 @@ impl TMFMesh {
     /// mesh.write_tmf_one(&mut output,&precision_info,"mesh_name").expect("Could not save .tmf mesh!");;
     ///```
     /// # Errors
-    /// Returns IO error if occurs.
+    /// Returns IO error if occurs, or a [`TMFExportError`] describing why a mesh can't be saved.
     pub fn write_tmf_one<W: Write, S: std::borrow::Borrow<str>>(
         &self,
         w: &mut W,
         p_info: &TMFPrecisionInfo,
         name: S,
     ) -> Result<(), TMFExportError> {
-        runtime_agnostic_block_on!(tmf_exporter::write_tmf(&[(self.clone(), name)], w, p_info))
+        runtime_agnostic_block_on!(tmf_exporter::write_tmf(
+            &[(TMFMeshRef::from(self), name)],
+            w,
+            p_info,
+            None
+        ))
+    }
+    /// Version of [`Self::write_tmf_one`] which also returns a report describing how the mesh was saved: chosen precision and size of each segment, and the largest error of saved data.
+    /// Errors are measured by decoding saved segments, so this is slower than [`Self::write_tmf_one`], but data is saved exactly the same way.
+    /// # Example
+    /// ```
+    /// # use tmf::{TMFMesh, TMFPrecisionInfo};
+    /// let mut mesh = TMFMesh::empty();
+    /// mesh.set_vertices([(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (0.0, 1.0, 0.0)]);
+    /// mesh.set_vertex_triangles([0, 1, 2]);
+    /// let mut output = Vec::new();
+    /// let report = mesh
+    ///     .write_tmf_one_with_report(&mut output, &TMFPrecisionInfo::default(), "triangle")
+    ///     .expect("Could not save .tmf mesh!");
+    /// let mesh_report = &report.meshes[0];
+    /// // By default, edges change by at most 10% of the shortest edge, so vertices move much less than that.
+    /// let error = mesh_report.max_position_error().unwrap();
+    /// assert!(error < 0.1);
+    /// for segment in &mesh_report.segments {
+    ///     println!("{:?}: {} bytes", segment.info.segment_type, segment.info.byte_size);
+    /// }
+    /// ```
+    /// # Errors
+    /// Returns IO error if occurs, or a [`TMFExportError`] describing why a mesh can't be saved.
+    pub fn write_tmf_one_with_report<W: Write, S: std::borrow::Borrow<str>>(
+        &self,
+        w: &mut W,
+        p_info: &TMFPrecisionInfo,
+        name: S,
+    ) -> Result<EncodeReport, TMFExportError> {
+        let mut report = EncodeReport::default();
+        runtime_agnostic_block_on!(tmf_exporter::write_tmf(
+            &[(TMFMeshRef::from(self), name)],
+            w,
+            p_info,
+            Some(&mut report)
+        ))?;
+        Ok(report)
     }
     /// Writes a number of TMF meshes into one file.
     /// # Example
@@ -769,13 +965,47 @@ impl TMFMesh {
     /// TMFMesh::write_tmf(&meshes,&mut output, &precision_info).expect("Could not save .tmf file!");
     /// ```
     /// # Errors
-    /// Returns IO error if occurs.
+    /// Returns IO error if occurs, or a [`TMFExportError`] describing why a mesh can't be saved.
     pub fn write_tmf<W: Write, S: std::borrow::Borrow<str>>(
         meshes_names: &[(Self, S)],
         w: &mut W,
         p_info: &TMFPrecisionInfo,
     ) -> Result<(), TMFExportError> {
-        runtime_agnostic_block_on!(tmf_exporter::write_tmf(meshes_names, w, p_info))
+        let meshes_names: Vec<_> = meshes_names
+            .iter()
+            .map(|(mesh, name)| (TMFMeshRef::from(mesh), name.borrow()))
+            .collect();
+        runtime_agnostic_block_on!(tmf_exporter::write_tmf(&meshes_names, w, p_info, None))
+    }
+    /// Version of [`Self::write_tmf`] which also returns a report describing how each mesh was saved. See [`Self::write_tmf_one_with_report`].
+    /// # Example
+    /// ```
+    /// # use tmf::{TMFMesh,TMFPrecisionInfo};
+    /// # let meshes = [(TMFMesh::empty(),"a".to_owned()),(TMFMesh::empty(),"b".to_owned())];
+    /// let mut output = Vec::new();
+    /// let report = TMFMesh::write_tmf_with_report(&meshes, &mut output, &TMFPrecisionInfo::default())
+    ///     .expect("Could not save .tmf file!");
+    /// assert_eq!(report.meshes.len(), meshes.len());
+    /// ```
+    /// # Errors
+    /// Returns IO error if occurs, or a [`TMFExportError`] describing why a mesh can't be saved.
+    pub fn write_tmf_with_report<W: Write, S: std::borrow::Borrow<str>>(
+        meshes_names: &[(Self, S)],
+        w: &mut W,
+        p_info: &TMFPrecisionInfo,
+    ) -> Result<EncodeReport, TMFExportError> {
+        let meshes_names: Vec<_> = meshes_names
+            .iter()
+            .map(|(mesh, name)| (TMFMeshRef::from(mesh), name.borrow()))
+            .collect();
+        let mut report = EncodeReport::default();
+        runtime_agnostic_block_on!(tmf_exporter::write_tmf(
+            &meshes_names,
+            w,
+            p_info,
+            Some(&mut report)
+        ))?;
+        Ok(report)
     }
     /// Creates an empty TMF Mesh(mesh with no data). Equivalent to [`TMFMesh::default`].
     /// # Example
@@ -796,6 +1026,7 @@ impl TMFMesh {
             tangents: None,
             //materials: None,
             custom_data: Vec::new(),
+            raw_segments: Vec::new(),
             tangent_triangles: None,
         }
     }
@@ -818,13 +1049,72 @@ impl TMFMesh {
     /// # Errors
     /// Returns: an IO error if it occurs, `NotTMFFile` if not tmf file, `NewerVersionRequired` if a newer importer is required for importing the file, and other errors for malformed tmf files.
     pub fn read_tmf<R: Read>(reader: &mut R) -> Result<Vec<(Self, String)>, TMFImportError> {
-        crate::tmf_importer::import_sync(reader)
+        crate::tmf_importer::import_sync(reader, &TMFImportOptions::default())
     }
-    /// Async version of [`Self::read_tmf`].
+    /// Reads all meshes from a .tmf file, only loading data selected by *options*.
+    /// # Example
+    /// ```
+    /// # use std::fs::File;
+    /// # use tmf::{TMFImportOptions, TMFMesh};
+    /// let mut file = File::open("testing/susan.tmf").expect("Could not open .tmf file");
+    /// let options = TMFImportOptions {
+    ///     uvs: false,
+    ///     ..Default::default()
+    /// };
+    /// let meshes = TMFMesh::read_tmf_with_options(&mut file, &options).expect("Could not load .tmf mesh!");
+    /// for (mesh, _name) in meshes {
+    ///     assert!(mesh.get_uvs().is_none());
+    /// }
+    /// ```
+    /// # Errors
+    /// Returns: an IO error if it occurs, `NotTMFFile` if not tmf file, `NewerVersionRequired` if a newer importer is required for importing the file, and other errors for malformed tmf files.
+    pub fn read_tmf_with_options<R: Read>(
+        reader: &mut R,
+        options: &TMFImportOptions,
+    ) -> Result<Vec<(Self, String)>, TMFImportError> {
+        crate::tmf_importer::import_sync(reader, options)
+    }
+    /// Reads all meshes from a possibly damaged or truncated .tmf file, recovering as much data as possible instead of failing on the first problem.
+    /// All meshes read before the damage are returned, together with the readable segments of the damaged mesh, and a list of problems found in the file. Segments which can't be decoded are skipped.
+    /// The whole file is read into memory first, but no more of it than a file within [`ImportLimits`] set by *options* could take up.
+    /// # Example
+    /// ```
+    /// # use tmf::{TMFImportOptions, TMFMesh};
+    /// let data = std::fs::read("testing/susan.tmf").expect("Could not read .tmf file");
+    /// // Simulate a download which was cut off.
+    /// let truncated = &data[..data.len() / 2];
+    /// let recovered = TMFMesh::read_tmf_lenient(&mut &truncated[..], &TMFImportOptions::default())
+    ///     .expect("Header is intact");
+    /// assert!(!recovered.is_complete());
+    /// for problem in &recovered.problems {
+    ///     println!("{problem}");
+    /// }
+    /// ```
+    /// # Errors
+    /// Returns: an IO error if the header can't be read, `NotTMFFile` if not tmf file, `NewerVersionRequired` if a newer importer is required for importing the file, and `LimitExceeded` if the file exceeds any limit set by *options*. Exceeded limits are never recovered from.
+    pub fn read_tmf_lenient<R: Read>(
+        reader: &mut R,
+        options: &TMFImportOptions,
+    ) -> Result<TMFPartialImport, TMFImportError> {
+        crate::tmf_importer::import_lenient(reader, options)
+    }
+    /// Reads all meshes from a .tmf file stored in memory. Segments are decoded directly from *data*, without being copied first, so this is the fastest way to read memory-mapped files.
+    /// # Example
+    /// ```
+    /// # use tmf::TMFMesh;
+    /// let data = std::fs::read("testing/susan.tmf").expect("Could not read .tmf file");
+    /// let meshes = TMFMesh::read_tmf_from_slice(&data).expect("Could not load .tmf mesh!");
+    /// ```
+    /// # Errors
+    /// Returns: an IO error if it occurs, `NotTMFFile` if not tmf file, `NewerVersionRequired` if a newer importer is required for importing the file, and other errors for malformed tmf files.
+    pub fn read_tmf_from_slice(data: &[u8]) -> Result<Vec<(Self, String)>, TMFImportError> {
+        crate::tmf_importer::import_from_slice(data, &TMFImportOptions::default())
+    }
+    /// Async version of [`Self::read_tmf`]. Reading from *reader* never blocks, so meshes can be streamed from async files or sockets.
     /// # Example
     /// ```
     /// # use tmf::{TMFImportError,TMFMesh};
-    /// async fn load_meshes_files<R:std::io::Read>(src:&mut [R])->Result<Vec<(TMFMesh,String)>,TMFImportError>{
+    /// async fn load_meshes_files<R:futures::AsyncRead + Unpin>(src:&mut [R])->Result<Vec<(TMFMesh,String)>,TMFImportError>{
     ///     let mut meshes = Vec::new();
     ///     for source in src{
     ///         meshes.extend(TMFMesh::read_tmf_async(source).await?);
@@ -832,10 +1122,35 @@ impl TMFMesh {
     ///     Ok(meshes)
     /// }
     /// ```
-    pub async fn read_tmf_async<R: Read>(
+    /// # Errors
+    /// Returns: an IO error if it occurs, `NotTMFFile` if not tmf file, `NewerVersionRequired` if a newer importer is required for importing the file, and other errors for malformed tmf files.
+    pub async fn read_tmf_async<R: futures::AsyncRead + Unpin>(
         reader: &mut R,
     ) -> Result<Vec<(Self, String)>, TMFImportError> {
-        crate::tmf_importer::TMFImportContext::import(reader).await
+        crate::tmf_importer::TMFImportContext::import_async(reader, &TMFImportOptions::default())
+            .await
+            .map_err(TMFImportError::located)
+    }
+    /// Version of [`Self::read_tmf_async`] reading from a tokio reader.
+    /// # Example
+    /// ```
+    /// # use tmf::{TMFImportError,TMFMesh};
+    /// async fn load_meshes<R: tokio::io::AsyncRead + Unpin>(
+    ///     src: &mut R,
+    /// ) -> Result<Vec<(TMFMesh, String)>, TMFImportError> {
+    ///     TMFMesh::read_tmf_tokio_async(src).await
+    /// }
+    /// ```
+    /// # Errors
+    /// Returns: an IO error if it occurs, `NotTMFFile` if not tmf file, `NewerVersionRequired` if a newer importer is required for importing the file, and other errors for malformed tmf files.
+    #[cfg(feature = "tokio_runtime")]
+    pub async fn read_tmf_tokio_async<R: tokio::io::AsyncRead + Unpin>(
+        reader: &mut R,
+    ) -> Result<Vec<(Self, String)>, TMFImportError> {
+        let reader = crate::tmf_importer::TokioReader(reader);
+        crate::tmf_importer::TMFImportContext::import_async(reader, &TMFImportOptions::default())
+            .await
+            .map_err(TMFImportError::located)
     }
     /// Reads a single mesh from a .tmf file. Returns [`Err`] if no meshes present or more than one mesh present.
     /// ```
@@ -854,19 +1169,23 @@ impl TMFMesh {
         match meshes.next() {
             Some(mesh) => {
                 if meshes.next().is_some() {
-                    Err(TMFImportError::TooManyMeshes)
+                    let second = TMFErrorLocation {
+                        mesh_index: Some(1),
+                        ..Default::default()
+                    };
+                    Err(TMFImportError::TooManyMeshes.at(second))
                 } else {
                     Ok(mesh)
                 }
             }
-            None => Err(TMFImportError::NoMeshes),
+            None => Err(TMFImportError::NoMeshes.located()),
         }
     }
     /// Async version of [`Self::read_tmf_one`].
     /// # Example
     /// ```
     /// # use tmf::{TMFImportError,TMFMesh};
-    /// async fn load_meshes_files<R:std::io::Read>(src:&mut [R])->Result<Vec<(TMFMesh,String)>,TMFImportError>{
+    /// async fn load_meshes_files<R:futures::AsyncRead + Unpin>(src:&mut [R])->Result<Vec<(TMFMesh,String)>,TMFImportError>{
     ///     let mut meshes = Vec::new();
     ///     for source in src{
     ///         meshes.push(TMFMesh::read_tmf_one_async(source).await?);
@@ -874,19 +1193,23 @@ impl TMFMesh {
     ///     Ok(meshes)
     /// }
     /// ```
-    pub async fn read_tmf_one_async<R: Read>(
+    pub async fn read_tmf_one_async<R: futures::AsyncRead + Unpin>(
         reader: &mut R,
     ) -> Result<(Self, String), TMFImportError> {
         let mut meshes = Self::read_tmf_async(reader).await?.into_iter();
         match meshes.next() {
             Some(mesh) => {
                 if meshes.next().is_some() {
-                    Err(TMFImportError::TooManyMeshes)
+                    let second = TMFErrorLocation {
+                        mesh_index: Some(1),
+                        ..Default::default()
+                    };
+                    Err(TMFImportError::TooManyMeshes.at(second))
                 } else {
                     Ok(mesh)
                 }
             }
-            None => Err(TMFImportError::NoMeshes),
+            None => Err(TMFImportError::NoMeshes.located()),
         }
     }
     /// Adds custom data array.
@@ -921,6 +1244,29 @@ impl TMFMesh {
     pub(crate) fn add_custom_data_seg(&mut self, custom_data: CustomDataSegment) {
         self.custom_data.push(custom_data);
     }
+    pub(crate) fn custom_data_segs(&self) -> &[CustomDataSegment] {
+        &self.custom_data
+    }
+    pub(crate) fn add_raw_segment(&mut self, raw: TMFRawSegment) {
+        self.raw_segments.push(raw);
+    }
+    /// Gets segments of types unknown to this version of the library, which were read with the mesh. They are saved unchanged together with the mesh.
+    /// # Example
+    /// ```
+    /// # use tmf::TMFMesh;
+    /// # let mut file = std::fs::File::open("testing/susan.tmf").unwrap();
+    /// let (mesh, _) = TMFMesh::read_tmf_one(&mut file).expect("Could not read .tmf file!");
+    /// for raw in mesh.get_raw_segments() {
+    ///     println!("Unknown segment of type {} ({} bytes)", raw.type_id(), raw.data().len());
+    /// }
+    /// ```
+    pub fn get_raw_segments(&self) -> &[TMFRawSegment] {
+        &self.raw_segments
+    }
+    /// Removes segments of unknown types, for example if changes to the mesh would make data in them invalid.
+    pub fn clear_raw_segments(&mut self) {
+        self.raw_segments.clear();
+    }
     /// Gets a custom data array with name *name*.
     /// Returns `None`, if data not present, or name too long(over 255 bytes).
     /// # Examples
@@ -951,7 +1297,9 @@ impl TMFMesh {
         if bytes.len() > u8::MAX as usize {
             return None;
         }
-        let Ok(bytes_len) = u8::try_from(bytes.len()) else { return None };
+        let Ok(bytes_len) = u8::try_from(bytes.len()) else {
+            return None;
+        };
         for data in &self.custom_data {
             if data.name_len() == bytes_len
                 && bytes == &data.name_bytes()[..(data.name_len() as usize)]
@@ -1107,7 +1455,18 @@ impl TMFMesh {
     }
 }
 /// An enum describing an error that occurred during loading a TMF mesh.  
+/// Errors returned by readers of .tmf files are always [`TMFImportError::Located`], even if little is known about their location, so they should be matched using [`TMFImportError::cause`]:
+/// ```
+/// # use tmf::{TMFImportError, TMFMesh};
+/// let Err(err) = TMFMesh::read_tmf(&mut &b"OBJ"[..]) else {
+///     panic!("Not a .tmf file");
+/// };
+/// assert!(matches!(err.cause(), TMFImportError::NotTMFFile));
+/// assert_eq!(err.location().and_then(|location| location.offset), Some(0));
+/// ```
+/// Before version 0.3, errors were returned without a location, so code matching their variants directly must now match [`TMFImportError::cause`] instead.
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum TMFImportError {
     #[error("tmf data couldn't be read, because an IO error:({0}) occurred.")]
     /// tmf data couldn't be read, because an IO error occurred.
@@ -1136,6 +1495,143 @@ pub enum TMFImportError {
     #[error("This compression type {0} is not supported in this particular context.")]
     /// This compression type is not supported in this particular context
     UnsuportedCompressionType(u8),
+    #[error("Checksum of segment {segment} of mesh \"{mesh}\" does not match its data, so the data is corrupted.")]
+    /// Checksum of a segment does not match its data, so the data is corrupted.
+    ChecksumMismatch {
+        /// Name of the mesh the segment belongs to.
+        mesh: String,
+        /// Index of the segment within the mesh.
+        segment: usize,
+    },
+    #[error("File exceeds the {0:?} limit set by import options.")]
+    /// File exceeds a limit set by [`TMFImportOptions::limits`].
+    LimitExceeded(ImportLimit),
+    #[error("A segment of type {0:?} was decoded as a segment of a different type.")]
+    /// A segment was decoded as a segment of a different type. Indicates a malformed file.
+    UnexpectedSegmentType(SectionType),
+    #[error("Vertices are predicted from vertex triangles of their mesh, but those triangles could not be loaded.")]
+    /// Vertices are predicted from vertex triangles of their mesh, but those triangles could not be loaded, so the vertices can't be reconstructed.
+    MissingPredictionTriangles,
+    #[error("{source} (in {location})")]
+    /// An error which occurred at a known location in the file. See [`TMFImportError::location`].
+    Located {
+        /// Place in the file where the error occurred.
+        location: Box<TMFErrorLocation>,
+        /// The error which occurred.
+        source: Box<TMFImportError>,
+    },
+}
+impl TMFImportError {
+    /// Returns the place in the file where the error occurred. Always present for errors returned by readers, but parts of it may be unknown.
+    /// ```
+    /// # use tmf::TMFMesh;
+    /// // A file with one mesh named "mesh", ending abruptly after its name.
+    /// let file = b"TMF\0\0\x03\0\0\0\x02\0\x01\0\0\0\x04\0mesh";
+    /// let Err(err) = TMFMesh::read_tmf(&mut &file[..]) else {
+    ///     panic!("Truncated file should not be read");
+    /// };
+    /// let location = err.location().expect("Mesh errors are located");
+    /// assert_eq!(location.mesh_index, Some(0));
+    /// assert_eq!(location.mesh_name.as_deref(), Some("mesh"));
+    /// ```
+    pub fn location(&self) -> Option<&TMFErrorLocation> {
+        match self {
+            Self::Located { location, .. } => Some(location),
+            _ => None,
+        }
+    }
+    /// Returns the error stripped of its location. Use it to check what went wrong.
+    pub fn cause(&self) -> &Self {
+        match self {
+            Self::Located { source, .. } => source,
+            _ => self,
+        }
+    }
+    /// Makes sure this error is [`Self::Located`], even if nothing is known about its location. Used before errors are returned to users.
+    pub(crate) fn located(self) -> Self {
+        self.at(TMFErrorLocation::default())
+    }
+    /// Attaches *location* to this error. Parts of the location already known are kept, so inner parts of the reader can locate errors more precisely.
+    pub(crate) fn at(self, location: TMFErrorLocation) -> Self {
+        match self {
+            Self::Located {
+                location: mut known,
+                source,
+            } => {
+                known.fill_from(location);
+                Self::Located {
+                    location: known,
+                    source,
+                }
+            }
+            _ => Self::Located {
+                location: Box::new(location),
+                source: Box::new(self),
+            },
+        }
+    }
+}
+/// Place in a .tmf file at which an import error occurred. Parts of the location which are not known are `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TMFErrorLocation {
+    /// Index of the mesh in the file.
+    pub mesh_index: Option<usize>,
+    /// Name of the mesh, if it was read before the error occurred.
+    pub mesh_name: Option<String>,
+    /// Index of the segment within the mesh.
+    pub segment_index: Option<usize>,
+    /// Type of the segment, if its header was read before the error occurred.
+    pub segment_type: Option<SectionType>,
+    /// Offset of the mesh or segment, counted in bytes from the start of the file header.
+    pub offset: Option<u64>,
+}
+impl TMFErrorLocation {
+    /// Location of an error in a segment of type *seg_type*.
+    pub(crate) fn of_segment_type(seg_type: SectionType) -> Self {
+        Self {
+            segment_type: Some(seg_type),
+            ..Default::default()
+        }
+    }
+    /// Location of an error in data *offset* bytes from the start of the file header, outside of any mesh.
+    pub(crate) fn at_offset(offset: u64) -> Self {
+        Self {
+            offset: Some(offset),
+            ..Default::default()
+        }
+    }
+    fn fill_from(&mut self, other: Self) {
+        self.mesh_index = self.mesh_index.or(other.mesh_index);
+        self.mesh_name = self.mesh_name.take().or(other.mesh_name);
+        self.segment_index = self.segment_index.or(other.segment_index);
+        self.segment_type = self.segment_type.or(other.segment_type);
+        self.offset = self.offset.or(other.offset);
+    }
+}
+impl std::fmt::Display for TMFErrorLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(index) = self.mesh_index {
+            parts.push(format!("mesh {index}"));
+        }
+        if let Some(name) = &self.mesh_name {
+            parts.push(format!("named \"{name}\""));
+        }
+        if let Some(index) = self.segment_index {
+            parts.push(format!("segment {index}"));
+        }
+        if let Some(seg_type) = self.segment_type {
+            parts.push(format!("of type {seg_type:?}"));
+        }
+        if let Some(offset) = self.offset {
+            parts.push(format!("at byte {offset}"));
+        }
+        if parts.is_empty() {
+            write!(f, "unknown location")
+        } else {
+            write!(f, "{}", parts.join(" "))
+        }
+    }
 }
 /// An error which occured when a `TMFMesh` is exported.
 #[derive(Debug, Error)]
@@ -1143,11 +1639,59 @@ pub enum TMFExportError {
     #[error("tmf data couldn't be written, because an IO error:({0}) occurred.")]
     /// tmf data couldn't be written, because an IO error occurred.
     IO(#[from] std::io::Error),
+    #[error(
+        "mesh has an empty vertex array, but vertices can't be saved without at least one of them."
+    )]
+    /// Mesh has an empty vertex array, but vertices can't be saved without at least one of them.
+    NoVertices,
+    #[error(
+        "shortest edge of the mesh is '{0}', but must be finite to calculate vertex precision."
+    )]
+    /// Shortest edge of the mesh, which vertex precision is relative to, is not finite. This happens if vertex triangles contain no full triangle, or if all edges have infinite or NaN length.
+    InfiniteShortestEdge(FloatType),
+    #[error("mesh name is {0} bytes long, but can be at most {max} bytes long.", max = u16::MAX)]
+    /// Mesh name is longer than 65535 bytes, the maximal length which can be saved.
+    NameTooLong(usize),
+    #[error("mesh would be saved in {0} segments, but can be saved in at most {max} segments.", max = u16::MAX)]
+    /// Mesh would be saved in more than 65535 segments, the maximal amount which can be saved.
+    TooManySegments(usize),
+    #[error("mesh is invalid: {0}")]
+    /// Mesh did not pass the integrity check enabled by [`TMFPrecisionInfo::verify`].
+    InvalidMesh(TMFIntegrityStatus),
+    #[error(
+        "vertex precision {0:?} results in quantization steps which are not finite and positive."
+    )]
+    /// Vertex precision results in quantization steps which are not finite and positive, for example an absolute deviation of 0, or a relative one for a mesh with an edge of length 0.
+    InvalidVertexPrecision(VertexPrecisionMode),
+    #[error("vertices have coordinates which are not finite, or span more than {max} along some axis, so they can only be saved losslessly.", max = FloatType::MAX)]
+    /// Vertices have coordinates which are not finite, or span more than [`FloatType::MAX`] along some axis, so their bounding box can't be quantized. Such vertices can only be saved using [`VertexPrecisionMode::LOSSLESS`].
+    VertexSpanNotFinite,
+    #[error("checksums must be enabled for all meshes of a file, or for none of them.")]
+    /// Checksums are enabled for some meshes written by a [`TMFWriter`], but disabled for others. All meshes of a file must be saved with checksums, or all without them.
+    ChecksumsMismatch,
+    #[error("saved data could not be read back to measure its errors: {0}")]
+    /// Saved data could not be read back to measure its errors for an [`EncodeReport`]. Data saved by this crate should always be readable, so this indicates a bug.
+    MeasurementFailed(TMFImportError),
 }
 #[cfg(test)]
 pub(crate) fn init_test_env() {
     std::fs::create_dir_all("target/test_res").unwrap();
 }
+/// Loads the mesh most tests are run on, and its name.
+#[cfg(all(test, feature = "obj_import"))]
+pub(crate) fn susan() -> (TMFMesh, String) {
+    let mut file = std::fs::File::open("testing/susan.obj").unwrap();
+    TMFMesh::read_from_obj_one(&mut file).unwrap()
+}
+/// Saves [`susan`] using default settings, returning the saved file together with the mesh and its name.
+#[cfg(all(test, feature = "obj_import"))]
+pub(crate) fn susan_tmf() -> (Vec<u8>, TMFMesh, String) {
+    let (mesh, name) = susan();
+    let mut out = Vec::new();
+    mesh.write_tmf_one(&mut out, &TMFPrecisionInfo::default(), name.as_str())
+        .unwrap();
+    (out, mesh, name)
+}
 #[cfg(test)]
 mod testing {
     use super::*;
@@ -1208,15 +1752,34 @@ mod testing {
         panic!();
     }
     #[test]
+    #[cfg(feature = "obj_import")]
     fn analize_suzan() {
-        let mut file = std::fs::File::open("target/test_res/susan.tmf").unwrap();
-        let mut out = Vec::new();
-        file.read_to_end(&mut out).unwrap();
-        let r_mesh = runtime_agnostic_block_on!(tmf_importer::TMFImportContext::analize(
-            &mut (&out as &[u8]),
-        ))
-        .unwrap();
-        //todo!();
+        let (out, _, name) = crate::susan_tmf();
+        let info = crate::inspect(&out[..]).unwrap();
+        assert_eq!((info.major, info.minor), (TMF_MAJOR, TMF_MINOR));
+        assert_eq!(info.meshes.len(), 1);
+        let mesh = &info.meshes[0];
+        assert_eq!(mesh.name, name);
+        for seg_type in [
+            SectionType::VertexSegment,
+            SectionType::NormalSegment,
+            SectionType::UvSegment,
+        ] {
+            assert!(mesh.segments.iter().any(|seg| seg.segment_type == seg_type));
+        }
+        let (r_mesh, _) = TMFMesh::read_tmf_one(&mut &out[..]).unwrap();
+        let sizes = mesh.sizes();
+        assert_eq!(sizes.vertices, r_mesh.get_vertices().unwrap().len());
+        assert_eq!(
+            sizes.vertex_triangles,
+            r_mesh.get_vertex_triangles().unwrap().len()
+        );
+        // Headers and data of segments make up the whole file.
+        let mesh_header = 2 * std::mem::size_of::<u16>() + name.len();
+        let segment_header = 2 * std::mem::size_of::<u8>() + std::mem::size_of::<u32>();
+        let headers =
+            tmf_exporter::header_size_of(&out) + mesh_header + mesh.segments.len() * segment_header;
+        assert_eq!(headers as u64 + mesh.byte_size(), out.len() as u64);
     }
     #[test]
     #[cfg(feature = "obj_import")]
@@ -1236,7 +1799,6 @@ mod testing {
         let (r_mesh, name) = TMFMesh::read_tmf_one(&mut (&out as &[u8])).unwrap();
         assert!(name == "Suzanne", "Name should be Suzanne but is {name}");
         r_mesh.verify().unwrap();
-        let mut index = 0;
         assert_eq!(
             tmf_mesh.get_vertex_triangles(),
             r_mesh.get_vertex_triangles()
@@ -1249,6 +1811,68 @@ mod testing {
         let mut out = std::fs::File::create("target/test_res/susan_ftmf.obj").unwrap();
         r_mesh.write_obj_one(&mut out, &name).unwrap();
     }
+    #[test]
+    #[cfg(feature = "obj_import")]
+    fn rw_susan_lossless() {
+        let (mut tmf_mesh, name) = susan();
+        // Values which quantization would not keep exactly.
+        let floats = [0.1, -1e-30, 7.0e20, FloatType::MIN_POSITIVE / 2.0, -0.0];
+        tmf_mesh
+            .add_custom_data(CustomData::lossless_float(&floats), "floats")
+            .unwrap();
+        let prec = TMFPrecisionInfo {
+            vertex_precision: VertexPrecisionMode::LOSSLESS,
+            normal_precision: NormalPrecisionMode::LOSSLESS,
+            uv_prec: UvPrecisionMode::LOSSLESS,
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        tmf_mesh
+            .write_tmf_one(&mut out, &prec, name.as_str())
+            .unwrap();
+        let to_bits = |values: &[FloatType]| values.iter().map(|v| v.to_bits()).collect::<Vec<_>>();
+        let flatten3 = |values: &[Vector3]| {
+            to_bits(
+                &values
+                    .iter()
+                    .flat_map(|v| [v.0, v.1, v.2])
+                    .collect::<Vec<_>>(),
+            )
+        };
+        let flatten2 = |values: &[Vector2]| {
+            to_bits(&values.iter().flat_map(|v| [v.0, v.1]).collect::<Vec<_>>())
+        };
+        let (r_mesh, _) = TMFMesh::read_tmf_one(&mut &out[..]).unwrap();
+        let slice_mesh = TMFMesh::read_tmf_from_slice(&out).unwrap().pop().unwrap().0;
+        for mesh in [&r_mesh, &slice_mesh] {
+            assert_eq!(
+                flatten3(mesh.get_vertices().unwrap()),
+                flatten3(tmf_mesh.get_vertices().unwrap())
+            );
+            assert_eq!(
+                flatten3(mesh.get_normals().unwrap()),
+                flatten3(tmf_mesh.get_normals().unwrap())
+            );
+            assert_eq!(
+                flatten2(mesh.get_uvs().unwrap()),
+                flatten2(tmf_mesh.get_uvs().unwrap())
+            );
+            let (r_floats, r_prec) = mesh
+                .lookup_custom_data("floats")
+                .unwrap()
+                .as_float()
+                .unwrap();
+            assert_eq!(to_bits(r_floats), to_bits(&floats));
+            assert_eq!(r_prec, 0.0);
+        }
+        let info = crate::inspect(&out[..]).unwrap();
+        let lossless = info.meshes[0]
+            .segments
+            .iter()
+            .filter(|seg| seg.compression_type == CompressionType::Lossless)
+            .count();
+        assert_eq!(lossless, 4);
+    }
     #[cfg(all(feature = "triangulation", feature = "obj_import"))]
     fn rw_cube_obj_not_triangulated() {
         init_test_env();
@@ -1269,7 +1893,7 @@ mod testing {
         for mesh_name in meshes {
             let (mesh, name) = mesh_name;
             mesh.verify().unwrap();
-            let mut out = std::fs::File::create(&format!("target/test_res/{}.obj", name)).unwrap();
+            let mut out = std::fs::File::create(format!("target/test_res/{}.obj", name)).unwrap();
             mesh.write_obj_one(&mut out, &name).unwrap();
         }
     }
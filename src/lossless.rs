@@ -0,0 +1,208 @@
+use crate::read_extension::ReadExt;
+use crate::unaligned_rw::{UnalignedRWMode, UnalignedReader, UnalignedWriter};
+use crate::{FloatType, TMFImportError, Vector2, Vector3, MAX_SEG_SIZE};
+use std::io::{Read, Write};
+/// Size of saved floats in bits.
+const FLOAT_BITS: u8 = (std::mem::size_of::<FloatType>() * 8) as u8;
+/// Size of the position and length of a window of bits which differ from the previous value.
+const WINDOW_PREC: UnalignedRWMode = UnalignedRWMode::precision_bits(6);
+/// Most bits read or written at once. Unaligned readers can't read whole 64 bit values.
+const MAX_CHUNK_BITS: u8 = 32;
+/// Elements made of floats, which can be saved without loss.
+pub(crate) trait FloatElement: Sized {
+    /// Amount of floats in an element.
+    const COMPONENTS: usize;
+    fn component(&self, index: usize) -> FloatType;
+    fn from_components(components: &[FloatType]) -> Self;
+}
+impl FloatElement for FloatType {
+    const COMPONENTS: usize = 1;
+    fn component(&self, _index: usize) -> FloatType {
+        *self
+    }
+    fn from_components(components: &[FloatType]) -> Self {
+        components[0]
+    }
+}
+impl FloatElement for Vector2 {
+    const COMPONENTS: usize = 2;
+    fn component(&self, index: usize) -> FloatType {
+        [self.0, self.1][index]
+    }
+    fn from_components(components: &[FloatType]) -> Self {
+        (components[0], components[1])
+    }
+}
+impl FloatElement for Vector3 {
+    const COMPONENTS: usize = 3;
+    fn component(&self, index: usize) -> FloatType {
+        [self.0, self.1, self.2][index]
+    }
+    fn from_components(components: &[FloatType]) -> Self {
+        (components[0], components[1], components[2])
+    }
+}
+/// Bits of a float which differed from the previous value of the same component, and are expected to differ for the next one.
+#[derive(Clone, Copy)]
+struct Window {
+    leading_zeros: u8,
+    len: u8,
+}
+impl Window {
+    fn new(float_bits: u8) -> Self {
+        Self {
+            leading_zeros: 0,
+            len: float_bits,
+        }
+    }
+    fn trailing_zeros(&self, float_bits: u8) -> u8 {
+        float_bits - self.leading_zeros - self.len
+    }
+}
+fn write_bits<W: Write>(
+    writer: &mut UnalignedWriter<W>,
+    bits: u8,
+    value: u64,
+) -> std::io::Result<()> {
+    if bits > MAX_CHUNK_BITS {
+        let low = bits - MAX_CHUNK_BITS;
+        write_bits(writer, MAX_CHUNK_BITS, value >> low)?;
+        write_bits(writer, low, value & ((1 << low) - 1))
+    } else {
+        writer.write_unaligned(UnalignedRWMode::precision_bits(bits), value)
+    }
+}
+fn read_bits<R: Read>(reader: &mut UnalignedReader<R>, bits: u8) -> std::io::Result<u64> {
+    if bits > MAX_CHUNK_BITS {
+        let low = bits - MAX_CHUNK_BITS;
+        let high = read_bits(reader, MAX_CHUNK_BITS)?;
+        Ok((high << low) | read_bits(reader, low)?)
+    } else {
+        reader.read_unaligned(UnalignedRWMode::precision_bits(bits))
+    }
+}
+/// Saves *elements* bit-exactly. Each float is XORed with the same component of the previous element, and only bits which changed are saved.
+pub(crate) fn save_floats<T: FloatElement, W: Write>(
+    elements: &[T],
+    writer: &mut W,
+) -> std::io::Result<()> {
+    writer.write_all(&(elements.len() as u64).to_le_bytes())?;
+    writer.write_all(&[FLOAT_BITS])?;
+    let mut writer = UnalignedWriter::new(writer);
+    let mut previous = vec![0_u64; T::COMPONENTS];
+    let mut windows = vec![Window::new(FLOAT_BITS); T::COMPONENTS];
+    for element in elements {
+        for (index, (previous, window)) in previous.iter_mut().zip(&mut windows).enumerate() {
+            let bits = element.component(index).to_bits() as u64;
+            let xor = bits ^ *previous;
+            *previous = bits;
+            // Same value as previous one.
+            writer.write_bit(xor != 0)?;
+            if xor == 0 {
+                continue;
+            }
+            let leading_zeros = (xor.leading_zeros() - (u64::BITS - FLOAT_BITS as u32)) as u8;
+            let trailing_zeros = xor.trailing_zeros() as u8;
+            let fits = leading_zeros >= window.leading_zeros
+                && trailing_zeros >= window.trailing_zeros(FLOAT_BITS);
+            // Changed bits must be described, unless they fit in the window of the previous value.
+            writer.write_bit(!fits)?;
+            if !fits {
+                *window = Window {
+                    leading_zeros,
+                    len: FLOAT_BITS - leading_zeros - trailing_zeros,
+                };
+                writer.write_unaligned(WINDOW_PREC, window.leading_zeros as u64)?;
+                writer.write_unaligned(WINDOW_PREC, (window.len - 1) as u64)?;
+            }
+            write_bits(
+                &mut writer,
+                window.len,
+                xor >> window.trailing_zeros(FLOAT_BITS),
+            )?;
+        }
+    }
+    writer.flush()
+}
+/// Reads elements saved using [`save_floats`], appending them to *elements*.
+pub(crate) fn read_floats_into<T: FloatElement, R: Read>(
+    reader: &mut R,
+    elements: &mut Vec<T>,
+) -> Result<(), TMFImportError> {
+    let count = reader.read_u64()?;
+    if count > MAX_SEG_SIZE as u64 {
+        return Err(TMFImportError::SegmentTooLong);
+    }
+    let float_bits = reader.read_u8()?;
+    if float_bits != 32 && float_bits != 64 {
+        return Err(TMFImportError::InvalidPrecision(float_bits));
+    }
+    crate::reserve_declared(elements, count as usize);
+    let mut reader = UnalignedReader::new(reader);
+    let mut components = vec![0_u64; T::COMPONENTS];
+    let mut windows = vec![Window::new(float_bits); T::COMPONENTS];
+    let mut floats = vec![0.0; T::COMPONENTS];
+    for _ in 0..count {
+        for ((bits, window), float) in components.iter_mut().zip(&mut windows).zip(&mut floats) {
+            if reader.read_bit()? {
+                if reader.read_bit()? {
+                    let leading_zeros = reader.read_unaligned(WINDOW_PREC)? as u8;
+                    let len = reader.read_unaligned(WINDOW_PREC)? as u8 + 1;
+                    if leading_zeros + len > float_bits {
+                        return Err(TMFImportError::InvalidPrecision(len));
+                    }
+                    *window = Window { leading_zeros, len };
+                }
+                *bits ^= read_bits(&mut reader, window.len)? << window.trailing_zeros(float_bits);
+            }
+            *float = match float_bits {
+                32 => f32::from_bits(*bits as u32) as FloatType,
+                _ => f64::from_bits(*bits) as FloatType,
+            };
+        }
+        elements.push(T::from_components(&floats));
+    }
+    Ok(())
+}
+/// Reads elements saved using [`save_floats`].
+pub(crate) fn read_floats<T: FloatElement, R: Read>(
+    reader: &mut R,
+) -> Result<Box<[T]>, TMFImportError> {
+    let mut elements = Vec::new();
+    read_floats_into(reader, &mut elements)?;
+    Ok(elements.into())
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn rw_floats() {
+        use rand::{thread_rng, Rng};
+        let mut rng = thread_rng();
+        let mut vertices: Vec<Vector3> = (0..0x1000)
+            .map(|index| {
+                let index = index as FloatType;
+                (index * 0.25, rng.gen::<FloatType>() * 10.0 - 5.0, 1.0)
+            })
+            .collect();
+        // Special values must be kept too.
+        vertices.push((FloatType::NAN, -0.0, FloatType::INFINITY));
+        vertices.push((FloatType::MIN_POSITIVE / 2.0, FloatType::MAX, -1.0));
+        let mut out = Vec::new();
+        save_floats(&vertices, &mut out).unwrap();
+        let raw_size = vertices.len() * 3 * std::mem::size_of::<FloatType>();
+        assert!(out.len() < raw_size, "{} >= {raw_size}", out.len());
+        let r_vertices: Box<[Vector3]> = read_floats(&mut &out[..]).unwrap();
+        assert_eq!(r_vertices.len(), vertices.len());
+        for (r_vertex, vertex) in r_vertices.iter().zip(&vertices) {
+            for index in 0..3 {
+                assert_eq!(
+                    r_vertex.component(index).to_bits(),
+                    vertex.component(index).to_bits()
+                );
+            }
+        }
+        // Truncated data is reported, instead of being read as zeros.
+        assert!(read_floats::<Vector3, _>(&mut &out[..out.len() / 2]).is_err());
+    }
+}
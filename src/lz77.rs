@@ -1,64 +1,141 @@
-#![allow(dead_code)]
+use crate::read_extension::ReadExt;
 use crate::unaligned_rw::{UnalignedRWMode, UnalignedReader, UnalignedWriter};
-use crate::IndexType;
-fn calc_sw_size(prec: u8) -> usize {
-    1_usize << prec
+use crate::vertices::calc_prec;
+use crate::{IndexType, TMFImportError, MAX_SEG_SIZE};
+/// Shortest run of values which may be replaced with a back-reference.
+const MIN_MATCH: usize = 3;
+/// Biggest amount of bits used to store the distance of a back-reference. Limits the size of the sliding window.
+const MAX_OFFSET_BITS: u8 = 16;
+/// Biggest amount of bits used to store the length of a back-reference.
+const MAX_LEN_BITS: u8 = 8;
+const MAX_MATCH: usize = MIN_MATCH + (1 << MAX_LEN_BITS) - 1;
+const WINDOW_SIZE: usize = 1 << MAX_OFFSET_BITS;
+const HASH_BITS: u32 = 16;
+/// How many previous occurrences of a hash are checked before giving up on finding a longer match.
+const MAX_CHAIN_DEPTH: usize = 64;
+const NO_POS: usize = usize::MAX;
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token {
+    Literal(u64),
+    /// Back-reference `(offset, len)`, copying `len` values starting `offset` values before the current position.
+    Match(usize, usize),
+}
+fn hash(data: &[u64], pos: usize) -> usize {
+    let mut hash: u64 = 0;
+    for value in &data[pos..(pos + MIN_MATCH)] {
+        hash = (hash ^ value).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    }
+    (hash >> (u64::BITS - HASH_BITS)) as usize
 }
-fn find_longest_match(data: &[u64], pos: usize, scrollback_limit: usize) -> (usize, usize) {
-    let mut best_offset = 0;
-    let mut best_len = 0;
-    let start = if pos < scrollback_limit {
-        0
-    } else {
-        pos - scrollback_limit
-    };
-    for offset in start..pos {
-        let len = match_len(data, offset, pos);
-        if len > best_len {
-            best_offset = pos - offset;
-            best_len = len;
+/// A hash-chain based match finder. Each position is linked to the previous position sharing the hash of its first [`MIN_MATCH`] values,
+/// so only positions which may actually match are visited.
+struct MatchFinder {
+    head: Box<[usize]>,
+    prev: Box<[usize]>,
+}
+impl MatchFinder {
+    fn new(len: usize) -> Self {
+        Self {
+            head: vec![NO_POS; 1 << HASH_BITS].into(),
+            prev: vec![NO_POS; len].into(),
+        }
+    }
+    fn insert(&mut self, data: &[u64], pos: usize) {
+        if pos + MIN_MATCH > data.len() {
+            return;
+        }
+        let hash = hash(data, pos);
+        self.prev[pos] = self.head[hash];
+        self.head[hash] = pos;
+    }
+    /// Returns `(offset, len)` of the longest match for data at `pos`. Must be called before `pos` is inserted.
+    fn find_longest_match(&self, data: &[u64], pos: usize) -> (usize, usize) {
+        let mut best_offset = 0;
+        let mut best_len = 0;
+        if pos + MIN_MATCH > data.len() {
+            return (best_offset, best_len);
+        }
+        let max_len = MAX_MATCH.min(data.len() - pos);
+        let mut candidate = self.head[hash(data, pos)];
+        let mut depth = 0;
+        while candidate != NO_POS && pos - candidate <= WINDOW_SIZE && depth < MAX_CHAIN_DEPTH {
+            // Matches may overlap the current position, the decoder copies values one by one.
+            let len = match_len(data, candidate, pos, max_len);
+            if len > best_len {
+                best_offset = pos - candidate;
+                best_len = len;
+                if len == max_len {
+                    break;
+                }
+            }
+            candidate = self.prev[candidate];
+            depth += 1;
         }
+        (best_offset, best_len)
     }
-    (best_offset, best_len)
 }
-fn match_len(data: &[u64], offset: usize, end: usize) -> usize {
-    let mut offset = offset;
-    let mut pos = end;
+fn match_len(data: &[u64], offset: usize, pos: usize, max_len: usize) -> usize {
     let mut len = 0;
-    while offset < pos && pos < data.len() && data[offset] == data[pos] {
-        offset += 1;
-        pos += 1;
+    while len < max_len && data[offset + len] == data[pos + len] {
         len += 1;
     }
     len
 }
-use crate::vertices::calc_prec;
+fn tokenize(data: &[u64], prec: UnalignedRWMode) -> Vec<Token> {
+    let mut finder = MatchFinder::new(data.len());
+    let mut tokens = Vec::with_capacity(data.len() / 2);
+    // A back-reference is only used when it is smaller than the literals it replaces, even with widest possible offset and length.
+    let match_cost = 1 + MAX_OFFSET_BITS as usize + MAX_LEN_BITS as usize;
+    let literal_cost = 1 + prec.bits() as usize;
+    let mut pos = 0;
+    while pos < data.len() {
+        let (offset, len) = finder.find_longest_match(data, pos);
+        if len >= MIN_MATCH && len * literal_cost > match_cost {
+            tokens.push(Token::Match(offset, len));
+            for curr in pos..(pos + len) {
+                finder.insert(data, curr);
+            }
+            pos += len;
+        } else {
+            tokens.push(Token::Literal(data[pos]));
+            finder.insert(data, pos);
+            pos += 1;
+        }
+    }
+    tokens
+}
 fn save_compressed_array<T, Mapper: Fn(&T) -> u64, W: std::io::Write>(
     data: &[T],
     mapper: Mapper,
     prec: UnalignedRWMode,
     target: &mut W,
 ) -> std::io::Result<()> {
-    println!("data.len():{}", data.len());
     let data: Box<_> = data.iter().map(mapper).collect();
+    let tokens = tokenize(&data, prec);
+    let (max_offset, max_len) = tokens
+        .iter()
+        .fold((1, MIN_MATCH), |acc, token| match token {
+            Token::Match(offset, len) => (acc.0.max(*offset), acc.1.max(*len)),
+            Token::Literal(_) => acc,
+        });
+    // Offsets and lengths are saved with no more bits than needed by the biggest of them.
+    let offset_bits = calc_prec(max_offset - 1);
+    let len_bits = calc_prec(max_len - MIN_MATCH);
+    target.write_all(&[offset_bits, len_bits])?;
+    let offset_prec = UnalignedRWMode::precision_bits(offset_bits);
+    let len_prec = UnalignedRWMode::precision_bits(len_bits);
     let mut writer = UnalignedWriter::new(target);
-    let mut pos = 0;
-    let scrollback_limit = 127;
-    println!("data.len():{}", data.len());
-    while pos < data.len() {
-        let (offset, len) = find_longest_match(&data, pos, scrollback_limit);
-        //TODO:Write
-        let write_raw = offset == 0 || len < 2;
-        writer.write_bit(write_raw)?;
-        if write_raw {
-            println!("({})", data[pos]);
-            writer.write_unaligned(prec, data[pos])?;
-            pos += 1;
-        } else {
-            writer.write_unaligned(prec, len as u64)?;
-            writer.write_unaligned(prec, offset as u64)?;
-            pos += len;
-            println!("({offset}, {len})");
+    for token in tokens {
+        match token {
+            Token::Literal(value) => {
+                writer.write_bit(true)?;
+                writer.write_unaligned(prec, value)?;
+            }
+            Token::Match(offset, len) => {
+                writer.write_bit(false)?;
+                writer.write_unaligned(len_prec, (len - MIN_MATCH) as u64)?;
+                writer.write_unaligned(offset_prec, (offset - 1) as u64)?;
+            }
         }
     }
     writer.flush()
@@ -68,26 +145,34 @@ fn read_compressed_array<T, Mapper: Fn(u64) -> T, R: std::io::Read>(
     prec: UnalignedRWMode,
     length: usize,
     src: &mut R,
-) -> std::io::Result<Box<[T]>> {
+) -> Result<Box<[T]>, TMFImportError> {
+    let offset_bits = src.read_u8()?;
+    if offset_bits >= u64::BITS as u8 {
+        return Err(TMFImportError::InvalidPrecision(offset_bits));
+    }
+    let len_bits = src.read_u8()?;
+    if len_bits >= u64::BITS as u8 {
+        return Err(TMFImportError::InvalidPrecision(len_bits));
+    }
+    let offset_prec = UnalignedRWMode::precision_bits(offset_bits);
+    let len_prec = UnalignedRWMode::precision_bits(len_bits);
     let mut output = Vec::with_capacity(length);
     let mut reader = UnalignedReader::new(src);
     while output.len() < length {
-        //println!("POS: {pos}");
         let is_raw = reader.read_bit()?;
-        let data_or_len = reader.read_unaligned(prec)?;
         if is_raw {
-            output.push(data_or_len);
-            print!("{data_or_len},");
+            output.push(reader.read_unaligned(prec)?);
         } else {
-            // Impossible
-            #[allow(clippy::cast_possible_truncation)]
-            let offset = reader.read_unaligned(prec)? as usize;
-            let Ok(len) = usize::try_from(data_or_len) else { return Err(std::io::Error::new(std::io::ErrorKind::Other, "Data segment length bigger than size of this devices address space.")) };
+            let len = reader.read_unaligned(len_prec)? as usize + MIN_MATCH;
+            let offset = reader.read_unaligned(offset_prec)? as usize + 1;
+            if offset > output.len() || len > length - output.len() {
+                return Err(TMFImportError::IO(std::io::Error::other(
+                    "LZZ back-reference points outside of decoded data.",
+                )));
+            }
             let start = output.len() - offset;
             for i in start..(start + len) {
-                let current = output[i];
-                print!("{current},");
-                output.push(current);
+                output.push(output[i]);
             }
         }
     }
@@ -105,8 +190,30 @@ pub(crate) fn save_triangles_lzz<W: std::io::Write>(
     writer.write_all(&(triangles.len() as u64).to_le_bytes())?;
     writer.write_all(&u64::from(min).to_le_bytes())?;
     let precision = UnalignedRWMode::precision_bits(precision);
-    save_compressed_array(triangles, |index| u64::from(index - min), precision, writer)?;
-    //writer.flush()
+    save_compressed_array(triangles, |index| u64::from(index - min), precision, writer)
+}
+pub(crate) fn read_triangles_lzz<R: std::io::Read>(
+    mut src: R,
+    data: &mut Vec<IndexType>,
+    ctx: &crate::tmf_importer::TMFImportContext,
+) -> Result<(), TMFImportError> {
+    let precision_bits = src.read_u8()?;
+    if precision_bits >= u64::BITS as u8 {
+        return Err(TMFImportError::InvalidPrecision(precision_bits));
+    }
+    let length = src.read_u64()?;
+    let min = ctx.read_traingle_min(&mut src)?;
+    if length > MAX_SEG_SIZE as u64 {
+        return Err(TMFImportError::SegmentTooLong);
+    }
+    let precision = UnalignedRWMode::precision_bits(precision_bits);
+    let indices = read_compressed_array(
+        |index| (index + min) as IndexType,
+        precision,
+        length as usize,
+        &mut src,
+    )?;
+    data.extend_from_slice(&indices);
     Ok(())
 }
 #[test]
@@ -120,14 +227,35 @@ fn encode_ascii_lzz() {
         &mut out,
     )
     .unwrap();
-    let _data = read_compressed_array(
+    let r_data = read_compressed_array(
         |input: u64| input as u8,
         UnalignedRWMode::precision_bits(7),
         data.len(),
         &mut &out[..],
     )
     .unwrap();
-    //panic!("Compressed is:{}%",(out.len() as f64 / data.len() as f64)*100.0);
+    assert_eq!(&data[..], &r_data[..]);
+    assert!(out.len() < data.len());
 }
 #[test]
-fn compress_triangles() {}
+fn compress_triangles() {
+    use crate::tmf_importer::TMFImportContext;
+    // Repetitive index data, similar to what a grid-like mesh produces.
+    let triangles: Vec<IndexType> = (0..10_000)
+        .map(|index| ((index % 6) + (index / 600) * 4) as IndexType)
+        .collect();
+    let max_index = *triangles.iter().max().unwrap() as usize;
+    let mut compressed = Vec::new();
+    save_triangles_lzz(&triangles, max_index, &mut compressed).unwrap();
+    let mut uncompressed = Vec::new();
+    crate::vertices::save_triangles(&triangles, max_index, &mut uncompressed).unwrap();
+    assert!(compressed.len() < uncompressed.len());
+    let mut r_triangles = Vec::new();
+    read_triangles_lzz(
+        &compressed[..],
+        &mut r_triangles,
+        &TMFImportContext::current(),
+    )
+    .unwrap();
+    assert_eq!(triangles, r_triangles);
+}
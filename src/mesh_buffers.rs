@@ -0,0 +1,265 @@
+use crate::inspect::{TMFMeshSizes, TMFSegmentInfo};
+use crate::tmf::{shared_triangle_types, CompressionType, EncodedSegment, SectionType};
+use crate::tmf_importer::{read_triangles_into, TMFImportContext};
+use crate::vertices::PredictedVertices;
+use crate::{IndexType, TMFErrorLocation, TMFImportError, Tangent, Vector2, Vector3};
+/// Caller-owned buffers, which meshes can be decoded into (see [`crate::TMFReader::read_mesh_into`]).
+/// Buffers are cleared before a mesh is decoded into them, but keep their capacity, so reusing them for many meshes avoids repeated allocations.
+/// # Example
+/// ```
+/// # use std::fs::File;
+/// # use tmf::{TMFMeshBuffers, TMFReader};
+/// # fn upload(_:&TMFMeshBuffers){}
+/// let file = File::open("testing/susan.tmf").expect("Could not open .tmf file");
+/// let mut reader = TMFReader::new(file).expect("Could not read .tmf header!");
+/// let mut buffers = TMFMeshBuffers::default();
+/// while let Some(name) = reader.read_mesh_into(&mut buffers) {
+///     let name = name.expect("Could not load .tmf mesh!");
+///     upload(&buffers);
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TMFMeshBuffers {
+    /// Positions of vertices.
+    pub vertices: Vec<Vector3>,
+    /// Indices into vertices, making up triangles.
+    pub vertex_triangles: Vec<IndexType>,
+    /// Normals.
+    pub normals: Vec<Vector3>,
+    /// Indices into normals.
+    pub normal_triangles: Vec<IndexType>,
+    /// UV coordinates.
+    pub uvs: Vec<Vector2>,
+    /// Indices into UV coordinates.
+    pub uv_triangles: Vec<IndexType>,
+    /// Tangents.
+    pub tangents: Vec<Tangent>,
+    /// Indices into tangents.
+    pub tangent_triangles: Vec<IndexType>,
+}
+impl TMFMeshBuffers {
+    /// Creates buffers able to hold arrays of *sizes* without reallocating.
+    /// # Example
+    /// ```
+    /// # use std::fs::File;
+    /// # use tmf::TMFMeshBuffers;
+    /// let file = File::open("testing/susan.tmf").expect("Could not open .tmf file");
+    /// let info = tmf::inspect(file).expect("Could not read .tmf file!");
+    /// let buffers = TMFMeshBuffers::with_sizes(&info.meshes[0].sizes());
+    /// ```
+    pub fn with_sizes(sizes: &TMFMeshSizes) -> Self {
+        let mut buffers = Self::default();
+        buffers.reserve(sizes);
+        buffers
+    }
+    /// Ensures buffers can hold arrays of *sizes* without reallocating.
+    pub fn reserve(&mut self, sizes: &TMFMeshSizes) {
+        self.reserve_up_to(sizes, usize::MAX);
+    }
+    /// Like [`Self::reserve`], but reserves at most *max* elements in each buffer.
+    fn reserve_up_to(&mut self, sizes: &TMFMeshSizes, max: usize) {
+        fn reserve<T>(buffer: &mut Vec<T>, size: usize, max: usize) {
+            buffer.reserve(size.min(max).saturating_sub(buffer.len()));
+        }
+        reserve(&mut self.vertices, sizes.vertices, max);
+        reserve(&mut self.vertex_triangles, sizes.vertex_triangles, max);
+        reserve(&mut self.normals, sizes.normals, max);
+        reserve(&mut self.normal_triangles, sizes.normal_triangles, max);
+        reserve(&mut self.uvs, sizes.uvs, max);
+        reserve(&mut self.uv_triangles, sizes.uv_triangles, max);
+        reserve(&mut self.tangents, sizes.tangents, max);
+        reserve(&mut self.tangent_triangles, sizes.tangent_triangles, max);
+    }
+    /// Removes all data from buffers, keeping their capacity.
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.vertex_triangles.clear();
+        self.normals.clear();
+        self.normal_triangles.clear();
+        self.uvs.clear();
+        self.uv_triangles.clear();
+        self.tangents.clear();
+        self.tangent_triangles.clear();
+    }
+    fn triangles_mut(&mut self, seg_type: SectionType) -> Option<&mut Vec<IndexType>> {
+        match seg_type {
+            SectionType::VertexTriangleSegment => Some(&mut self.vertex_triangles),
+            SectionType::NormalTriangleSegment => Some(&mut self.normal_triangles),
+            SectionType::UvTriangleSegment => Some(&mut self.uv_triangles),
+            SectionType::TangentTriangleSegment => Some(&mut self.tangent_triangles),
+            _ => None,
+        }
+    }
+    /// Reads triangles from *data*, appending them to triangle arrays of all *seg_types*.
+    fn read_triangles(
+        &mut self,
+        seg_types: &[SectionType],
+        data: &[u8],
+        compresion_type: CompressionType,
+        ctx: &TMFImportContext,
+    ) -> Result<(), TMFImportError> {
+        let Some((first, rest)) = seg_types.split_first() else {
+            return Ok(());
+        };
+        // Triangles are decoded into the first array, and then copied into others.
+        let Some(mut indices) = self.triangles_mut(*first).map(std::mem::take) else {
+            return Ok(());
+        };
+        let start = indices.len();
+        let res = read_triangles_into(data, compresion_type, ctx, &mut indices);
+        for seg_type in rest {
+            if let Some(triangles) = self.triangles_mut(*seg_type) {
+                triangles.extend_from_slice(&indices[start..]);
+            }
+        }
+        if let Some(triangles) = self.triangles_mut(*first) {
+            *triangles = indices;
+        }
+        res
+    }
+    /// Decodes mesh made of *segs* into buffers, replacing their previous contents. Custom data is ignored.
+    /// Errors are located using *locate*, which returns location of the segment with given index in *segs*.
+    pub(crate) fn decode(
+        &mut self,
+        segs: &[EncodedSegment<'_>],
+        ctx: &TMFImportContext,
+        locate: impl Fn(usize) -> TMFErrorLocation,
+    ) -> Result<(), TMFImportError> {
+        self.clear();
+        // Sizes are read from the file, so they are not trusted.
+        self.reserve_up_to(
+            &TMFMeshSizes::of_segments(
+                segs.iter()
+                    .map(|seg| TMFSegmentInfo::from_encoded(seg, ctx)),
+            ),
+            crate::MAX_SEG_RESERVE,
+        );
+        // Segments derived from other mesh data, resolved after all other segments are decoded.
+        let mut predicted = Vec::new();
+        let mut omitted = Vec::new();
+        for (index, seg) in segs.iter().enumerate() {
+            self.decode_segment(seg, ctx, &mut predicted, &mut omitted)
+                .map_err(|err| err.at(locate(index)))?;
+        }
+        for vertices in predicted {
+            let vertices = vertices.reconstruct(&self.vertex_triangles, self.vertices.len());
+            self.vertices.extend_from_slice(&vertices);
+        }
+        for seg_type in omitted {
+            let len = match seg_type {
+                SectionType::VertexTriangleSegment => self.vertices.len(),
+                SectionType::NormalTriangleSegment => self.normals.len(),
+                SectionType::UvTriangleSegment => self.uvs.len(),
+                _ => self.tangents.len(),
+            };
+            if let Some(triangles) = self.triangles_mut(seg_type) {
+                triangles.extend((0..len).map(|index| index as IndexType));
+            }
+        }
+        Ok(())
+    }
+    /// Decodes *seg* into buffers. Segments which can only be resolved after all other segments are decoded are pushed to *predicted* and *omitted*.
+    fn decode_segment(
+        &mut self,
+        seg: &EncodedSegment<'_>,
+        ctx: &TMFImportContext,
+        predicted: &mut Vec<PredictedVertices>,
+        omitted: &mut Vec<SectionType>,
+    ) -> Result<(), TMFImportError> {
+        let mut data = seg.data();
+        match (seg.seg_type(), seg.compresion_type()) {
+            (SectionType::VertexSegment, CompressionType::None) => {
+                crate::vertices::read_tmf_vertices_into(&mut data, &mut self.vertices)?
+            }
+            (SectionType::VertexSegment, CompressionType::Parallelogram) => {
+                predicted.push(crate::vertices::read_predicted_vertices(&mut data)?)
+            }
+            (SectionType::VertexSegment, CompressionType::Lossless) => {
+                crate::lossless::read_floats_into(&mut data, &mut self.vertices)?
+            }
+            (SectionType::VertexSegment, compresion_type) => {
+                return Err(TMFImportError::UnsuportedCompressionType(
+                    compresion_type as u8,
+                ))
+            }
+            (SectionType::NormalSegment, CompressionType::Lossless) => {
+                crate::lossless::read_floats_into(&mut data, &mut self.normals)?
+            }
+            (SectionType::UvSegment, CompressionType::Lossless) => {
+                crate::lossless::read_floats_into(&mut data, &mut self.uvs)?
+            }
+            (SectionType::NormalSegment, _) => {
+                crate::normals::read_normal_array_into(&mut data, &mut self.normals)?
+            }
+            (SectionType::UvSegment, _) => crate::uv::read_uvs_into(&mut data, &mut self.uvs)?,
+            (SectionType::TangentSegment, _) => {
+                crate::tangents::read_tangents_into(&mut data, &mut self.tangents)?
+            }
+            (SectionType::SharedTriangleSegment, compresion_type) => {
+                let Some((mask, data)) = data.split_first() else {
+                    return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+                };
+                let seg_types = shared_triangle_types(*mask);
+                self.read_triangles(&seg_types, data, compresion_type, ctx)?;
+            }
+            (seg_type, CompressionType::Omitted) if seg_type.is_triangle() => {
+                omitted.push(seg_type)
+            }
+            (seg_type, compresion_type) if seg_type.is_triangle() => {
+                self.read_triangles(&[seg_type], data, compresion_type, ctx)?
+            }
+            // Custom data is not stored in buffers.
+            _ => (),
+        }
+        Ok(())
+    }
+}
+#[cfg(test)]
+#[test]
+#[cfg(feature = "obj_import")]
+fn read_into_buffers() {
+    use crate::{ConnectivityMode, TMFMesh, TMFPrecisionInfo, TMFReader, VertexPredictionMode};
+    let (tmf_mesh, name) = crate::susan();
+    let mut unified = tmf_mesh.clone();
+    unified.unify_index_data();
+    let predicted = TMFPrecisionInfo {
+        connectivity_mode: ConnectivityMode::Edgebreaker,
+        vertex_prediction: VertexPredictionMode::Parallelogram,
+        ..Default::default()
+    };
+    let mut buffers = TMFMeshBuffers::default();
+    for prec in [TMFPrecisionInfo::default(), predicted] {
+        for mesh in [&tmf_mesh, &unified] {
+            let mut out = Vec::new();
+            mesh.write_tmf_one(&mut out, &prec, name.as_str()).unwrap();
+            let (expected, _) = TMFMesh::read_tmf_one(&mut &out[..]).unwrap();
+            let sizes = crate::inspect(&out[..]).unwrap().meshes[0].sizes();
+            let mut reader = TMFReader::new(&out[..]).unwrap();
+            assert_eq!(reader.read_mesh_into(&mut buffers).unwrap().unwrap(), name);
+            assert!(reader.read_mesh_into(&mut buffers).is_none());
+            assert_eq!(Some(&buffers.vertices[..]), expected.get_vertices());
+            assert_eq!(
+                Some(&buffers.vertex_triangles[..]),
+                expected.get_vertex_triangles()
+            );
+            assert_eq!(Some(&buffers.normals[..]), expected.get_normals());
+            assert_eq!(
+                Some(&buffers.normal_triangles[..]),
+                expected.get_normal_triangles()
+            );
+            assert_eq!(Some(&buffers.uvs[..]), expected.get_uvs());
+            assert_eq!(Some(&buffers.uv_triangles[..]), expected.get_uv_triangles());
+            assert_eq!(sizes.vertices, buffers.vertices.len());
+            assert_eq!(sizes.vertex_triangles, buffers.vertex_triangles.len());
+            assert_eq!(sizes.normals, buffers.normals.len());
+            assert_eq!(sizes.normal_triangles, buffers.normal_triangles.len());
+            assert_eq!(sizes.uvs, buffers.uvs.len());
+            assert_eq!(sizes.uv_triangles, buffers.uv_triangles.len());
+            // Decoding the same mesh again does not need to allocate.
+            let vertices = buffers.vertices.as_ptr();
+            let mut reader = TMFReader::new(&out[..]).unwrap();
+            reader.read_mesh_into(&mut buffers).unwrap().unwrap();
+            assert_eq!(vertices, buffers.vertices.as_ptr());
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use crate::custom_data::CustomDataSegment;
+use crate::{
+    IndexType, TMFExportError, TMFIntegrityStatus, TMFMesh, TMFPrecisionInfo, TMFRawSegment,
+    Tangent, Vector2, Vector3,
+};
+use std::io::Write;
+/// Mesh made of borrowed data, which can be saved without copying it into a [`TMFMesh`] first.
+/// # Example
+/// ```
+/// # use tmf::{TMFMeshRef, TMFPrecisionInfo};
+/// let vertices = [(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (0.0, 1.0, 0.0)];
+/// let triangles = [0, 1, 2];
+/// let mesh = TMFMeshRef::new()
+///     .with_vertices(&vertices)
+///     .with_vertex_triangles(&triangles);
+/// let mut out = Vec::new();
+/// mesh.write_tmf_one(&mut out, &TMFPrecisionInfo::default(), "triangle")
+///     .expect("Could not save .tmf mesh!");
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TMFMeshRef<'a> {
+    vertices: Option<&'a [Vector3]>,
+    vertex_triangles: Option<&'a [IndexType]>,
+    normals: Option<&'a [Vector3]>,
+    normal_triangles: Option<&'a [IndexType]>,
+    uvs: Option<&'a [Vector2]>,
+    uv_triangles: Option<&'a [IndexType]>,
+    tangents: Option<&'a [Tangent]>,
+    tangent_triangles: Option<&'a [IndexType]>,
+    custom_data: &'a [CustomDataSegment],
+    raw_segments: &'a [TMFRawSegment],
+}
+impl<'a> TMFMeshRef<'a> {
+    /// Creates a mesh with no data.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Sets vertex positions of the mesh.
+    #[must_use]
+    pub fn with_vertices(mut self, vertices: &'a [Vector3]) -> Self {
+        self.vertices = Some(vertices);
+        self
+    }
+    /// Sets vertex triangles of the mesh.
+    #[must_use]
+    pub fn with_vertex_triangles(mut self, triangles: &'a [IndexType]) -> Self {
+        self.vertex_triangles = Some(triangles);
+        self
+    }
+    /// Sets normals of the mesh.
+    #[must_use]
+    pub fn with_normals(mut self, normals: &'a [Vector3]) -> Self {
+        self.normals = Some(normals);
+        self
+    }
+    /// Sets normal triangles of the mesh.
+    #[must_use]
+    pub fn with_normal_triangles(mut self, triangles: &'a [IndexType]) -> Self {
+        self.normal_triangles = Some(triangles);
+        self
+    }
+    /// Sets UV coordinates of the mesh.
+    #[must_use]
+    pub fn with_uvs(mut self, uvs: &'a [Vector2]) -> Self {
+        self.uvs = Some(uvs);
+        self
+    }
+    /// Sets UV triangles of the mesh.
+    #[must_use]
+    pub fn with_uv_triangles(mut self, triangles: &'a [IndexType]) -> Self {
+        self.uv_triangles = Some(triangles);
+        self
+    }
+    /// Sets tangents of the mesh.
+    #[must_use]
+    pub fn with_tangents(mut self, tangents: &'a [Tangent]) -> Self {
+        self.tangents = Some(tangents);
+        self
+    }
+    /// Sets tangent triangles of the mesh.
+    #[must_use]
+    pub fn with_tangent_triangles(mut self, triangles: &'a [IndexType]) -> Self {
+        self.tangent_triangles = Some(triangles);
+        self
+    }
+    /// Sets custom data of the mesh. See [`TMFMesh::add_custom_data`].
+    /// # Example
+    /// ```
+    /// # use tmf::{CustomData, CustomDataSegment, TMFMeshRef, TMFPrecisionInfo};
+    /// let vertices = [(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (0.0, 1.0, 0.0)];
+    /// let weights = [0.2, 0.5, 1.0];
+    /// let custom_data = [CustomDataSegment::new(CustomData::from(&weights[..]), "weights")
+    ///     .expect("Could not create custom data!")];
+    /// let mesh = TMFMeshRef::new()
+    ///     .with_vertices(&vertices)
+    ///     .with_custom_data(&custom_data);
+    /// let mut out = Vec::new();
+    /// mesh.write_tmf_one(&mut out, &TMFPrecisionInfo::default(), "weighted")
+    ///     .expect("Could not save .tmf mesh!");
+    /// ```
+    #[must_use]
+    pub fn with_custom_data(mut self, custom_data: &'a [CustomDataSegment]) -> Self {
+        self.custom_data = custom_data;
+        self
+    }
+    /// Sets segments of unknown types, saved unchanged with the mesh. See [`TMFMesh::get_raw_segments`].
+    #[must_use]
+    pub fn with_raw_segments(mut self, raw_segments: &'a [TMFRawSegment]) -> Self {
+        self.raw_segments = raw_segments;
+        self
+    }
+    /// Gets the vertex array of the mesh.
+    pub fn get_vertices(&self) -> Option<&'a [Vector3]> {
+        self.vertices
+    }
+    /// Gets the vertex triangle index array of the mesh.
+    pub fn get_vertex_triangles(&self) -> Option<&'a [IndexType]> {
+        self.vertex_triangles
+    }
+    /// Gets the normal array of the mesh.
+    pub fn get_normals(&self) -> Option<&'a [Vector3]> {
+        self.normals
+    }
+    /// Gets the normal triangle index array of the mesh.
+    pub fn get_normal_triangles(&self) -> Option<&'a [IndexType]> {
+        self.normal_triangles
+    }
+    /// Gets the UV array of the mesh.
+    pub fn get_uvs(&self) -> Option<&'a [Vector2]> {
+        self.uvs
+    }
+    /// Gets the UV triangle index array of the mesh.
+    pub fn get_uv_triangles(&self) -> Option<&'a [IndexType]> {
+        self.uv_triangles
+    }
+    /// Gets the tangent array of the mesh.
+    pub fn get_tangents(&self) -> Option<&'a [Tangent]> {
+        self.tangents
+    }
+    /// Gets the tangent triangle index array of the mesh.
+    pub fn get_tangent_triangles(&self) -> Option<&'a [IndexType]> {
+        self.tangent_triangles
+    }
+    pub(crate) fn custom_data(&self) -> &'a [CustomDataSegment] {
+        self.custom_data
+    }
+    /// Gets segments of unknown types, saved unchanged with the mesh.
+    pub fn get_raw_segments(&self) -> &'a [TMFRawSegment] {
+        self.raw_segments
+    }
+    /// Checks if mesh is valid and can be saved. See [`TMFMesh::verify`].
+    /// # Errors
+    /// Returns a [`TMFIntegrityStatus`] if mesh is invalid.
+    pub fn verify(&self) -> Result<(), TMFIntegrityStatus> {
+        crate::verify::verify_tmf_mesh(self)
+    }
+    /// Writes this mesh to a .tmf file with a single mesh named *name*. See [`TMFMesh::write_tmf_one`].
+    /// # Errors
+    /// Returns IO error if it occurs, or a [`TMFExportError`] describing why the mesh can't be saved.
+    pub fn write_tmf_one<W: Write>(
+        &self,
+        w: &mut W,
+        p_info: &TMFPrecisionInfo,
+        name: &str,
+    ) -> Result<(), TMFExportError> {
+        runtime_agnostic_block_on!(crate::tmf_exporter::write_tmf(
+            &[(*self, name)],
+            w,
+            p_info,
+            None
+        ))
+    }
+}
+impl<'a> From<&'a TMFMesh> for TMFMeshRef<'a> {
+    fn from(mesh: &'a TMFMesh) -> Self {
+        Self {
+            vertices: mesh.get_vertices(),
+            vertex_triangles: mesh.get_vertex_triangles(),
+            normals: mesh.get_normals(),
+            normal_triangles: mesh.get_normal_triangles(),
+            uvs: mesh.get_uvs(),
+            uv_triangles: mesh.get_uv_triangles(),
+            tangents: mesh.get_tangents(),
+            tangent_triangles: mesh.get_tangent_triangles(),
+            custom_data: mesh.custom_data_segs(),
+            raw_segments: mesh.get_raw_segments(),
+        }
+    }
+}
+#[cfg(test)]
+#[test]
+#[cfg(feature = "obj_import")]
+fn write_borrowed_mesh() {
+    use crate::CustomData;
+    let (tmf_mesh, name) = crate::susan();
+    let vertices = tmf_mesh.get_vertices().unwrap().to_vec();
+    let vertex_triangles = tmf_mesh.get_vertex_triangles().unwrap().to_vec();
+    let prec = TMFPrecisionInfo::default();
+    let mut out = Vec::new();
+    TMFMeshRef::new()
+        .with_vertices(&vertices)
+        .with_vertex_triangles(&vertex_triangles)
+        .write_tmf_one(&mut out, &prec, &name)
+        .unwrap();
+    let (read, r_name) = TMFMesh::read_tmf_one(&mut &out[..]).unwrap();
+    assert_eq!(r_name, name);
+    assert_eq!(read.get_vertex_triangles(), Some(&vertex_triangles[..]));
+    assert!(read.get_normals().is_none());
+    // Saving a borrowed mesh gives the same result as saving the mesh itself.
+    let mut owned = Vec::new();
+    tmf_mesh
+        .write_tmf_one(&mut owned, &prec, name.as_str())
+        .unwrap();
+    let mut borrowed = Vec::new();
+    TMFMeshRef::from(&tmf_mesh)
+        .write_tmf_one(&mut borrowed, &prec, &name)
+        .unwrap();
+    assert_eq!(owned, borrowed);
+    // Custom data is saved like custom data of the mesh itself.
+    let floats = [0.25, 0.5, 0.75];
+    let custom_data = [CustomDataSegment::new(CustomData::from(&floats[..]), "floats").unwrap()];
+    let mut with_custom = tmf_mesh.clone();
+    with_custom
+        .add_custom_data(CustomData::from(&floats[..]), "floats")
+        .unwrap();
+    let mut owned = Vec::new();
+    with_custom
+        .write_tmf_one(&mut owned, &prec, name.as_str())
+        .unwrap();
+    let mut borrowed = Vec::new();
+    TMFMeshRef::from(&tmf_mesh)
+        .with_custom_data(&custom_data)
+        .write_tmf_one(&mut borrowed, &prec, &name)
+        .unwrap();
+    assert_eq!(owned, borrowed);
+}
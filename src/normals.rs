@@ -9,8 +9,34 @@ use std::io::{Read, Write};
 
 #[derive(Clone, Copy, PartialEq)]
 /// Setting dictating how much can any normal in a model deviate, expressed as an angle.
-pub struct NormalPrecisionMode(u8);
+pub struct NormalPrecisionMode {
+    precision: NormalPrecision,
+}
+/// Kinds of [`NormalPrecisionMode`].
+#[derive(Clone, Copy, PartialEq)]
+enum NormalPrecision {
+    /// Normals are quantized, with this many bits per component.
+    Bits(u8),
+    /// Normals are saved exactly, as floats.
+    Lossless,
+}
+/// Most bits normals can be saved with.
+const MAX_NORMAL_BITS: u8 = u64::BITS as u8 - 1;
 impl NormalPrecisionMode {
+    /// Normals are saved exactly, so they are read back bit for bit the same.
+    /// ```
+    /// # use tmf::{NormalPrecisionMode, TMFPrecisionInfo};
+    /// let prec = TMFPrecisionInfo {
+    ///     normal_precision: NormalPrecisionMode::LOSSLESS,
+    ///     ..TMFPrecisionInfo::default()
+    /// };
+    /// ```
+    pub const LOSSLESS: Self = Self {
+        precision: NormalPrecision::Lossless,
+    };
+    pub(crate) fn is_lossless(&self) -> bool {
+        self.precision == NormalPrecision::Lossless
+    }
     /// Creates [`NormalPrecisionMode`] from maximal allowed deviation angle in degrees, for radians use [`Self::from_rad_dev`]
     /// ```
     /// # use tmf::NormalPrecisionMode;
@@ -22,8 +48,8 @@ impl NormalPrecisionMode {
     /// let dev_5_deg = NormalPrecisionMode::from_deg_dev(5.0);
     /// ```
     pub fn from_deg_dev(deg: FloatType) -> Self {
-        let prec = ((90.0 / deg).log2().ceil() as u8).max(1);
-        Self(prec)
+        let prec = ((90.0 / deg).log2().ceil() as u8).clamp(1, MAX_NORMAL_BITS);
+        Self::from_bits(prec)
     }
     /// Creates NormalPrecisionMode from maximal allowed deviation angle in radians, for degrees use [`Self::from_deg_dev`]
     /// ```
@@ -34,14 +60,20 @@ impl NormalPrecisionMode {
     /// let dev_0_point_05_rad = NormalPrecisionMode::from_rad_dev(0.05);
     /// ```
     pub fn from_rad_dev(rad: FloatType) -> Self {
-        let prec = (FRAC_PI_2 / rad).log2().ceil() as u8;
-        Self(prec)
+        let prec = ((FRAC_PI_2 / rad).log2().ceil() as u8).min(MAX_NORMAL_BITS);
+        Self::from_bits(prec)
     }
+    /// Returns the amount of bits each component of a quantized normal is saved with. Lossless normals are not quantized, so they use the most bits quantized normals can have.
     pub(crate) fn bits(&self) -> u8 {
-        self.0
+        match self.precision {
+            NormalPrecision::Bits(bits) => bits,
+            NormalPrecision::Lossless => MAX_NORMAL_BITS,
+        }
     }
-    pub(crate) fn from_bits(bits: u8) -> Self {
-        Self(bits)
+    pub(crate) const fn from_bits(bits: u8) -> Self {
+        Self {
+            precision: NormalPrecision::Bits(bits),
+        }
     }
 }
 impl Default for NormalPrecisionMode {
@@ -68,7 +100,7 @@ pub(crate) fn normal_to_encoding(
     normal: Vector3,
     precision: &NormalPrecisionMode,
 ) -> (u64, u64, bool, bool, bool) {
-    let multiplier = ((1 << precision.0) - 1) as FloatType;
+    let multiplier = ((1 << precision.bits()) - 1) as FloatType;
     //Calculate asine
     let xy = (normal.0.abs(), normal.1.abs());
     let xy_mag = (xy.0 * xy.0 + xy.1 * xy.1).sqrt();
@@ -92,13 +124,13 @@ pub(crate) fn normal_from_encoding(
     sz: bool,
     precision: NormalPrecisionMode,
 ) -> Vector3 {
-    if precision.0 == 0 {
+    if precision.bits() == 0 {
         let x = if sx { -1.0 } else { 1.0 };
         let y = if sy { -1.0 } else { 1.0 };
         let z = if sz { -1.0 } else { 1.0 };
         return (x, y, z);
     }
-    let divisor = ((1_u64 << precision.0) - 1) as FloatType;
+    let divisor = ((1_u64 << precision.bits()) - 1) as FloatType;
     //Read raw asine
     let asine = (asine as FloatType) / divisor;
     //Convert asine form 0-1 to 0-tau
@@ -130,7 +162,7 @@ fn save_normal<W: Write>(
     writer: &mut UnalignedWriter<W>,
 ) -> std::io::Result<()> {
     let (asine, z, sx, sy, sz) = normal_to_encoding(normal, &precision);
-    let main_prec = UnalignedRWMode::precision_bits(precision.0);
+    let main_prec = UnalignedRWMode::precision_bits(precision.bits());
 
     writer.write_bit(sx)?;
     writer.write_bit(sy)?;
@@ -145,7 +177,7 @@ fn read_normal<R: Read>(
     precision: NormalPrecisionMode,
     reader: &mut UnalignedReader<R>,
 ) -> std::io::Result<Vector3> {
-    let main_prec = UnalignedRWMode::precision_bits(precision.0);
+    let main_prec = UnalignedRWMode::precision_bits(precision.bits());
     // Get signs of x y z component
     let sx = reader.read_bit()?;
     let sy = reader.read_bit()?;
@@ -160,7 +192,7 @@ pub(crate) fn save_normal_array<W: Write>(
 ) -> std::io::Result<()> {
     let count = (normals.len() as u64).to_le_bytes();
     writer.write_all(&count)?;
-    writer.write_all(&[precision.0])?;
+    writer.write_all(&[precision.bits()])?;
     let mut writer = UnalignedWriter::new(writer);
     for normal in normals {
         save_normal(*normal, precision, &mut writer)?;
@@ -169,6 +201,15 @@ pub(crate) fn save_normal_array<W: Write>(
     Ok(())
 }
 pub(crate) fn read_normal_array<R: Read>(reader: &mut R) -> Result<Box<[Vector3]>, TMFImportError> {
+    let mut normals = Vec::new();
+    read_normal_array_into(reader, &mut normals)?;
+    Ok(normals.into())
+}
+/// Reads normals from *reader*, appending them to *normals*.
+pub(crate) fn read_normal_array_into<R: Read>(
+    reader: &mut R,
+    normals: &mut Vec<Vector3>,
+) -> Result<(), TMFImportError> {
     let count = reader.read_u64()? as usize;
     if count > MAX_SEG_SIZE {
         return Err(TMFImportError::SegmentTooLong);
@@ -177,25 +218,25 @@ pub(crate) fn read_normal_array<R: Read>(reader: &mut R) -> Result<Box<[Vector3]
     if precision >= u64::BITS as u8 {
         return Err(TMFImportError::InvalidPrecision(precision));
     }
-    let precision = NormalPrecisionMode(precision);
+    let precision = NormalPrecisionMode::from_bits(precision);
     let mut reader = UnalignedReader::new(reader);
-    let mut normals = Vec::with_capacity(count);
+    crate::reserve_declared(normals, count);
     for _ in 0..count {
         let normal = read_normal(precision, &mut reader)?;
         normals.push(normal);
     }
-    Ok(normals.into())
+    Ok(())
 }
 #[cfg(test)]
 mod test_normal {
     use super::*;
-    pub const NORM_PREC_HIGH: NormalPrecisionMode = NormalPrecisionMode(13);
+    pub const NORM_PREC_HIGH: NormalPrecisionMode = NormalPrecisionMode::from_bits(13);
     fn dot(a: Vector3, b: Vector3) -> FloatType {
         a.0 * b.0 + a.1 * b.1 + a.2 * b.2
     }
     fn test_save(normal: Vector3) {
         let mut res = Vec::with_capacity(64);
-        let precision = NormalPrecisionMode(14);
+        let precision = NormalPrecisionMode::from_bits(14);
         {
             let mut writter = UnalignedWriter::new(&mut res);
             save_normal(normal, precision, &mut writter).unwrap();
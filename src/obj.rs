@@ -1,6 +1,6 @@
 use crate::model_importer::ModelImporter;
 use crate::{FloatType, IndexType, TMFMesh, Vector2, Vector3};
-use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Write};
+use std::io::{BufReader, BufWriter, Error, Read, Result, Write};
 pub(crate) const SMALL_VEC_CAP: usize = 8;
 fn parse_line(line: Result<String>, oi: &mut ModelImporter) -> Result<Option<(TMFMesh, String)>> {
     let line = line?;
@@ -42,10 +42,7 @@ pub fn read_from_obj<R: Read>(reader: &mut R) -> Result<Vec<(TMFMesh, String)>>
 fn parse_float_type(float: &str) -> Result<FloatType> {
     match float.parse::<FloatType>() {
         Ok(float) => Ok(float),
-        Err(err) => Err(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            err.to_string(),
-        )),
+        Err(err) => Err(std::io::Error::other(err.to_string())),
     }
 }
 fn parse_index(uint: &str) -> IndexType {
@@ -54,10 +51,7 @@ fn parse_index(uint: &str) -> IndexType {
 fn match_split(split: Option<&str>) -> Result<&str> {
     match split {
         Some(beg) => Ok(beg),
-        None => Err(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            "Invalid .obj line",
-        )),
+        None => Err(std::io::Error::other("Invalid .obj line")),
     }
 }
 use smallvec::SmallVec;
@@ -70,10 +64,7 @@ fn load_indices(split: &mut Split<&[char; 2]>) -> Result<(IndexType, IndexType,
     );
     let (i0, i1, i2) = (parse_index(i0), parse_index(i1), parse_index(i2));
     if i0 < 1 || i1 < 1 || i2 < 1 {
-        Err(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            "Invalid index!",
-        ))
+        Err(std::io::Error::other("Invalid index!"))
     } else {
         Ok((i0 - 1, i1 - 1, i2 - 1))
     }
@@ -90,7 +81,7 @@ fn load_face(split: &mut Split<&[char; 2]>, oi: &mut ModelImporter) -> Result<()
     }
     match oi.push_face(&vertex_indices, &uv_indices, &normal_indices) {
         Ok(()) => Ok(()),
-        Err(msg) => Err(Error::new(ErrorKind::Other, msg)),
+        Err(msg) => Err(Error::other(msg)),
     }
 }
 pub fn load_vec3(split: &mut Split<&[char; 2]>) -> Result<Vector3> {
@@ -148,12 +139,10 @@ fn save_obj<W: Write>(
         // If no vertex triangle array, then object is a point cloud, so should not have any other triangle array.
         None => {
             if mesh.get_normal_triangles().is_some() {
-                return Err(Error::new(ErrorKind::Other, "If vertex triangles data is not present, normal triangle data must not be present either!"));
+                return Err(Error::other("If vertex triangles data is not present, normal triangle data must not be present either!"));
             }
             if mesh.get_uv_triangles().is_some() {
-                return Err(Error::new(
-                    ErrorKind::Other,
-                    "If vertex triangles data is not present, uv triangle data must not be present either!",
+                return Err(Error::other("If vertex triangles data is not present, uv triangle data must not be present either!",
                 ));
             }
             return Ok((0, 0, 0));
@@ -162,8 +151,7 @@ fn save_obj<W: Write>(
     // Ensure normal triangle array, if present, has the right length.
     if let Some(normal_triangles) = mesh.get_normal_triangles() {
         if normal_triangles.len() != vert_triangle_len {
-            return Err(Error::new(
-                ErrorKind::Other,
+            return Err(Error::other(
                 "Number of triangles in the vertex triangle and normal triangle array differs.",
             ));
         }
@@ -171,8 +159,7 @@ fn save_obj<W: Write>(
     // Ensure uv triangle array, if present, has the right length.
     if let Some(uv_triangles) = mesh.get_uv_triangles() {
         if uv_triangles.len() != vert_triangle_len {
-            return Err(Error::new(
-                ErrorKind::Other,
+            return Err(Error::other(
                 "Number of triangles in the vertex triangle and uv triangle array differs.",
             ));
         }
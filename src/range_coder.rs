@@ -0,0 +1,297 @@
+use crate::read_extension::ReadExt;
+use crate::{IndexType, TMFImportError, MAX_SEG_SIZE};
+use std::io::{Read, Write};
+/// Amount of bits used to store probability of a bit being 0.
+const PROB_BITS: u32 = 11;
+pub(crate) const PROB_INIT: u16 = 1 << (PROB_BITS - 1);
+/// How fast probabilities adapt to the data. Smaller values adapt faster, but are less precise.
+const MOVE_BITS: u32 = 5;
+const TOP: u32 = 1 << 24;
+/// Amount of bits needed to store bit length of a `u64` (0..=64).
+const LEN_BITS: u32 = 7;
+/// Amount of previous indices checked for a repeat.
+const RECENT_COUNT: usize = 3;
+const RECENT_BITS: u32 = 2;
+/// Adaptive binary range encoder.
+pub(crate) struct RangeEncoder<W: Write> {
+    low: u64,
+    range: u32,
+    cache: u8,
+    cache_size: u64,
+    target: W,
+}
+impl<W: Write> RangeEncoder<W> {
+    pub(crate) fn new(target: W) -> Self {
+        Self {
+            low: 0,
+            range: u32::MAX,
+            cache: 0,
+            cache_size: 1,
+            target,
+        }
+    }
+    fn shift_low(&mut self) -> std::io::Result<()> {
+        if (self.low as u32) < 0xFF00_0000 || (self.low >> 32) != 0 {
+            let carry = (self.low >> 32) as u8;
+            let mut byte = self.cache;
+            while self.cache_size > 0 {
+                self.target.write_all(&[byte.wrapping_add(carry)])?;
+                byte = 0xFF;
+                self.cache_size -= 1;
+            }
+            self.cache = (self.low >> 24) as u8;
+        }
+        self.cache_size += 1;
+        self.low = (self.low & 0x00FF_FFFF) << 8;
+        Ok(())
+    }
+    pub(crate) fn encode_bit(&mut self, prob: &mut u16, bit: bool) -> std::io::Result<()> {
+        let bound = (self.range >> PROB_BITS) * u32::from(*prob);
+        if bit {
+            self.low += u64::from(bound);
+            self.range -= bound;
+            *prob -= *prob >> MOVE_BITS;
+        } else {
+            self.range = bound;
+            *prob += ((1 << PROB_BITS) - *prob) >> MOVE_BITS;
+        }
+        while self.range < TOP {
+            self.range <<= 8;
+            self.shift_low()?;
+        }
+        Ok(())
+    }
+    /// Encodes lowest `bits` bits of `value`, starting with the most significant one, using a binary tree of probabilities.
+    pub(crate) fn encode_tree(
+        &mut self,
+        probs: &mut [u16],
+        bits: u32,
+        value: u64,
+    ) -> std::io::Result<()> {
+        let mut node = 1;
+        for bit in (0..bits).rev() {
+            let bit = (value >> bit) & 1 != 0;
+            self.encode_bit(&mut probs[node], bit)?;
+            node = (node << 1) | usize::from(bit);
+        }
+        Ok(())
+    }
+    pub(crate) fn finish(mut self) -> std::io::Result<()> {
+        for _ in 0..5 {
+            self.shift_low()?;
+        }
+        self.target.flush()
+    }
+}
+/// Adaptive binary range decoder.
+pub(crate) struct RangeDecoder<R: Read> {
+    range: u32,
+    code: u32,
+    src: R,
+}
+impl<R: Read> RangeDecoder<R> {
+    pub(crate) fn new(mut src: R) -> std::io::Result<Self> {
+        // First byte is always 0, since the encoder starts with an empty cache.
+        let mut code = 0;
+        for _ in 0..5 {
+            code = (code << 8) | u32::from(src.read_u8()?);
+        }
+        Ok(Self {
+            range: u32::MAX,
+            code,
+            src,
+        })
+    }
+    pub(crate) fn decode_bit(&mut self, prob: &mut u16) -> std::io::Result<bool> {
+        let bound = (self.range >> PROB_BITS) * u32::from(*prob);
+        let bit = if self.code < bound {
+            self.range = bound;
+            *prob += ((1 << PROB_BITS) - *prob) >> MOVE_BITS;
+            false
+        } else {
+            self.code -= bound;
+            self.range -= bound;
+            *prob -= *prob >> MOVE_BITS;
+            true
+        };
+        while self.range < TOP {
+            self.range <<= 8;
+            self.code = (self.code << 8) | u32::from(self.src.read_u8()?);
+        }
+        Ok(bit)
+    }
+    pub(crate) fn decode_tree(&mut self, probs: &mut [u16], bits: u32) -> std::io::Result<u64> {
+        let mut node = 1;
+        for _ in 0..bits {
+            node = (node << 1) | usize::from(self.decode_bit(&mut probs[node])?);
+        }
+        Ok((node - (1 << bits)) as u64)
+    }
+}
+/// Adaptive model of unsigned values, storing bit length of a value followed by bits below its highest set bit.
+pub(crate) struct ValueModel {
+    len: [u16; 1 << LEN_BITS],
+    /// Probabilities of bits below the highest set bit, for each bit length and bit position.
+    bits: Box<[[u16; u64::BITS as usize]]>,
+}
+impl ValueModel {
+    pub(crate) fn new() -> Self {
+        Self {
+            len: [PROB_INIT; 1 << LEN_BITS],
+            bits: vec![[PROB_INIT; u64::BITS as usize]; u64::BITS as usize + 1].into(),
+        }
+    }
+    pub(crate) fn encode<W: Write>(
+        &mut self,
+        encoder: &mut RangeEncoder<W>,
+        value: u64,
+    ) -> std::io::Result<()> {
+        let len = u64::BITS - value.leading_zeros();
+        encoder.encode_tree(&mut self.len, LEN_BITS, u64::from(len))?;
+        // Highest set bit is implied by the length.
+        for bit in (0..len.saturating_sub(1)).rev() {
+            let prob = &mut self.bits[len as usize][bit as usize];
+            encoder.encode_bit(prob, (value >> bit) & 1 != 0)?;
+        }
+        Ok(())
+    }
+    pub(crate) fn decode<R: Read>(
+        &mut self,
+        decoder: &mut RangeDecoder<R>,
+    ) -> Result<u64, TMFImportError> {
+        let len = decoder.decode_tree(&mut self.len, LEN_BITS)? as u32;
+        if len > u64::BITS {
+            return Err(TMFImportError::InvalidPrecision(len as u8));
+        }
+        let mut value = u64::from(len != 0);
+        for bit in (0..len.saturating_sub(1)).rev() {
+            let prob = &mut self.bits[len as usize][bit as usize];
+            value = (value << 1) | u64::from(decoder.decode_bit(prob)?);
+        }
+        Ok(value)
+    }
+    /// Encodes a signed difference between *value* and *reference*, wrapping around.
+    pub(crate) fn encode_delta<W: Write>(
+        &mut self,
+        encoder: &mut RangeEncoder<W>,
+        value: u64,
+        reference: u64,
+    ) -> std::io::Result<()> {
+        let delta = value.wrapping_sub(reference) as i64;
+        self.encode(encoder, ((delta << 1) ^ (delta >> 63)) as u64)
+    }
+    pub(crate) fn decode_delta<R: Read>(
+        &mut self,
+        decoder: &mut RangeDecoder<R>,
+        reference: u64,
+    ) -> Result<u64, TMFImportError> {
+        let zigzag = self.decode(decoder)?;
+        let delta = ((zigzag >> 1) as i64) ^ -((zigzag & 1) as i64);
+        Ok(reference.wrapping_add(delta as u64))
+    }
+}
+/// Adaptive model of triangle indices. Each index is either a repeat of one of [`RECENT_COUNT`] previous indices,
+/// or a zigzag encoded difference from the previous index. Statistics are kept separately for each position within a triangle.
+struct IndexModel {
+    recent: [u64; RECENT_COUNT],
+    is_repeat: [u16; 3],
+    repeat_slot: [[u16; 1 << RECENT_BITS]; 3],
+    delta: [ValueModel; 3],
+}
+impl IndexModel {
+    fn new() -> Self {
+        Self {
+            recent: [0; RECENT_COUNT],
+            is_repeat: [PROB_INIT; 3],
+            repeat_slot: [[PROB_INIT; 1 << RECENT_BITS]; 3],
+            delta: [ValueModel::new(), ValueModel::new(), ValueModel::new()],
+        }
+    }
+    fn push_recent(&mut self, index: u64) {
+        self.recent.copy_within(0..(RECENT_COUNT - 1), 1);
+        self.recent[0] = index;
+    }
+    fn encode<W: Write>(
+        &mut self,
+        encoder: &mut RangeEncoder<W>,
+        pos: usize,
+        index: u64,
+    ) -> std::io::Result<()> {
+        let slot = self.recent.iter().position(|recent| *recent == index);
+        encoder.encode_bit(&mut self.is_repeat[pos], slot.is_some())?;
+        match slot {
+            Some(slot) => {
+                encoder.encode_tree(&mut self.repeat_slot[pos], RECENT_BITS, slot as u64)?
+            }
+            None => self.delta[pos].encode_delta(encoder, index, self.recent[0])?,
+        }
+        self.push_recent(index);
+        Ok(())
+    }
+    fn decode<R: Read>(
+        &mut self,
+        decoder: &mut RangeDecoder<R>,
+        pos: usize,
+    ) -> Result<u64, TMFImportError> {
+        let index = if decoder.decode_bit(&mut self.is_repeat[pos])? {
+            let slot = decoder.decode_tree(&mut self.repeat_slot[pos], RECENT_BITS)? as usize;
+            *self.recent.get(slot).ok_or_else(|| {
+                std::io::Error::other("Repeated index refers to a nonexistent previous index.")
+            })?
+        } else {
+            self.delta[pos].decode_delta(decoder, self.recent[0])?
+        };
+        self.push_recent(index);
+        Ok(index)
+    }
+}
+/// Saves *triangles* as indices delta coded against previous ones and compressed using an adaptive range coder.
+pub(crate) fn save_triangles_rc<W: Write>(
+    triangles: &[IndexType],
+    writer: &mut W,
+) -> std::io::Result<()> {
+    writer.write_all(&(triangles.len() as u64).to_le_bytes())?;
+    let mut encoder = RangeEncoder::new(writer);
+    let mut model = IndexModel::new();
+    for (pos, index) in triangles.iter().enumerate() {
+        model.encode(&mut encoder, pos % 3, u64::from(*index))?;
+    }
+    encoder.finish()
+}
+pub(crate) fn read_triangles_rc<R: Read>(
+    mut src: R,
+    data: &mut Vec<IndexType>,
+) -> Result<(), TMFImportError> {
+    let length = src.read_u64()?;
+    if length > MAX_SEG_SIZE as u64 {
+        return Err(TMFImportError::SegmentTooLong);
+    }
+    let mut decoder = RangeDecoder::new(src)?;
+    let mut model = IndexModel::new();
+    crate::reserve_declared(data, length as usize);
+    for pos in 0..(length as usize) {
+        let index = model.decode(&mut decoder, pos % 3)?;
+        let index = IndexType::try_from(index)
+            .map_err(|_| std::io::Error::other("Decoded index exceeds the range of index type."))?;
+        data.push(index);
+    }
+    Ok(())
+}
+#[test]
+fn rw_range_coded_triangles() {
+    // Strip-like triangles with a few large jumps, to exercise both repeats and deltas.
+    let mut triangles: Vec<IndexType> = Vec::new();
+    for index in 0..3000 {
+        let base = if index % 500 == 0 { 70_000 } else { 0 };
+        triangles.extend([index + base, index + 1, index + 2].map(|i| i as IndexType));
+    }
+    let max_index = *triangles.iter().max().unwrap() as usize;
+    let mut compressed = Vec::new();
+    save_triangles_rc(&triangles, &mut compressed).unwrap();
+    let mut uncompressed = Vec::new();
+    crate::vertices::save_triangles(&triangles, max_index, &mut uncompressed).unwrap();
+    assert!(compressed.len() < uncompressed.len());
+    let mut r_triangles = Vec::new();
+    read_triangles_rc(&compressed[..], &mut r_triangles).unwrap();
+    assert_eq!(triangles, r_triangles);
+}
@@ -79,7 +79,7 @@ fn read_susan_obj() {
     tmf_mesh.set_normal_triangles(normal_indices);
     tmf_mesh.set_uv_triangles(uv_indices);
     let mut out = std::fs::File::create("target/test_res/suan_reordered_ftmf.obj").unwrap();
-    tmf_mesh.write_obj_one(&mut out, &"SUSAN").unwrap();
+    tmf_mesh.write_obj_one(&mut out, "SUSAN").unwrap();
     let mut out = std::fs::File::create("target/test_res/susan_reordered.tmf").unwrap();
     tmf_mesh
         .write_tmf_one(&mut out, &TMFPrecisionInfo::default(), "SUSAN")
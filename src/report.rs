@@ -0,0 +1,259 @@
+use crate::inspect::TMFSegmentInfo;
+use crate::tmf::{CompressionType, DecodedSegment, EncodedSegment, SectionType};
+use crate::tmf_exporter::EncodeInfo;
+use crate::tmf_importer::TMFImportContext;
+use crate::utilis::{cross, distance, dot, magnitude};
+use crate::{CustomData, CustomDataSegment, FloatType, TMFImportError, Vector3};
+/// Report describing how meshes were saved, returned by [`crate::TMFMesh::write_tmf_with_report`].
+#[derive(Debug, Clone, Default)]
+pub struct EncodeReport {
+    /// Reports of all saved meshes, in order they were saved in.
+    pub meshes: Vec<MeshEncodeReport>,
+}
+/// Report describing how a mesh was saved.
+#[derive(Debug, Clone)]
+pub struct MeshEncodeReport {
+    /// Name of the mesh.
+    pub name: String,
+    /// Reports of segments of this mesh, in order they were saved in.
+    pub segments: Vec<SegmentEncodeReport>,
+}
+impl MeshEncodeReport {
+    /// Returns the total size of data of all segments of this mesh, in bytes.
+    pub fn byte_size(&self) -> u64 {
+        self.segments.iter().map(|seg| seg.info.byte_size).sum()
+    }
+    /// Returns the largest distance between a saved vertex position and the original one, or `None` if the mesh has no vertices.
+    pub fn max_position_error(&self) -> Option<FloatType> {
+        self.max_error(SectionType::VertexSegment)
+    }
+    /// Returns the largest angle between a saved normal and the original one, in radians, or `None` if the mesh has no normals.
+    pub fn max_normal_angle_error(&self) -> Option<FloatType> {
+        self.max_error(SectionType::NormalSegment)
+    }
+    /// Returns the largest distance between a saved UV and the original one, or `None` if the mesh has no UVs.
+    pub fn max_uv_error(&self) -> Option<FloatType> {
+        self.max_error(SectionType::UvSegment)
+    }
+    /// Returns the largest angle between a saved tangent and the original one, in radians, or `None` if the mesh has no tangents.
+    pub fn max_tangent_angle_error(&self) -> Option<FloatType> {
+        self.max_error(SectionType::TangentSegment)
+    }
+    fn max_error(&self, seg_type: SectionType) -> Option<FloatType> {
+        self.segments
+            .iter()
+            .filter(|seg| seg.info.segment_type == seg_type)
+            .filter_map(|seg| seg.max_error)
+            .reduce(FloatType::max)
+    }
+}
+/// Report describing how a segment was saved.
+#[derive(Debug, Clone)]
+pub struct SegmentEncodeReport {
+    /// Type, compression, chosen precision and size of the segment, as they would be reported by [`crate::inspect`].
+    pub info: TMFSegmentInfo,
+    /// Largest error of saved data, measured by decoding the segment. Distance for vertices and UVs, angle in radians for normals and tangents, absolute difference for custom floats and the largest difference of a channel for custom colors.
+    /// `None` for segments with other kinds of data, like triangles or custom indices, which are saved without loss.
+    pub max_error: Option<FloatType>,
+}
+impl SegmentEncodeReport {
+    /// Measures how *source* was saved as *encoded*, using *ei* it was encoded with.
+    pub(crate) fn measure(
+        source: &DecodedSegment<'_>,
+        encoded: &EncodedSegment<'_>,
+        ei: &EncodeInfo<'_>,
+    ) -> Result<Self, TMFImportError> {
+        let ctx = TMFImportContext::current();
+        let info = TMFSegmentInfo::from_encoded(encoded, &ctx);
+        let mut data = encoded.data();
+        let max_error = match source {
+            DecodedSegment::AppendVertex(vertices) => {
+                let decoded = match encoded.compresion_type() {
+                    CompressionType::Parallelogram => {
+                        crate::vertices::read_predicted_vertices(&mut data)?
+                            .reconstruct(ei.vertex_triangles(), 0)
+                    }
+                    CompressionType::Lossless => crate::lossless::read_floats(&mut data)?,
+                    _ => crate::vertices::read_tmf_vertices(&mut data)?,
+                };
+                max_of(vertices, &decoded, |a, b| distance(*a, *b))?
+            }
+            DecodedSegment::AppendNormal(normals) => {
+                let decoded = match encoded.compresion_type() {
+                    CompressionType::Lossless => crate::lossless::read_floats(&mut data)?,
+                    _ => crate::normals::read_normal_array(&mut data)?,
+                };
+                max_of(normals, &decoded, |a, b| angle(*a, *b))?
+            }
+            DecodedSegment::AppendUV(uvs) => {
+                let decoded = match encoded.compresion_type() {
+                    CompressionType::Lossless => crate::lossless::read_floats(&mut data)?,
+                    _ => crate::uv::read_uvs(&mut data)?,
+                };
+                max_of(uvs, &decoded, |a, b| (a.0 - b.0).hypot(a.1 - b.1))?
+            }
+            DecodedSegment::AppendTangent(tangents) => {
+                let decoded = crate::tangents::read_tangents(&mut data)?;
+                max_of(tangents, &decoded, |a, b| angle(a.0, b.0))?
+            }
+            DecodedSegment::AppendCustom(custom) => match custom.custom_data() {
+                CustomData::CustomFloat(floats, _) | CustomData::CustomLosslessFloat(floats) => {
+                    let decoded = read_custom(encoded, &ctx)?;
+                    let decoded = decoded.custom_data().as_float().map(|(floats, _)| floats);
+                    max_of(floats, decoded.unwrap_or_default(), |a, b| (a - b).abs())?
+                }
+                CustomData::CustomColorRGBA(colors, _) => {
+                    let decoded = read_custom(encoded, &ctx)?;
+                    let decoded = decoded
+                        .custom_data()
+                        .as_color_rgba()
+                        .map(|(colors, _)| colors);
+                    max_of(colors, decoded.unwrap_or_default(), |a, b| {
+                        [a.0 - b.0, a.1 - b.1, a.2 - b.2, a.3 - b.3]
+                            .iter()
+                            .fold(0.0, |max, diff| diff.abs().max(max))
+                    })?
+                }
+                _ => None,
+            },
+            _ => None,
+        };
+        Ok(Self { info, max_error })
+    }
+}
+/// Reads back custom data saved as *encoded*.
+fn read_custom(
+    encoded: &EncodedSegment<'_>,
+    ctx: &TMFImportContext,
+) -> Result<CustomDataSegment, TMFImportError> {
+    CustomDataSegment::read(
+        encoded.data(),
+        encoded.seg_type(),
+        encoded.compresion_type(),
+        ctx,
+    )
+}
+/// Returns the largest *error* between elements of *source* and *decoded*, or `None` if there are no elements.
+fn max_of<T>(
+    source: &[T],
+    decoded: &[T],
+    error: impl Fn(&T, &T) -> FloatType,
+) -> Result<Option<FloatType>, TMFImportError> {
+    if source.len() != decoded.len() {
+        return Err(std::io::Error::other("Saved segment has a different element count.").into());
+    }
+    Ok(source
+        .iter()
+        .zip(decoded)
+        .map(|(a, b)| error(a, b))
+        .reduce(FloatType::max))
+}
+/// Angle between *a* and *b*, in radians. Calculated from both the sine and cosine, so it stays precise for small angles.
+fn angle(a: Vector3, b: Vector3) -> FloatType {
+    magnitude(cross(a, b)).atan2(dot(a, b))
+}
+#[cfg(test)]
+#[test]
+#[cfg(feature = "obj_import")]
+fn report_encoding_errors() {
+    use crate::{TMFMesh, TMFPrecisionInfo, VertexPrecisionMode};
+    let (tmf_mesh, name) = crate::susan();
+    let mut prev_error = FloatType::INFINITY;
+    for precision in [0.1, 0.01, 0.001] {
+        let prec = TMFPrecisionInfo {
+            vertex_precision: VertexPrecisionMode::relative(precision),
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        let report = tmf_mesh
+            .write_tmf_one_with_report(&mut out, &prec, name.as_str())
+            .unwrap();
+        let mut plain = Vec::new();
+        tmf_mesh
+            .write_tmf_one(&mut plain, &prec, name.as_str())
+            .unwrap();
+        // Measuring errors does not change the saved data.
+        assert_eq!(out, plain);
+        let mesh = &report.meshes[0];
+        assert_eq!(mesh.name, name);
+        let info = crate::inspect(&out[..]).unwrap();
+        assert_eq!(mesh.byte_size(), info.meshes[0].byte_size());
+        for (seg, expected) in mesh.segments.iter().zip(&info.meshes[0].segments) {
+            assert_eq!(seg.info.segment_type, expected.segment_type);
+            assert_eq!(seg.info.precision_bits, expected.precision_bits);
+            assert_eq!(seg.info.byte_size, expected.byte_size);
+        }
+        // Errors are measured against data which was read back.
+        let (r_mesh, _) = TMFMesh::read_tmf_one(&mut &out[..]).unwrap();
+        let measured = tmf_mesh
+            .get_vertices()
+            .unwrap()
+            .iter()
+            .zip(r_mesh.get_vertices().unwrap())
+            .map(|(a, b)| distance(*a, *b))
+            .fold(0.0, FloatType::max);
+        let error = mesh.max_position_error().unwrap();
+        assert_eq!(error, measured);
+        assert!(error < prev_error);
+        prev_error = error;
+        assert!(mesh.max_normal_angle_error().unwrap() > 0.0);
+        assert!(mesh.max_uv_error().is_some());
+        assert!(mesh.max_tangent_angle_error().is_none());
+    }
+}
+#[cfg(test)]
+#[test]
+#[cfg(feature = "obj_import")]
+fn report_custom_data_errors() {
+    use crate::{TMFMesh, TMFPrecisionInfo, Vector4};
+    let (mut tmf_mesh, name) = crate::susan();
+    let floats: Vec<FloatType> = (0..100).map(|i| (i as FloatType * 0.37).sin()).collect();
+    let colors: Vec<Vector4> = floats.iter().map(|f| (f.abs(), 0.0, 1.0, 1.0)).collect();
+    tmf_mesh
+        .add_custom_data(floats[..].into(), "floats")
+        .unwrap();
+    tmf_mesh
+        .add_custom_data(CustomData::lossless_float(&floats), "exact")
+        .unwrap();
+    tmf_mesh
+        .add_custom_data(colors[..].into(), "colors")
+        .unwrap();
+    let mut out = Vec::new();
+    let report = tmf_mesh
+        .write_tmf_one_with_report(&mut out, &TMFPrecisionInfo::default(), name.as_str())
+        .unwrap();
+    let (r_mesh, _) = TMFMesh::read_tmf_one(&mut &out[..]).unwrap();
+    let error_of = |custom_name: &str| {
+        report.meshes[0]
+            .segments
+            .iter()
+            .find(|seg| seg.info.custom_name.as_deref() == Some(custom_name))
+            .and_then(|seg| seg.max_error)
+            .unwrap()
+    };
+    let (read, _) = r_mesh
+        .lookup_custom_data("floats")
+        .unwrap()
+        .as_float()
+        .unwrap();
+    let measured = floats
+        .iter()
+        .zip(read)
+        .map(|(a, b)| (a - b).abs())
+        .fold(0.0, FloatType::max);
+    assert_eq!(error_of("floats"), measured);
+    assert!(measured > 0.0 && measured <= 0.01);
+    assert_eq!(error_of("exact"), 0.0);
+    let (read, _) = r_mesh
+        .lookup_custom_data("colors")
+        .unwrap()
+        .as_color_rgba()
+        .unwrap();
+    let measured = colors
+        .iter()
+        .zip(read)
+        .map(|(a, b)| (a.0 - b.0).abs())
+        .fold(0.0, FloatType::max);
+    assert_eq!(error_of("colors"), measured);
+    assert!(measured > 0.0 && measured <= 0.01);
+}
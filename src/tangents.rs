@@ -91,6 +91,15 @@ pub(crate) fn save_tangents<W: std::io::Write>(
 pub(crate) fn read_tangents<R: std::io::Read>(
     src: &mut R,
 ) -> Result<Box<[Tangent]>, TMFImportError> {
+    let mut tangents = Vec::new();
+    read_tangents_into(src, &mut tangents)?;
+    Ok(tangents.into())
+}
+/// Reads tangents from *src*, appending them to *tangents*.
+pub(crate) fn read_tangents_into<R: std::io::Read>(
+    src: &mut R,
+    tangents: &mut Vec<Tangent>,
+) -> Result<(), TMFImportError> {
     let count = src.read_u64()?;
     if count > crate::MAX_SEG_SIZE as u64 {
         return Err(TMFImportError::SegmentTooLong);
@@ -102,7 +111,7 @@ pub(crate) fn read_tangents<R: std::io::Read>(
     let mut reader = UnalignedReader::new(src);
     let prec = UnalignedRWMode::precision_bits(bits_prec);
     let tan_prec = TangentPrecisionMode::from_bits(bits_prec);
-    let mut tangents = Vec::with_capacity(count as usize);
+    crate::reserve_declared(tangents, count as usize);
     for _ in 0..count {
         let handeness = reader.read_bit()?;
         let sx = reader.read_bit()?;
@@ -114,7 +123,7 @@ pub(crate) fn read_tangents<R: std::io::Read>(
             asine, z, sx, sy, sz, handeness, tan_prec,
         ));
     }
-    Ok(tangents.into())
+    Ok(())
 }
 #[cfg(test)]
 fn test_tangent(tangent: Tangent, prec: TangentPrecisionMode) -> FloatType {
@@ -152,11 +161,11 @@ fn tangent_rw() {
 #[test]
 fn tangents_rw() {
     let prec = TangentPrecisionMode::default();
-    let src_tangents: Vec<_> = (0..100_000).into_iter().map(|_| rand_tangent()).collect();
+    let src_tangents: Vec<_> = (0..100_000).map(|_| rand_tangent()).collect();
     let mut data = Vec::with_capacity(100_000);
     save_tangents(&src_tangents, prec, &mut data).unwrap();
     let target_tangents = read_tangents(&mut (&data as &[u8])).unwrap();
-    for tangent in target_tangents.into_iter() {
+    for tangent in target_tangents.iter() {
         let degree = test_tangent(*tangent, prec);
         assert!(degree < 5.0);
     }
@@ -171,12 +180,7 @@ fn rw_susan_tmf_tans() {
     let (mut tmf_mesh, name) = TMFMesh::read_from_obj_one(&mut file).unwrap();
     tmf_mesh.verify().unwrap();
     let len = tmf_mesh.get_vertices().unwrap().len();
-    tmf_mesh.set_tangents(
-        (0..len)
-            .into_iter()
-            .map(|_| rand_tangent())
-            .collect::<Vec<_>>(),
-    );
+    tmf_mesh.set_tangents((0..len).map(|_| rand_tangent()).collect::<Vec<_>>());
     assert!(name == "Suzanne", "Name should be Suzanne but is {name}");
     let prec = TMFPrecisionInfo::default();
     let mut out = Vec::new();
@@ -198,11 +202,7 @@ fn rw_susan_tmf_tan_tris() {
     let (mut tmf_mesh, name) = TMFMesh::read_from_obj_one(&mut file).unwrap();
     tmf_mesh.verify().unwrap();
     let len = tmf_mesh.get_vertex_triangles().unwrap().len();
-    tmf_mesh.set_tangent_triangles(
-        (0..(len as crate::IndexType))
-            .into_iter()
-            .collect::<Vec<_>>(),
-    );
+    tmf_mesh.set_tangent_triangles((0..(len as crate::IndexType)).collect::<Vec<_>>());
     assert!(name == "Suzanne", "Name should be Suzanne but is {name}");
     let prec = TMFPrecisionInfo::default();
     let mut out = Vec::new();
@@ -1,45 +1,66 @@
+use crate::checksum::crc32;
 use crate::read_extension::ReadExt;
-use crate::tmf_exporter::{opt_tris, opt_vertices, EncodeInfo};
+use crate::tmf_exporter::{is_sequence, opt_tris, opt_vertices, EncodeInfo};
 use crate::tmf_importer::{
     decode_custom_seg, decode_normal_seg, decode_triangle_seg, decode_uv_seg, decode_vertex_seg,
-    TMFImportContext,
+    LimitTracker, TMFImportContext,
 };
 
+use crate::vertices::PredictedVertices;
 use crate::{
-    CustomDataSegment, IndexType, TMFExportError, TMFImportError, TMFMesh, TMFPrecisionInfo,
-    Tangent, Vector2, Vector3,
+    ConnectivityMode, CustomDataSegment, IndexType, TMFErrorLocation, TMFExportError,
+    TMFImportError, TMFImportOptions, TMFMesh, TMFPrecisionInfo, Tangent, Vector2, Vector3,
+    VertexPredictionMode,
 };
 use smallvec::{smallvec, SmallVec};
+use std::borrow::Cow;
+/// Type of data stored in a segment of a .tmf file.
 #[repr(u16)]
-#[derive(Debug, PartialEq, Clone, Copy)]
-pub(crate) enum SectionType {
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[non_exhaustive]
+pub enum SectionType {
+    /// Segment is invalid or of unknown type, and is ignored.
     Invalid = 0,
+    /// Positions of vertices.
     VertexSegment = 1,
+    /// Indices into vertices, making up triangles.
     VertexTriangleSegment = 2,
+    /// Normals.
     NormalSegment = 3,
+    /// Indices into normals.
     NormalTriangleSegment = 4,
+    /// UV coordinates.
     UvSegment = 5,
+    /// Indices into UV coordinates.
     UvTriangleSegment = 6,
     //ColorSegment = 7,
     //ColorTriangleSegment = 8,
+    /// Tangents.
     TangentSegment = 9,
+    /// Indices into tangents.
     TangentTriangleSegment = 10,
     //MaterialInfo = 11,
     //MaterialTriangleRanges = 12,
     //GroupInfo = 13,
     //GroupTriangleRanges = 14,
+    /// Custom data made of indices.
     CustomIndexSegment = 15,
+    /// Custom data made of floats.
     CustomFloatSegment = 16,
     //CustomUnit2Segment = 17,
     //CustomUnit3Segment = 18,
     //CustomVector2Segment = 19,
     //CustomVector3Segment = 20,
     //CustomVector4Segment = 21,
+    /// Custom data made of RGBA colors.
     CustomColorRGBASegment = 23,
-    CustomIntigerSegment = 24,
+    /// Custom data made of integers.
+    CustomIntegerSegment = 24,
+    /// Indices shared by multiple kinds of triangles.
     SharedTriangleSegment = 25,
 }
 impl SectionType {
+    /// Checks if this segment stores triangles.
     pub fn is_triangle(&self) -> bool {
         matches!(
             self,
@@ -50,14 +71,14 @@ impl SectionType {
                 | Self::SharedTriangleSegment
         )
     }
-    pub fn from_u16(input: u16) -> Self {
+    pub(crate) fn from_u16(input: u16) -> Self {
         if input > u8::MAX as u16 {
             Self::Invalid
         } else {
             Self::from_u8(input as u8)
         }
     }
-    pub fn from_u8(input: u8) -> Self {
+    pub(crate) fn from_u8(input: u8) -> Self {
         match input {
             1 => Self::VertexSegment,
             2 => Self::VertexTriangleSegment,
@@ -70,52 +91,133 @@ impl SectionType {
             15 => Self::CustomIndexSegment,
             16 => Self::CustomFloatSegment,
             23 => Self::CustomColorRGBASegment,
-            24 => Self::CustomIntigerSegment,
+            24 => Self::CustomIntegerSegment,
             25 => Self::SharedTriangleSegment,
             _ => Self::Invalid,
         }
     }
 }
+/// Compression used by a segment of a .tmf file.
 #[repr(u8)]
-#[derive(Debug, PartialEq, Clone, Copy)]
-pub(crate) enum CompressionType {
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[non_exhaustive]
+pub enum CompressionType {
+    /// Data is not compressed.
     None = 0,
-    Ommited = 1,
+    /// Data is omitted, because it can be derived from other mesh data.
+    Omitted = 1,
+    /// Data compressed using LZ77 on unaligned values.
     UnalignedLZZ = 2,
+    /// Delta coded data, compressed using an adaptive range coder.
+    RangeCoded = 3,
+    /// Triangles stored as a traversal of the mesh.
+    Edgebreaker = 4,
+    /// Vertices stored as differences from positions predicted using the parallelogram rule.
+    Parallelogram = 5,
+    /// Floats stored exactly, XORed with previous values.
+    Lossless = 6,
     /// Represents data that is laid out sequentially and changes by exactly one with each element.
     Sequence = 255,
 }
 impl CompressionType {
-    pub fn from_u8(input: u8) -> Result<Self, TMFImportError> {
+    pub(crate) fn from_u8(input: u8) -> Result<Self, TMFImportError> {
         match input {
             0 => Ok(Self::None),
-            1 => Ok(Self::Ommited),
+            1 => Ok(Self::Omitted),
             2 => Ok(Self::UnalignedLZZ),
+            3 => Ok(Self::RangeCoded),
+            4 => Ok(Self::Edgebreaker),
+            5 => Ok(Self::Parallelogram),
+            6 => Ok(Self::Lossless),
             255 => Ok(Self::Sequence),
             _ => Err(TMFImportError::CompressionTypeUnknown(input)),
         }
     }
+    /// Minimum minor version of reader able to read segments compressed this way.
+    pub(crate) fn min_minor(self) -> u16 {
+        match self {
+            Self::None => crate::MIN_TMF_MINOR,
+            Self::Omitted
+            | Self::UnalignedLZZ
+            | Self::RangeCoded
+            | Self::Edgebreaker
+            | Self::Parallelogram
+            | Self::Lossless
+            | Self::Sequence => 3,
+        }
+    }
+    /// Reads compression of a segment of type *seg_type*. Compression of segments of unknown type is not interpreted, since they are never decoded.
+    pub(crate) fn of_segment(seg_type: SectionType, input: u8) -> Result<Self, TMFImportError> {
+        match seg_type {
+            SectionType::Invalid => Ok(Self::None),
+            _ => Self::from_u8(input),
+        }
+    }
+}
+/// Segment of a type unknown to this version of the library (for example written by a newer version), kept as-is, so it is not lost when a mesh is saved again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TMFRawSegment {
+    type_id: u8,
+    compression: u8,
+    data: Vec<u8>,
 }
+impl TMFRawSegment {
+    /// Type id of the segment, as stored in the file.
+    pub fn type_id(&self) -> u8 {
+        self.type_id
+    }
+    /// Compression id of the segment, as stored in the file.
+    pub fn compression(&self) -> u8 {
+        self.compression
+    }
+    /// Data of the segment.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+/// Segment of mesh data, borrowed if it is about to be saved, or owned if it was read.
 #[derive(Debug, Clone)]
-pub(crate) enum DecodedSegment {
+pub(crate) enum DecodedSegment<'a> {
     Nothing,
-    AppendVertex(Box<[Vector3]>),
-    AppendNormal(Box<[Vector3]>),
-    AppendUV(Box<[Vector2]>),
-    AppendTangent(Box<[Tangent]>),
-    AppendTriangleVertex(Box<[IndexType]>),
-    AppendTriangleNormal(Box<[IndexType]>),
-    AppendTriangleUV(Box<[IndexType]>),
-    AppendTriangleTangent(Box<[IndexType]>),
-    SharedTriangleSegment(SharedSegmentKind, Box<[IndexType]>),
-    AppendCustom(CustomDataSegment),
+    AppendVertex(Cow<'a, [Vector3]>),
+    AppendNormal(Cow<'a, [Vector3]>),
+    AppendUV(Cow<'a, [Vector2]>),
+    AppendTangent(Cow<'a, [Tangent]>),
+    AppendTriangleVertex(Cow<'a, [IndexType]>),
+    AppendTriangleNormal(Cow<'a, [IndexType]>),
+    AppendTriangleUV(Cow<'a, [IndexType]>),
+    AppendTriangleTangent(Cow<'a, [IndexType]>),
+    SharedTriangleSegment(SharedSegmentKind, Cow<'a, [IndexType]>),
+    AppendCustom(Cow<'a, CustomDataSegment>),
+    /// Vertices which can only be reconstructed once vertex triangles are known.
+    PredictedVertices(PredictedVertices),
+    /// Triangle array of a given type, equal to the sequence of all indices into its data array.
+    OmittedTriangles(SectionType),
+    /// Segment of unknown type, written back unchanged.
+    Raw(Cow<'a, TMFRawSegment>),
 }
-pub(crate) struct EncodedSegment {
+struct SegmentHeader {
     seg_type: SectionType,
+    data_length: usize,
     compresion_type: CompressionType,
-    data: Box<[u8]>,
+    /// Type id and compression of a segment of unknown type, as stored in the file.
+    unknown: Option<(u8, u8)>,
+    checksum: Option<u32>,
 }
-impl EncodedSegment {
+pub(crate) struct EncodedSegment<'a> {
+    seg_type: SectionType,
+    compresion_type: CompressionType,
+    /// Data of the segment, borrowed if the segment was read from memory.
+    data: Cow<'a, [u8]>,
+    /// Type id and compression of a segment of unknown type, as stored in the file.
+    unknown: Option<(u8, u8)>,
+    /// Is data of the segment matching its checksum. Always true for segments without a checksum.
+    intact: bool,
+}
+impl<'a> EncodedSegment<'a> {
+    pub(crate) fn is_intact(&self) -> bool {
+        self.intact
+    }
     pub(crate) fn data(&self) -> &[u8] {
         &self.data
     }
@@ -129,31 +231,301 @@ impl EncodedSegment {
     pub(crate) fn seg_type(&self) -> SectionType {
         self.seg_type
     }
-    pub(crate) fn write<W: std::io::Write>(&self, w: &mut W) -> std::io::Result<()> {
-        let st: u8 = self.seg_type as u16 as u8;
+    /// Writes the segment to *w*, with a checksum of its data in the header if *checksums* are enabled.
+    pub(crate) fn write<W: std::io::Write>(
+        &self,
+        w: &mut W,
+        checksums: bool,
+    ) -> std::io::Result<()> {
+        let (st, compresion_type) = self
+            .unknown
+            .unwrap_or((self.seg_type as u16 as u8, self.compresion_type as u8));
         w.write_all(&[st])?;
         w.write_all(&(self.data.len() as u32).to_le_bytes())?;
-        w.write_all(&[self.compresion_type as u8])?;
+        w.write_all(&[compresion_type])?;
+        if checksums {
+            w.write_all(&crc32(&self.data).to_le_bytes())?;
+        }
         w.write_all(&self.data)
     }
-    pub(crate) fn read<R: std::io::Read>(
+    /// Reads the beginning of segment data from *src*, as much as is needed to check if the segment contains data selected by *options*.
+    /// Returns if the segment is selected, and the read data.
+    fn select<R: std::io::Read>(
+        seg_type: SectionType,
+        data_length: usize,
+        src: &mut R,
+        options: &TMFImportOptions,
+    ) -> Result<(bool, Vec<u8>), TMFImportError> {
+        let mut prefix = Vec::new();
+        let is_selected = match seg_type {
+            SectionType::VertexSegment | SectionType::VertexTriangleSegment => options.vertices,
+            SectionType::NormalSegment | SectionType::NormalTriangleSegment => options.normals,
+            SectionType::UvSegment | SectionType::UvTriangleSegment => options.uvs,
+            SectionType::TangentSegment | SectionType::TangentTriangleSegment => options.tangents,
+            SectionType::SharedTriangleSegment if data_length > 0 => {
+                let mask = src.read_u8()?;
+                prefix.push(mask);
+                mask & SharedSegmentKind::selected(options).mask() != 0
+            }
+            SectionType::CustomIndexSegment
+            | SectionType::CustomIntegerSegment
+            | SectionType::CustomFloatSegment
+            | SectionType::CustomColorRGBASegment
+                if data_length > 0 =>
+            {
+                let name_len = src.read_u8()?;
+                prefix.push(name_len);
+                prefix.resize(name_len as usize + 1, 0);
+                src.read_exact(&mut prefix[1..])?;
+                options.custom_data.selects(&prefix[1..])
+            }
+            _ => true,
+        };
+        Ok((is_selected, prefix))
+    }
+    fn read_header<R: std::io::Read>(
         ctx: &TMFImportContext,
         src: &mut R,
-    ) -> Result<Self, TMFImportError> {
-        let seg_type = ctx.segment_type_width().read(src)?;
+    ) -> Result<SegmentHeader, TMFImportError> {
+        let type_id = ctx.segment_type_width().read(src)?;
+        let seg_type = SectionType::from_u16(type_id);
+        Self::read_header_of_type(ctx, src, type_id)
+            .map_err(|err| err.at(TMFErrorLocation::of_segment_type(seg_type)))
+    }
+    /// Reads the rest of the header of a segment with type *type_id*.
+    fn read_header_of_type<R: std::io::Read>(
+        ctx: &TMFImportContext,
+        src: &mut R,
+        type_id: u16,
+    ) -> Result<SegmentHeader, TMFImportError> {
+        let seg_type = SectionType::from_u16(type_id);
         let data_length = ctx.segment_length_width().read(src)?;
         if data_length > crate::MAX_SEG_SIZE {
             return Err(TMFImportError::SegmentTooLong);
         }
-        let compresion_type = CompressionType::from_u8(src.read_u8()?)?;
-        let mut data = vec![0; data_length];
-        src.read_exact(&mut data)?;
-        Ok(Self {
-            data: data.into(),
-            compresion_type,
+        let compression = src.read_u8()?;
+        let compresion_type = CompressionType::of_segment(seg_type, compression)?;
+        // Unknown segments with type ids not fitting in the current format can't be saved again, so they are dropped.
+        let unknown = match (seg_type, u8::try_from(type_id)) {
+            (SectionType::Invalid, Ok(type_id)) => Some((type_id, compression)),
+            _ => None,
+        };
+        let checksum = ctx.read_checksum(src)?;
+        Ok(SegmentHeader {
             seg_type,
+            data_length,
+            compresion_type,
+            unknown,
+            checksum,
         })
     }
+    /// Removes unselected kinds of triangles from shared triangle segment *data*, so they are not appended to the mesh.
+    fn unselect_shared(seg_type: SectionType, data: &mut [u8], options: &TMFImportOptions) {
+        if let (SectionType::SharedTriangleSegment, Some(mask)) = (seg_type, data.first_mut()) {
+            *mask &= SharedSegmentKind::selected(options).mask();
+        }
+    }
+    /// Reads a segment, if it contains data selected by *options*. Otherwise, skips over its data without reading it into memory and returns `None`.
+    pub(crate) fn read_selected<R: std::io::Read>(
+        ctx: &TMFImportContext,
+        src: &mut R,
+        options: &TMFImportOptions,
+        tracker: &mut LimitTracker,
+    ) -> Result<Option<Self>, TMFImportError> {
+        let header = Self::read_header(ctx, src)?;
+        let seg_type = header.seg_type;
+        Self::read_selected_data(header, src, options, tracker)
+            .map_err(|err| err.at(TMFErrorLocation::of_segment_type(seg_type)))
+    }
+    /// Reads data of a segment described by *header*. See [`Self::read_selected`].
+    fn read_selected_data<R: std::io::Read>(
+        header: SegmentHeader,
+        src: &mut R,
+        options: &TMFImportOptions,
+        tracker: &mut LimitTracker,
+    ) -> Result<Option<Self>, TMFImportError> {
+        use std::io::Read;
+        let SegmentHeader {
+            seg_type,
+            data_length,
+            compresion_type,
+            unknown,
+            checksum,
+        } = header;
+        let mut data_src = src.take(data_length as u64);
+        let (is_selected, prefix) = Self::select(seg_type, data_length, &mut data_src, options)?;
+        if !is_selected {
+            let skipped = std::io::copy(&mut data_src, &mut std::io::sink())?;
+            if skipped + (prefix.len() as u64) < data_length as u64 {
+                return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+            }
+            return Ok(None);
+        }
+        tracker.take_bytes(data_length)?;
+        let mut data = prefix;
+        let prefix_len = data.len();
+        data.resize(data_length, 0);
+        data_src.read_exact(&mut data[prefix_len..])?;
+        let intact = checksum.is_none_or(|checksum| checksum == crc32(&data));
+        Self::unselect_shared(seg_type, &mut data, options);
+        Ok(Some(Self {
+            data: Cow::Owned(data),
+            compresion_type,
+            seg_type,
+            unknown,
+            intact,
+        }))
+    }
+    /// Version of [`Self::read_selected`] reading from memory, which borrows segment data from *src* instead of copying it.
+    pub(crate) fn read_selected_from_slice(
+        ctx: &TMFImportContext,
+        src: &mut &'a [u8],
+        options: &TMFImportOptions,
+        tracker: &mut LimitTracker,
+    ) -> Result<Option<Self>, TMFImportError> {
+        let header = Self::read_header(ctx, src)?;
+        let seg_type = header.seg_type;
+        Self::read_selected_data_from_slice(header, src, options, tracker)
+            .map_err(|err| err.at(TMFErrorLocation::of_segment_type(seg_type)))
+    }
+    /// Reads data of a segment described by *header*. See [`Self::read_selected_from_slice`].
+    fn read_selected_data_from_slice(
+        header: SegmentHeader,
+        src: &mut &'a [u8],
+        options: &TMFImportOptions,
+        tracker: &mut LimitTracker,
+    ) -> Result<Option<Self>, TMFImportError> {
+        let SegmentHeader {
+            seg_type,
+            data_length,
+            compresion_type,
+            unknown,
+            checksum,
+        } = header;
+        if data_length > src.len() {
+            return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+        }
+        let (data, rest) = src.split_at(data_length);
+        *src = rest;
+        let (is_selected, _) = Self::select(seg_type, data_length, &mut &data[..], options)?;
+        if !is_selected {
+            return Ok(None);
+        }
+        tracker.take_bytes(data_length)?;
+        let intact = checksum.is_none_or(|checksum| checksum == crc32(data));
+        let mut data = Cow::Borrowed(data);
+        // Data only needs to be copied if some kinds of shared triangles are removed.
+        let selected = SharedSegmentKind::selected(options).mask();
+        if seg_type == SectionType::SharedTriangleSegment
+            && data.first().is_some_and(|mask| mask & selected != *mask)
+        {
+            Self::unselect_shared(seg_type, data.to_mut(), options);
+        }
+        Ok(Some(Self {
+            data,
+            compresion_type,
+            seg_type,
+            unknown,
+            intact,
+        }))
+    }
+}
+/// Appends `vertices` to `data`, predicting their positions if enabled and if that gives a smaller result.
+fn encode_vertices(
+    vertices: &[Vector3],
+    prec: &TMFPrecisionInfo,
+    ei: &EncodeInfo<'_>,
+    data: &mut Vec<u8>,
+) -> Result<CompressionType, TMFExportError> {
+    if vertices.is_empty() {
+        return Err(TMFExportError::NoVertices);
+    }
+    let max_deviations = prec.vertex_precision.max_deviations(ei.shortest_edge());
+    let mut encoded = Vec::new();
+    let mut compression = CompressionType::None;
+    if prec.vertex_precision.is_lossless() {
+        crate::lossless::save_floats(vertices, &mut encoded)?;
+        compression = CompressionType::Lossless;
+    } else if !max_deviations
+        .iter()
+        .all(|deviation| deviation.is_finite() && *deviation > 0.0)
+    {
+        return Err(TMFExportError::InvalidVertexPrecision(
+            prec.vertex_precision,
+        ));
+    } else if !crate::vertices::has_finite_span(vertices) {
+        return Err(TMFExportError::VertexSpanNotFinite);
+    } else {
+        crate::vertices::save_tmf_vertices(
+            vertices,
+            prec.vertex_precision,
+            &mut encoded,
+            ei.shortest_edge(),
+        )?;
+        if prec.vertex_prediction == VertexPredictionMode::Parallelogram {
+            let mut predicted = Vec::new();
+            let is_predicted = crate::vertices::save_predicted_vertices(
+                vertices,
+                ei.vertex_triangles(),
+                prec.vertex_precision,
+                ei.shortest_edge(),
+                &mut predicted,
+            )?;
+            if is_predicted && predicted.len() < encoded.len() {
+                encoded = predicted;
+                compression = CompressionType::Parallelogram;
+            }
+        }
+    }
+    // Self-check: in debug builds, saved vertices are decoded back, and must stay within the promised bound.
+    if cfg!(debug_assertions) {
+        let mut saved = &encoded[..];
+        let decoded = match compression {
+            CompressionType::Parallelogram => crate::vertices::read_predicted_vertices(&mut saved)
+                .map(|predicted| predicted.reconstruct(ei.vertex_triangles(), 0)),
+            CompressionType::Lossless => crate::lossless::read_floats(&mut saved),
+            _ => crate::vertices::read_tmf_vertices(&mut saved),
+        }
+        .expect("Saved vertices could not be decoded!");
+        crate::vertices::check_deviations(vertices, &decoded, max_deviations);
+    }
+    data.extend(encoded);
+    Ok(compression)
+}
+/// Appends `triangles` to `data`, using whichever of supported compression types gives the smallest result.
+fn encode_triangles(
+    triangles: &[IndexType],
+    connectivity_mode: ConnectivityMode,
+    data: &mut Vec<u8>,
+) -> std::io::Result<CompressionType> {
+    if is_sequence(triangles) {
+        data.extend(u64::from(triangles[0]).to_le_bytes());
+        data.extend((triangles.len() as u64).to_le_bytes());
+        return Ok(CompressionType::Sequence);
+    }
+    let max_index = *triangles.iter().max().unwrap_or(&0) as usize;
+    let mut uncompressed = Vec::new();
+    crate::vertices::save_triangles(triangles, max_index, &mut uncompressed)?;
+    let mut lzz = Vec::new();
+    crate::lz77::save_triangles_lzz(triangles, max_index, &mut lzz)?;
+    let mut range_coded = Vec::new();
+    crate::range_coder::save_triangles_rc(triangles, &mut range_coded)?;
+    let mut candidates = vec![
+        (CompressionType::None, uncompressed),
+        (CompressionType::UnalignedLZZ, lzz),
+        (CompressionType::RangeCoded, range_coded),
+    ];
+    if connectivity_mode == ConnectivityMode::Edgebreaker && triangles.len() % 3 == 0 {
+        let mut traversal = Vec::new();
+        crate::edgebreaker::save_triangles_eb(triangles, &mut traversal)?;
+        candidates.push((CompressionType::Edgebreaker, traversal));
+    }
+    let (compresion_type, smallest) = candidates
+        .into_iter()
+        .min_by_key(|(_, encoded)| encoded.len())
+        .expect("Candidate encodings are never empty.");
+    data.extend(smallest);
+    Ok(compresion_type)
 }
 #[derive(Default, Debug, Copy, Clone)]
 pub(crate) struct SharedSegmentKind {
@@ -189,6 +561,35 @@ impl SharedSegmentKind {
     fn from_mask(mask: u8) -> Self {
         Self { mask }
     }
+    /// Returns kind containing all triangle types selected by *options*.
+    fn selected(options: &TMFImportOptions) -> Self {
+        let mut kind = Self::default();
+        if options.vertices {
+            kind.set_vertex();
+        }
+        if options.normals {
+            kind.set_normal();
+        }
+        if options.uvs {
+            kind.set_uv();
+        }
+        kind
+    }
+}
+/// Returns types of triangles sharing a shared triangle segment with *mask*.
+pub(crate) fn shared_triangle_types(mask: u8) -> SmallVec<[SectionType; 3]> {
+    let kind = SharedSegmentKind::from_mask(mask);
+    let mut types = SmallVec::new();
+    if kind.get_vertex() {
+        types.push(SectionType::VertexTriangleSegment);
+    }
+    if kind.get_normal() {
+        types.push(SectionType::NormalTriangleSegment);
+    }
+    if kind.get_uv() {
+        types.push(SectionType::UvTriangleSegment);
+    }
+    types
 }
 impl std::fmt::Display for SharedSegmentKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
@@ -205,22 +606,43 @@ impl std::fmt::Display for SharedSegmentKind {
         write!(f, "}}")
     }
 }
-impl DecodedSegment {
+/// Splits *data* into parts chosen by *split*. Borrowed data is split without copying it.
+fn split_data<'a, T: Clone>(
+    data: Cow<'a, [T]>,
+    split: impl Fn(&[T]) -> SmallVec<[&[T]; 4]>,
+) -> SmallVec<[Cow<'a, [T]>; 1]> {
+    match data {
+        Cow::Borrowed(data) => split(data).into_iter().map(Cow::Borrowed).collect(),
+        Cow::Owned(data) => {
+            let parts: SmallVec<[Cow<'a, [T]>; 1]> = match split(&data)[..] {
+                [_] => SmallVec::new(),
+                ref parts => parts.iter().map(|part| Cow::Owned(part.to_vec())).collect(),
+            };
+            if parts.is_empty() {
+                smallvec![Cow::Owned(data)]
+            } else {
+                parts
+            }
+        }
+    }
+}
+impl<'a> DecodedSegment<'a> {
     pub(crate) fn merge(&mut self, other: &mut Self) {
-        if other.as_triangles().is_none() {
+        let (Some(other_triangles), Some(other_kind)) = (other.as_triangles(), other.shared_kind())
+        else {
             return;
-        }
+        };
         match self {
             Self::SharedTriangleSegment(kind, indices) => {
-                if &indices[..] != other.as_triangles().unwrap() {
+                if &indices[..] != other_triangles {
                     return;
                 }
-                let combined = kind.combine(other.shared_kind());
+                let combined = kind.combine(other_kind);
                 *self = Self::SharedTriangleSegment(combined, indices.clone());
                 *other = Self::Nothing;
             }
             Self::AppendTriangleVertex(indices) => {
-                if &indices[..] != other.as_triangles().unwrap() {
+                if &indices[..] != other_triangles {
                     return;
                 }
                 let kind = {
@@ -228,12 +650,12 @@ impl DecodedSegment {
                     kind.set_vertex();
                     kind
                 };
-                let combined = kind.combine(other.shared_kind());
+                let combined = kind.combine(other_kind);
                 *self = Self::SharedTriangleSegment(combined, indices.clone());
                 *other = Self::Nothing;
             }
             Self::AppendTriangleNormal(indices) => {
-                if &indices[..] != other.as_triangles().unwrap() {
+                if &indices[..] != other_triangles {
                     return;
                 }
                 let kind = {
@@ -241,12 +663,12 @@ impl DecodedSegment {
                     kind.set_normal();
                     kind
                 };
-                let combined = kind.combine(other.shared_kind());
+                let combined = kind.combine(other_kind);
                 *self = Self::SharedTriangleSegment(combined, indices.clone());
                 *other = Self::Nothing;
             }
             Self::AppendTriangleUV(indices) => {
-                if &indices[..] != other.as_triangles().unwrap() {
+                if &indices[..] != other_triangles {
                     return;
                 }
                 let kind = {
@@ -254,7 +676,7 @@ impl DecodedSegment {
                     kind.set_uv();
                     kind
                 };
-                let combined = kind.combine(other.shared_kind());
+                let combined = kind.combine(other_kind);
                 *self = Self::SharedTriangleSegment(combined, indices.clone());
                 *other = Self::Nothing;
             }
@@ -268,92 +690,80 @@ impl DecodedSegment {
             _ => None,
         }
     }
-    fn shared_kind(&self) -> SharedSegmentKind {
+    fn shared_kind(&self) -> Option<SharedSegmentKind> {
+        let mut kind = SharedSegmentKind::default();
         match self {
-            Self::AppendTriangleVertex(_) => {
-                let mut kind = SharedSegmentKind::default();
-                kind.set_vertex();
-                kind
-            }
-            Self::AppendTriangleNormal(_) => {
-                let mut kind = SharedSegmentKind::default();
-                kind.set_normal();
-                kind
-            }
-            _ => todo!("{:?}", self),
+            Self::AppendTriangleVertex(_) => kind.set_vertex(),
+            Self::AppendTriangleNormal(_) => kind.set_normal(),
+            _ => return None,
+        }
+        Some(kind)
+    }
+    /// Segments with bigger apply order depend on data from segments with smaller apply order, and must be applied after them.
+    pub(crate) fn apply_order(&self) -> u8 {
+        match self {
+            Self::PredictedVertices(_) => 1,
+            Self::OmittedTriangles(_) => 2,
+            _ => 0,
         }
     }
     pub(crate) fn is_something(&self) -> bool {
         !matches!(self, Self::Nothing)
     }
-    pub(crate) async fn optimize(self) -> SmallVec<[Self; 1]> {
+    pub(crate) async fn optimize(
+        self,
+        prec: &TMFPrecisionInfo,
+        ei: &EncodeInfo<'_>,
+    ) -> SmallVec<[Self; 1]> {
         match self {
-            Self::AppendTriangleVertex(triangles) => {
-                //search_for_sequential_regions(&triangles);
-                let optimised = opt_tris(&triangles);
-                let mut res = SmallVec::new();
-                for seg in optimised {
-                    res.push(Self::AppendTriangleVertex(seg.into()));
-                }
-                res
-            }
-            Self::AppendTriangleUV(triangles) => {
-                let optimised = opt_tris(&triangles);
-                let mut res = SmallVec::new();
-                for seg in optimised {
-                    res.push(Self::AppendTriangleUV(seg.into()));
-                }
-                res
-            }
-            Self::AppendTriangleNormal(triangles) => {
-                let optimised = opt_tris(&triangles);
-                let mut res = SmallVec::new();
-                for seg in optimised {
-                    res.push(Self::AppendTriangleNormal(seg.into()));
-                }
-                res
-            }
-            Self::AppendTriangleTangent(triangles) => {
-                let optimised = opt_tris(&triangles);
-                let mut res = SmallVec::new();
-                for seg in optimised {
-                    res.push(Self::AppendTriangleTangent(seg.into()));
-                }
-                res
+            Self::AppendTriangleVertex(triangles) => split_data(triangles, opt_tris)
+                .into_iter()
+                .map(Self::AppendTriangleVertex)
+                .collect(),
+            Self::AppendTriangleUV(triangles) => split_data(triangles, opt_tris)
+                .into_iter()
+                .map(Self::AppendTriangleUV)
+                .collect(),
+            Self::AppendTriangleNormal(triangles) => split_data(triangles, opt_tris)
+                .into_iter()
+                .map(Self::AppendTriangleNormal)
+                .collect(),
+            Self::AppendTriangleTangent(triangles) => split_data(triangles, opt_tris)
+                .into_iter()
+                .map(Self::AppendTriangleTangent)
+                .collect(),
+            // Predicted vertices are not split, since predictions use indices from the whole mesh.
+            // Lossless vertices do not depend on bounding boxes, so splitting them would only add segment headers.
+            Self::AppendVertex(vertices)
+                if prec.vertex_prediction == VertexPredictionMode::Parallelogram
+                    || prec.vertex_precision.is_lossless() =>
+            {
+                smallvec![Self::AppendVertex(vertices)]
             }
             Self::AppendVertex(vertices) => {
-                let optimised = opt_vertices(&vertices);
-                let mut res = SmallVec::new();
-                for seg in optimised {
-                    res.push(Self::AppendVertex(seg.into()));
-                }
-                res
-            }
-            Self::SharedTriangleSegment(kind,triangles) => {
-                let optimised = opt_tris(&triangles);
-                let mut res = SmallVec::new();
-                for seg in optimised {
-                    res.push(Self::SharedTriangleSegment(kind,seg.into()));
-                }
-                res
+                let max_deviations = prec.vertex_precision.max_deviations(ei.shortest_edge());
+                split_data(vertices, |vertices| opt_vertices(vertices, max_deviations))
+                    .into_iter()
+                    .map(Self::AppendVertex)
+                    .collect()
             }
+            Self::SharedTriangleSegment(kind, triangles) => split_data(triangles, opt_tris)
+                .into_iter()
+                .map(|triangles| Self::SharedTriangleSegment(kind, triangles))
+                .collect(),
             _ => smallvec![self],
         }
     }
     pub(crate) async fn encode(
         self,
         prec: &TMFPrecisionInfo,
-        ei: &EncodeInfo,
-    ) -> Result<EncodedSegment, TMFExportError> {
+        ei: &EncodeInfo<'_>,
+    ) -> Result<EncodedSegment<'a>, TMFExportError> {
         let mut data = vec![];
+        let mut compresion_type = CompressionType::None;
         let seg_type = match self {
             Self::AppendVertex(vertices) => {
-                crate::vertices::save_tmf_vertices(
-                    &vertices,
-                    prec.vertex_precision,
-                    &mut data,
-                    ei.shortest_edge(),
-                )?;
+                compresion_type = encode_vertices(&vertices, prec, ei, &mut data)?;
                 SectionType::VertexSegment
             }
             Self::AppendTangent(tangents) => {
@@ -361,62 +771,106 @@ impl DecodedSegment {
                 SectionType::TangentSegment
             }
             Self::AppendNormal(normals) => {
-                crate::normals::save_normal_array(&normals, &mut data, prec.normal_precision)?;
+                if prec.normal_precision.is_lossless() {
+                    crate::lossless::save_floats(&normals, &mut data)?;
+                    compresion_type = CompressionType::Lossless;
+                } else {
+                    crate::normals::save_normal_array(&normals, &mut data, prec.normal_precision)?;
+                }
                 SectionType::NormalSegment
             }
             Self::AppendUV(uvs) => {
-                crate::uv::save_uvs(&uvs, &mut data, prec.uv_prec)?;
+                if prec.uv_prec.is_lossless() {
+                    crate::lossless::save_floats(&uvs, &mut data)?;
+                    compresion_type = CompressionType::Lossless;
+                } else {
+                    crate::uv::save_uvs(&uvs, &mut data, prec.uv_prec)?;
+                }
                 SectionType::UvSegment
             }
             Self::AppendTriangleVertex(triangles) => {
-                let max_index = triangles.iter().max().unwrap_or(&0);
-                crate::vertices::save_triangles(&triangles, (*max_index) as usize, &mut data)?;
+                compresion_type = encode_triangles(&triangles, prec.connectivity_mode, &mut data)?;
                 SectionType::VertexTriangleSegment
             }
             Self::AppendTriangleNormal(triangles) => {
-                let max_index = triangles.iter().max().unwrap_or(&0);
-                crate::vertices::save_triangles(&triangles, (*max_index) as usize, &mut data)?;
+                compresion_type =
+                    encode_triangles(&triangles, ConnectivityMode::Indices, &mut data)?;
                 SectionType::NormalTriangleSegment
             }
             Self::AppendTriangleUV(triangles) => {
-                let max_index = triangles.iter().max().unwrap_or(&0);
-                crate::vertices::save_triangles(&triangles, (*max_index) as usize, &mut data)?;
+                compresion_type =
+                    encode_triangles(&triangles, ConnectivityMode::Indices, &mut data)?;
                 SectionType::UvTriangleSegment
             }
             Self::AppendTriangleTangent(triangles) => {
-                let max_index = triangles.iter().max().unwrap_or(&0);
-                crate::vertices::save_triangles(&triangles, (*max_index) as usize, &mut data)?;
+                compresion_type =
+                    encode_triangles(&triangles, ConnectivityMode::Indices, &mut data)?;
                 SectionType::TangentTriangleSegment
             }
-            Self::AppendCustom(custom_data) => custom_data.encode(&mut data)?,
+            Self::AppendCustom(custom_data) => {
+                let seg_type;
+                (seg_type, compresion_type) = custom_data.encode(&mut data)?;
+                seg_type
+            }
             Self::SharedTriangleSegment(kind, triangles) => {
-                let max_index = triangles.iter().max().unwrap_or(&0);
                 data.push(kind.mask());
-                crate::vertices::save_triangles(&triangles, (*max_index) as usize, &mut data)?;
+                compresion_type = encode_triangles(&triangles, prec.connectivity_mode, &mut data)?;
                 SectionType::SharedTriangleSegment
             }
+            Self::PredictedVertices(_) => {
+                return Err(TMFExportError::IO(std::io::Error::other(
+                    "Predicted vertices must be reconstructed before they can be saved.",
+                )))
+            }
+            Self::OmittedTriangles(seg_type) => {
+                compresion_type = CompressionType::Omitted;
+                seg_type
+            }
+            Self::Raw(raw) => {
+                let unknown = Some((raw.type_id, raw.compression));
+                let data = match raw {
+                    Cow::Borrowed(raw) => Cow::Borrowed(&raw.data[..]),
+                    Cow::Owned(raw) => Cow::Owned(raw.data),
+                };
+                return Ok(EncodedSegment {
+                    seg_type: SectionType::Invalid,
+                    data,
+                    compresion_type,
+                    unknown,
+                    intact: true,
+                });
+            }
             Self::Nothing => SectionType::Invalid,
         };
         Ok(EncodedSegment {
             seg_type,
-            data: data.into(),
-            compresion_type: CompressionType::None,
+            data: Cow::Owned(data),
+            compresion_type,
+            unknown: None,
+            intact: true,
         })
     }
     pub(crate) async fn decode(
-        seg: EncodedSegment,
+        seg: EncodedSegment<'_>,
         ctx: &crate::tmf_importer::TMFImportContext,
     ) -> Result<Self, TMFImportError> {
         //println!("Decoding segment of type:{:?}!",seg.seg_type());
         match seg.seg_type {
-            SectionType::Invalid => Ok(Self::Nothing),
+            SectionType::Invalid => Ok(match seg.unknown {
+                Some((type_id, compression)) => Self::Raw(Cow::Owned(TMFRawSegment {
+                    type_id,
+                    compression,
+                    data: seg.data.into_owned(),
+                })),
+                None => Self::Nothing,
+            }),
             SectionType::VertexSegment => decode_vertex_seg(seg).await,
             SectionType::NormalSegment => decode_normal_seg(seg).await,
             SectionType::UvSegment => decode_uv_seg(seg).await,
             SectionType::TangentSegment => {
                 async {
                     let tans = crate::tangents::read_tangents(&mut &seg.data[..])?;
-                    Ok(DecodedSegment::AppendTangent(tans))
+                    Ok(DecodedSegment::AppendTangent(tans.into_vec().into()))
                 }
                 .await
             }
@@ -425,28 +879,23 @@ impl DecodedSegment {
             | SectionType::UvTriangleSegment
             | SectionType::TangentTriangleSegment => decode_triangle_seg(seg, ctx).await,
             SectionType::CustomIndexSegment
-            | SectionType::CustomIntigerSegment
+            | SectionType::CustomIntegerSegment
             | SectionType::CustomFloatSegment
             | SectionType::CustomColorRGBASegment => decode_custom_seg(seg, ctx).await,
             SectionType::SharedTriangleSegment => {
-                if seg.data.len() < 1 {
+                if seg.data.is_empty() {
                     return Err(TMFImportError::IO(std::io::Error::from(
                         std::io::ErrorKind::UnexpectedEof,
                     )));
                 }
                 let kind = SharedSegmentKind::from_mask(seg.data[0]);
-                let data: &[u8] = &seg.data()[1..];
                 let mut indices = Vec::new();
-                match seg.compresion_type() {
-                    CompressionType::None => {
-                        crate::tmf_importer::read_default_triangles(data, &mut indices, ctx)?
-                    }
-                    _ => {
-                        return Err(TMFImportError::UnsuportedCompressionType(
-                            seg.compresion_type() as u8,
-                        ))
-                    }
-                }
+                crate::tmf_importer::read_triangles_into(
+                    &seg.data()[1..],
+                    seg.compresion_type(),
+                    ctx,
+                    &mut indices,
+                )?;
                 Ok(Self::SharedTriangleSegment(kind, indices.into()))
             }
         }
@@ -466,13 +915,44 @@ impl DecodedSegment {
                 mesh.append_uv_triangles(uv_triangles)
             }
             DecodedSegment::AppendCustom(custom_data_seg) => {
-                mesh.add_custom_data_seg(custom_data_seg.clone())
+                mesh.add_custom_data_seg(custom_data_seg.clone().into_owned())
             }
             DecodedSegment::AppendTangent(tans) => mesh.append_tangents(tans),
             DecodedSegment::AppendTriangleTangent(tan_triangles) => {
                 mesh.append_tangent_triangles(tan_triangles)
             }
             DecodedSegment::Nothing => (),
+            DecodedSegment::Raw(raw) => mesh.add_raw_segment(raw.clone().into_owned()),
+            DecodedSegment::PredictedVertices(predicted) => {
+                let offset = mesh.get_vertices().map_or(0, <[_]>::len);
+                let triangles = mesh.get_vertex_triangles().unwrap_or(&[]);
+                let vertices = predicted.reconstruct(triangles, offset);
+                mesh.append_vertices(&vertices);
+            }
+            DecodedSegment::OmittedTriangles(seg_type) => {
+                fn identity(len: usize) -> Box<[IndexType]> {
+                    (0..len).map(|index| index as IndexType).collect()
+                }
+                match seg_type {
+                    SectionType::VertexTriangleSegment => {
+                        let len = mesh.get_vertices().map_or(0, <[_]>::len);
+                        mesh.append_vertex_triangles(&identity(len));
+                    }
+                    SectionType::NormalTriangleSegment => {
+                        let len = mesh.get_normals().map_or(0, <[_]>::len);
+                        mesh.append_normal_triangles(&identity(len));
+                    }
+                    SectionType::UvTriangleSegment => {
+                        let len = mesh.get_uvs().map_or(0, <[_]>::len);
+                        mesh.append_uv_triangles(&identity(len));
+                    }
+                    SectionType::TangentTriangleSegment => {
+                        let len = mesh.get_tangents().map_or(0, <[_]>::len);
+                        mesh.append_tangent_triangles(&identity(len));
+                    }
+                    _ => (),
+                }
+            }
             DecodedSegment::SharedTriangleSegment(kind, indices) => {
                 //println!("kind:{kind}");
                 if kind.get_vertex() {
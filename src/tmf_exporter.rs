@@ -1,39 +1,50 @@
-use crate::tmf::DecodedSegment;
+use crate::report::{EncodeReport, MeshEncodeReport, SegmentEncodeReport};
+use crate::tmf::{DecodedSegment, SectionType};
 
 use smallvec::{smallvec, SmallVec};
 
 use crate::{
-    FloatType, IndexType, TMFExportError, TMFMesh, TMFPrecisionInfo, Vector3, MIN_TMF_MAJOR,
-    MIN_TMF_MINOR, TMF_MAJOR, TMF_MINOR,
+    ConnectivityMode, FloatType, IndexType, TMFExportError, TMFMesh, TMFMeshRef, TMFPrecisionInfo,
+    Vector3, VertexPredictionMode, CHECKSUMS_FLAG, CHECKSUM_TMF_MINOR, FLAGS_TMF_MINOR,
+    MIN_TMF_MAJOR, MIN_TMF_MINOR, TMF_MAJOR, TMF_MINOR,
 };
+use std::borrow::Cow;
 
-pub(crate) struct EncodeInfo {
+pub(crate) struct EncodeInfo<'a> {
     shortest_edge: FloatType,
+    /// Vertex triangles of the mesh, only present if they are needed to predict vertices.
+    vertex_triangles: &'a [IndexType],
 }
-impl Default for EncodeInfo {
+impl Default for EncodeInfo<'_> {
     fn default() -> Self {
-        Self { shortest_edge: 0.1 }
+        Self {
+            shortest_edge: 0.1,
+            vertex_triangles: &[],
+        }
     }
 }
-impl EncodeInfo {
+impl EncodeInfo<'_> {
     pub(crate) fn shortest_edge(&self) -> FloatType {
         self.shortest_edge
     }
+    pub(crate) fn vertex_triangles(&self) -> &[IndexType] {
+        self.vertex_triangles
+    }
 }
 fn calc_shortest_edge(
     vertex_triangles: Option<&[IndexType]>,
     vertices: Option<&[Vector3]>,
-) -> FloatType {
+) -> Result<FloatType, TMFExportError> {
     let shortest_edge = match vertex_triangles {
         Some(vertex_triangles) => {
             if vertex_triangles.is_empty() {
                 //TODO: handle 0 faced mesh as mesh with no faces!
-                return 0.1;
+                return Ok(0.1);
             }
             use crate::utilis::distance;
             let vertices = match vertices {
                 Some(vertices) => vertices,
-                None => return 0.1,
+                None => return Ok(0.1),
             };
             let mut shortest_edge = FloatType::INFINITY;
             for i in 0..(vertex_triangles.len() / 3) {
@@ -56,20 +67,19 @@ fn calc_shortest_edge(
         // TODO: Calculate distance between closest points for point cloud
         None => 0.1,
     };
-    assert!(
-        shortest_edge.is_finite(),
-        "Shortest edge should be finite but is '{shortest_edge}'!"
-    );
-    shortest_edge
+    if !shortest_edge.is_finite() {
+        return Err(TMFExportError::InfiniteShortestEdge(shortest_edge));
+    }
+    Ok(shortest_edge)
 }
-
-pub(crate) fn write_mesh_name<W: std::io::Write>(w: &mut W, s: &str) -> std::io::Result<()> {
+/// Writes mesh name *s*, or returns [`TMFExportError::NameTooLong`] without writing anything if it does not fit in the u16 length.
+pub(crate) fn write_mesh_name<W: std::io::Write>(w: &mut W, s: &str) -> Result<(), TMFExportError> {
     let bytes = s.as_bytes();
-    w.write_all(&(bytes.len() as u16).to_le_bytes())?;
-    w.write_all(bytes)
+    let len = u16::try_from(bytes.len()).map_err(|_| TMFExportError::NameTooLong(bytes.len()))?;
+    w.write_all(&len.to_le_bytes())?;
+    Ok(w.write_all(bytes)?)
 }
-fn merge_segments(segments: &[DecodedSegment]) -> Box<[DecodedSegment]> {
-    let mut segments: Vec<_> = segments.into();
+fn merge_segments(mut segments: Vec<DecodedSegment<'_>>) -> Vec<DecodedSegment<'_>> {
     let mut new_segments = Vec::with_capacity(segments.len());
     while let Some(mut segment) = segments.pop() {
         if segment.is_something() {
@@ -79,76 +89,481 @@ fn merge_segments(segments: &[DecodedSegment]) -> Box<[DecodedSegment]> {
             new_segments.push(segment);
         }
     }
-    new_segments.into()
+    new_segments
+}
+/// Returns *mesh* with triangles reordered to follow its traversal, and data reordered by first use, making connectivity coding most efficient.
+/// Returns `None` if the mesh can't be safely reordered.
+fn reorder_for_traversal(mesh: &TMFMeshRef<'_>) -> Option<TMFMesh> {
+    let vertex_triangles = mesh.get_vertex_triangles()?;
+    // Custom data and unknown segments may refer to vertices or triangles, so they would be invalidated by reordering.
+    if !mesh.custom_data().is_empty()
+        || !mesh.get_raw_segments().is_empty()
+        || vertex_triangles.len() % 3 != 0
+    {
+        return None;
+    }
+    let len = vertex_triangles.len();
+    let all_triangles = [
+        mesh.get_normal_triangles(),
+        mesh.get_uv_triangles(),
+        mesh.get_tangent_triangles(),
+    ];
+    if all_triangles.iter().flatten().any(|tris| tris.len() != len) {
+        return None;
+    }
+    let order = crate::edgebreaker::traversal_order(vertex_triangles);
+    let reorder = |triangles: &[IndexType]| -> Box<[IndexType]> {
+        order.iter().map(|corner| triangles[*corner]).collect()
+    };
+    let mut reordered = TMFMesh::empty();
+    if let Some(vertices) = mesh.get_vertices() {
+        reordered.set_vertices(vertices);
+    }
+    if let Some(normals) = mesh.get_normals() {
+        reordered.set_normals(normals);
+    }
+    if let Some(uvs) = mesh.get_uvs() {
+        reordered.set_uvs(uvs);
+    }
+    if let Some(tangents) = mesh.get_tangents() {
+        reordered.set_tangents(tangents);
+    }
+    let mut mesh = reordered;
+    mesh.set_vertex_triangles(reorder(vertex_triangles));
+    if let Some(normal_triangles) = all_triangles[0] {
+        mesh.set_normal_triangles(reorder(normal_triangles));
+    }
+    if let Some(uv_triangles) = all_triangles[1] {
+        mesh.set_uv_triangles(reorder(uv_triangles));
+    }
+    if let Some(tangent_triangles) = all_triangles[2] {
+        mesh.set_tangent_triangles(reorder(tangent_triangles));
+    }
+    mesh.reorder_data();
+    Some(mesh)
 }
+/// Writes *mesh*, with checksums of segments if *checksums* is true. Checksums must be enabled for all meshes of a file or for none of them.
+/// If *report* is present, a report describing how the mesh was saved is appended to it.
+/// Every check is done before anything is written, so meshes which can't be saved leave *target* untouched.
+/// Returns the minimum minor version of reader able to read the mesh, which depends on compression of its segments.
 async fn write_mesh<W: std::io::Write>(
-    mesh: &TMFMesh,
+    mesh: TMFMeshRef<'_>,
     name: &str,
     target: &mut W,
     p_info: &TMFPrecisionInfo,
-) -> Result<(), TMFExportError> {
-    write_mesh_name(target, name)?;
+    checksums: bool,
+    report: Option<&mut EncodeReport>,
+) -> Result<u16, TMFExportError> {
+    if p_info.verify {
+        mesh.verify().map_err(TMFExportError::InvalidMesh)?;
+    }
+    if name.len() > u16::MAX as usize {
+        return Err(TMFExportError::NameTooLong(name.len()));
+    }
+    let reordered = match p_info.connectivity_mode {
+        ConnectivityMode::Edgebreaker => reorder_for_traversal(&mesh),
+        ConnectivityMode::Indices => None,
+    };
+    let mesh = reordered.as_ref().map_or(mesh, TMFMeshRef::from);
+    let vertex_triangles = match p_info.vertex_prediction {
+        VertexPredictionMode::Parallelogram => mesh.get_vertex_triangles().unwrap_or(&[]),
+        VertexPredictionMode::Disabled => &[],
+    };
+    // Absolute precision does not depend on edges, so meshes without valid edges can still be saved with it.
+    let shortest_edge = if p_info.vertex_precision.is_relative() {
+        calc_shortest_edge(mesh.get_vertex_triangles(), mesh.get_vertices())?
+    } else {
+        EncodeInfo::default().shortest_edge
+    };
     let ei = EncodeInfo {
-        shortest_edge: calc_shortest_edge(mesh.get_vertex_triangles(), mesh.get_vertices()),
+        shortest_edge,
+        vertex_triangles,
     };
-    let tmf_segs = merge_segments(&MeshSegIter::tmf_segs(mesh).collect::<Box<[_]>>());
+    let tmf_segs = merge_segments(MeshSegIter::tmf_segs(mesh).collect());
     let mut new_segs = Vec::with_capacity(32);
-    for seg in tmf_segs.iter() {
-        let c_segs = seg.clone().optimize().await;
+    for seg in tmf_segs {
+        let c_segs = seg.optimize(p_info, &ei).await;
         for c_seg in c_segs {
             new_segs.push(c_seg);
         }
     }
     let tmf_segs = new_segs;
+    // Segments are encoded by value, so data they were made from must be kept to measure errors.
+    let sources = match report {
+        Some(_) => tmf_segs.clone(),
+        None => Vec::new(),
+    };
     let mut encoded = Vec::with_capacity(tmf_segs.len());
     for seg in tmf_segs {
         encoded.push(seg.encode(p_info, &ei));
     }
-    let encoded = futures::future::join_all(encoded).await;
-    target.write_all(&(encoded.len() as u16).to_le_bytes())?;
-    for seg in encoded {
-        seg?.write(target)?;
+    let encoded = futures::future::join_all(encoded)
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()?;
+    let seg_count =
+        u16::try_from(encoded.len()).map_err(|_| TMFExportError::TooManySegments(encoded.len()))?;
+    let segment_reports = match report {
+        Some(_) => Some(
+            sources
+                .iter()
+                .zip(&encoded)
+                .map(|(source, seg)| SegmentEncodeReport::measure(source, seg, &ei))
+                .collect::<Result<_, _>>()
+                .map_err(TMFExportError::MeasurementFailed)?,
+        ),
+        None => None,
+    };
+    write_mesh_name(target, name)?;
+    target.write_all(&seg_count.to_le_bytes())?;
+    for seg in &encoded {
+        seg.write(target, checksums)?;
     }
-    Ok(())
+    if let (Some(report), Some(segments)) = (report, segment_reports) {
+        report.meshes.push(MeshEncodeReport {
+            name: name.to_owned(),
+            segments,
+        });
+    }
+    Ok(encoded
+        .iter()
+        .map(|seg| seg.compresion_type().min_minor())
+        .fold(MIN_TMF_MINOR, u16::max))
 }
 pub(crate) async fn write_tmf<W: std::io::Write, S: std::borrow::Borrow<str>>(
-    meshes_names: &[(TMFMesh, S)],
+    meshes_names: &[(TMFMeshRef<'_>, S)],
     target: &mut W,
     p_info: &TMFPrecisionInfo,
+    mut report: Option<&mut EncodeReport>,
 ) -> Result<(), TMFExportError> {
-    let mesh_count = meshes_names.len();
-    write_tmf_header(target, mesh_count as u32)?;
+    let mesh_count = u32::try_from(meshes_names.len())
+        .map_err(|_| std::io::Error::other("Too many meshes in one file."))?;
+    // The header is written before any mesh is encoded, so the version of reader it requires must be known up front.
+    let min_minor = meshes_names
+        .iter()
+        .map(|(mesh, _)| predict_min_minor(mesh, p_info))
+        .fold(MIN_TMF_MINOR, u16::max);
+    write_tmf_header(target, mesh_count, p_info.checksums, min_minor)?;
     for (mesh, name) in meshes_names {
-        write_mesh(mesh, name.borrow(), target, p_info).await?;
+        let report = report.as_deref_mut();
+        let mesh_min_minor = write_mesh(
+            *mesh,
+            name.borrow(),
+            target,
+            p_info,
+            p_info.checksums,
+            report,
+        )
+        .await?;
+        debug_assert!(
+            mesh_min_minor <= min_minor,
+            "Mesh \"{}\" requires a newer reader than predicted.",
+            name.borrow()
+        );
     }
     Ok(())
 }
+/// Returns the minimum minor version of reader able to read *mesh* saved with *p_info*, without encoding it.
+/// Encodings introduced in 0.3 are picked by size for some data, so a mesh requires a 0.3 reader if any of them could be used.
+fn predict_min_minor(mesh: &TMFMeshRef<'_>, p_info: &TMFPrecisionInfo) -> u16 {
+    use crate::custom_data::CustomData;
+    const NEW_ENCODINGS_MINOR: u16 = 3;
+    // Triangles are saved with whichever compression is the smallest.
+    let has_triangles = mesh.get_vertex_triangles().is_some()
+        || mesh.get_normal_triangles().is_some()
+        || mesh.get_uv_triangles().is_some()
+        || mesh.get_tangent_triangles().is_some();
+    // Predicted vertices are delta coded even without triangles, so they may be smaller.
+    let has_predicted_vertices = p_info.vertex_prediction == VertexPredictionMode::Parallelogram
+        && mesh.get_vertices().is_some();
+    let has_lossless_data = (p_info.vertex_precision.is_lossless()
+        && mesh.get_vertices().is_some())
+        || (p_info.normal_precision.is_lossless() && mesh.get_normals().is_some())
+        || (p_info.uv_prec.is_lossless() && mesh.get_uvs().is_some())
+        || mesh
+            .custom_data()
+            .iter()
+            .any(|seg| matches!(seg.custom_data(), CustomData::CustomLosslessFloat(_)));
+    if has_triangles || has_predicted_vertices || has_lossless_data {
+        NEW_ENCODINGS_MINOR
+    } else {
+        MIN_TMF_MINOR
+    }
+}
+/// Size of the header, up to and including mesh count.
+pub(crate) const TMF_HEADER_SIZE: u64 = 15;
+/// Size of the header of files with header flags, which require readers of at least [`FLAGS_TMF_MINOR`] version.
+pub(crate) const MAX_TMF_HEADER_SIZE: u64 = TMF_HEADER_SIZE + std::mem::size_of::<u8>() as u64;
+/// Returns the size of the header of a file readable by readers of at least *min_minor* version.
+pub(crate) fn tmf_header_size(min_minor: u16) -> u64 {
+    if min_minor >= FLAGS_TMF_MINOR {
+        MAX_TMF_HEADER_SIZE
+    } else {
+        TMF_HEADER_SIZE
+    }
+}
+/// Returns the size of the header at the beginning of *file*.
+#[cfg(test)]
+pub(crate) fn header_size_of(file: &[u8]) -> usize {
+    tmf_header_size(u16::from_le_bytes([file[9], file[10]])) as usize
+}
+/// Writes the header of a file with *mesh_count* meshes, readable by readers of at least *min_minor* version. Files with *checksums* are flagged in header flags, and require a newer reader.
 pub(crate) fn write_tmf_header<W: std::io::Write>(
     w: &mut W,
     mesh_count: u32,
+    checksums: bool,
+    min_minor: u16,
 ) -> Result<(), TMFExportError> {
+    let (min_minor, flags) = if checksums {
+        (min_minor.max(CHECKSUM_TMF_MINOR), CHECKSUMS_FLAG)
+    } else {
+        (min_minor, 0)
+    };
     w.write_all(b"TMF")?;
     w.write_all(&TMF_MAJOR.to_le_bytes())?;
     w.write_all(&(TMF_MINOR).to_le_bytes())?;
     w.write_all(&MIN_TMF_MAJOR.to_le_bytes())?;
-    w.write_all(&(MIN_TMF_MINOR).to_le_bytes())?;
-    Ok(w.write_all(&mesh_count.to_le_bytes())?)
+    w.write_all(&min_minor.to_le_bytes())?;
+    w.write_all(&mesh_count.to_le_bytes())?;
+    // Older readers don't expect flags, so they are only written in files those readers refuse.
+    if min_minor >= FLAGS_TMF_MINOR {
+        w.write_all(&[flags])?;
+    }
+    Ok(())
+}
+/// Minimum minor version of reader required by files written by [`TMFWriter`], high enough for any mesh. Its header is rewritten in place, so its size, which depends on this version, can't change.
+const TMFWRITER_MIN_MINOR: u16 = TMF_MINOR;
+/// Writer of .tmf files, which writes meshes one by one, as they are created. Mesh count is written when the writer is finished.
+/// Compression of meshes is not known when the header is written, so files written by it always require a reader of the current version.
+/// # Example
+/// ```
+/// # use std::fs::File;
+/// # use tmf::{TMFMesh, TMFPrecisionInfo, TMFWriter};
+/// # fn generate_mesh(_: usize) -> TMFMesh { TMFMesh::empty() }
+/// # let path = "target/test_res/doc_writer_out.tmf";
+/// # std::fs::create_dir_all("target/test_res").unwrap();
+/// let output = File::create(path).expect("Could not create file!");
+/// let mut writer = TMFWriter::new(output).expect("Could not write .tmf header!");
+/// let precision_info = TMFPrecisionInfo::default();
+/// for index in 0..16 {
+///     let mesh = generate_mesh(index);
+///     writer
+///         .write_mesh(&mesh, &format!("mesh{index}"), &precision_info)
+///         .expect("Could not write mesh!");
+/// }
+/// writer.finish().expect("Could not finish .tmf file!");
+/// ```
+pub struct TMFWriter<W: std::io::Write + std::io::Seek> {
+    target: W,
+    /// Position of the header within *target*.
+    start: u64,
+    mesh_count: u32,
+    /// Names, offsets and lengths of written meshes, if a table of contents should be written.
+    toc: Option<Vec<(String, u64, u64)>>,
+    /// Are segments written with checksums. Decided by the first written mesh, and required to be the same for all others.
+    checksums: Option<bool>,
+    /// Set if writing to *target* failed, leaving an incomplete mesh in it.
+    poisoned: bool,
+}
+impl<W: std::io::Write + std::io::Seek> TMFWriter<W> {
+    /// Writes header of a .tmf file to *target*, at its current position. Until [`Self::finish`] is called, the file is valid, but has no meshes.
+    /// # Errors
+    /// Returns an IO error if it occurs.
+    pub fn new(mut target: W) -> Result<Self, TMFExportError> {
+        let start = target.stream_position()?;
+        write_tmf_header(&mut target, 0, false, TMFWRITER_MIN_MINOR)?;
+        Ok(Self {
+            target,
+            start,
+            mesh_count: 0,
+            toc: None,
+            checksums: None,
+            poisoned: false,
+        })
+    }
+    /// Like [`Self::new`], but [`Self::finish`] will also write a table of contents after all meshes, allowing them to be loaded by name using [`crate::TMFFile`].
+    /// Readers not supporting tables of contents ignore it.
+    /// # Errors
+    /// Returns an IO error if it occurs.
+    pub fn with_table_of_contents(target: W) -> Result<Self, TMFExportError> {
+        let mut writer = Self::new(target)?;
+        writer.toc = Some(Vec::new());
+        Ok(writer)
+    }
+    /// Writes *mesh* named *name*, using settings from *p_info*. *mesh* may be a [`TMFMesh`] or a [`TMFMeshRef`] to borrowed data.
+    /// Checksums are enabled for the whole file, so [`TMFPrecisionInfo::checksums`] must be the same for all meshes.
+    /// The mesh is encoded in memory first, so if it can't be saved nothing is written, and the writer can still be used.
+    /// # Errors
+    /// Returns an IO error if it occurs, or if there are too many meshes to be saved in one file, [`TMFExportError::ChecksumsMismatch`] if checksums are enabled for other meshes of the file but not for this one or vice versa, or a [`TMFExportError`] describing why the mesh can't be saved.
+    /// After an IO error while writing to the underlying writer, the file is left incomplete, and all further calls return an error.
+    pub fn write_mesh<'a>(
+        &mut self,
+        mesh: impl Into<TMFMeshRef<'a>>,
+        name: &str,
+        p_info: &TMFPrecisionInfo,
+    ) -> Result<(), TMFExportError> {
+        self.check_poisoned()?;
+        let mesh_count = self
+            .mesh_count
+            .checked_add(1)
+            .ok_or_else(|| std::io::Error::other("Too many meshes in one file."))?;
+        let checksums = p_info.checksums;
+        if self.checksums.is_some_and(|enabled| enabled != checksums) {
+            return Err(TMFExportError::ChecksumsMismatch);
+        }
+        let mut encoded = Vec::new();
+        let min_minor = runtime_agnostic_block_on!(write_mesh(
+            mesh.into(),
+            name,
+            &mut encoded,
+            p_info,
+            checksums,
+            None
+        ))?;
+        let mesh_start = self.target.stream_position()?;
+        self.poisoned = true;
+        self.target.write_all(&encoded)?;
+        self.poisoned = false;
+        if let Some(toc) = &mut self.toc {
+            toc.push((
+                name.to_owned(),
+                mesh_start - self.start,
+                encoded.len() as u64,
+            ));
+        }
+        debug_assert!(min_minor <= TMFWRITER_MIN_MINOR);
+        self.checksums = Some(checksums);
+        self.mesh_count = mesh_count;
+        Ok(())
+    }
+    /// Returns an error if a previous write failed, leaving the file incomplete.
+    fn check_poisoned(&self) -> Result<(), TMFExportError> {
+        if self.poisoned {
+            return Err(std::io::Error::other(
+                "A previous write failed, so the .tmf file is incomplete.",
+            )
+            .into());
+        }
+        Ok(())
+    }
+    /// Returns the amount of meshes written so far.
+    pub fn mesh_count(&self) -> u32 {
+        self.mesh_count
+    }
+    /// Writes the mesh count and the table of contents if enabled, and returns the underlying writer, positioned after the end of the file.
+    /// # Errors
+    /// Returns an IO error if it occurs, or if a previous write failed.
+    pub fn finish(mut self) -> Result<W, TMFExportError> {
+        use std::io::SeekFrom;
+        self.check_poisoned()?;
+        if let Some(toc) = &self.toc {
+            let toc_offset = self.target.stream_position()? - self.start;
+            self.target.write_all(&(toc.len() as u32).to_le_bytes())?;
+            for (name, offset, length) in toc {
+                write_mesh_name(&mut self.target, name)?;
+                self.target.write_all(&offset.to_le_bytes())?;
+                self.target.write_all(&length.to_le_bytes())?;
+            }
+            self.target.write_all(&toc_offset.to_le_bytes())?;
+            self.target.write_all(&crate::TOC_MAGIC)?;
+        }
+        let end = self.target.stream_position()?;
+        self.target.seek(SeekFrom::Start(self.start))?;
+        let checksums = self.checksums.unwrap_or(false);
+        write_tmf_header(
+            &mut self.target,
+            self.mesh_count,
+            checksums,
+            TMFWRITER_MIN_MINOR,
+        )?;
+        self.target.seek(SeekFrom::Start(end))?;
+        self.target.flush()?;
+        Ok(self.target)
+    }
+}
+#[test]
+#[cfg(feature = "obj_import")]
+fn write_meshes_one_by_one() {
+    use std::io::Cursor;
+    let (tmf_mesh, name) = crate::susan();
+    let prec = TMFPrecisionInfo::default();
+    // Data before the header should be left untouched.
+    let mut target = Cursor::new(vec![0xFF; 4]);
+    target.set_position(4);
+    let mut writer = TMFWriter::new(target).unwrap();
+    for index in 0..3 {
+        writer
+            .write_mesh(&tmf_mesh, &format!("{name}{index}"), &prec)
+            .unwrap();
+        // Meshes which can't be saved are skipped, without damaging the file.
+        let mut invalid = TMFMesh::empty();
+        invalid.set_vertices(Vec::new());
+        assert!(writer.write_mesh(&invalid, "invalid", &prec).is_err());
+    }
+    assert_eq!(writer.mesh_count(), 3);
+    let out = writer.finish().unwrap().into_inner();
+    assert_eq!(out[..4], [0xFF; 4]);
+    let meshes = TMFMesh::read_tmf(&mut &out[4..]).unwrap();
+    assert_eq!(meshes.len(), 3);
+    for (index, (mesh, r_name)) in meshes.iter().enumerate() {
+        assert_eq!(*r_name, format!("{name}{index}"));
+        assert_eq!(mesh.get_vertex_triangles(), tmf_mesh.get_vertex_triangles());
+    }
+}
+#[test]
+fn poison_writer_after_failed_write() {
+    use std::io::Cursor;
+    let mut mesh = TMFMesh::empty();
+    mesh.set_vertices([(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (0.0, 1.0, 0.0)]);
+    mesh.set_vertex_triangles([0, 1, 2]);
+    let prec = TMFPrecisionInfo::default();
+    // Only the header and a part of the mesh fit.
+    let mut out = [0; MAX_TMF_HEADER_SIZE as usize + 4];
+    let mut writer = TMFWriter::new(Cursor::new(&mut out[..])).unwrap();
+    assert!(matches!(
+        writer.write_mesh(&mesh, "a", &prec),
+        Err(TMFExportError::IO(_))
+    ));
+    assert!(writer.write_mesh(&TMFMesh::empty(), "b", &prec).is_err());
+    assert!(writer.finish().is_err());
 }
 #[cfg(test)]
 fn init_test_env() {
     std::fs::create_dir_all("target/test_res").unwrap();
 }
+/// Checks if *triangles* are just indices of all elements of a data array with length *len*, in order.
+fn is_identity(triangles: &[IndexType], len: usize) -> bool {
+    triangles.len() == len
+        && triangles
+            .iter()
+            .enumerate()
+            .all(|(index, tri)| *tri as usize == index)
+}
+/// Returns an omitted segment of type *seg_type* if *triangles* can be derived from *data*, or a segment created by *seg* otherwise.
+fn omit_identity<'a, T>(
+    triangles: &'a [IndexType],
+    data: Option<&[T]>,
+    seg_type: SectionType,
+    seg: fn(Cow<'a, [IndexType]>) -> DecodedSegment<'a>,
+) -> DecodedSegment<'a> {
+    match data {
+        Some(data) if is_identity(triangles, data.len()) => {
+            DecodedSegment::OmittedTriangles(seg_type)
+        }
+        _ => seg(triangles.into()),
+    }
+}
 struct MeshSegIter<'a> {
-    mesh: &'a TMFMesh,
+    mesh: TMFMeshRef<'a>,
     item: usize,
 }
 impl<'a> MeshSegIter<'a> {
-    fn tmf_segs(mesh: &'a TMFMesh) -> Self {
+    fn tmf_segs(mesh: TMFMeshRef<'a>) -> Self {
         Self { mesh, item: 0 }
     }
 }
 impl<'a> std::iter::Iterator for MeshSegIter<'a> {
-    type Item = DecodedSegment;
+    type Item = DecodedSegment<'a>;
     fn next(&mut self) -> Option<Self::Item> {
         self.item += 1;
         match self.item {
@@ -170,11 +585,21 @@ impl<'a> std::iter::Iterator for MeshSegIter<'a> {
                 None => self.next(),
             },
             5 => match self.mesh.get_normal_triangles() {
-                Some(tris) => Some(DecodedSegment::AppendTriangleNormal(tris.into())),
+                Some(tris) => Some(omit_identity(
+                    tris,
+                    self.mesh.get_normals(),
+                    SectionType::NormalTriangleSegment,
+                    DecodedSegment::AppendTriangleNormal,
+                )),
                 None => self.next(),
             },
             6 => match self.mesh.get_uv_triangles() {
-                Some(tris) => Some(DecodedSegment::AppendTriangleUV(tris.into())),
+                Some(tris) => Some(omit_identity(
+                    tris,
+                    self.mesh.get_uvs(),
+                    SectionType::UvTriangleSegment,
+                    DecodedSegment::AppendTriangleUV,
+                )),
                 None => self.next(),
             },
             7 => match self.mesh.get_tangents() {
@@ -182,13 +607,27 @@ impl<'a> std::iter::Iterator for MeshSegIter<'a> {
                 None => self.next(),
             },
             8 => match self.mesh.get_tangent_triangles() {
-                Some(tans) => Some(DecodedSegment::AppendTriangleTangent(tans.into())),
+                Some(tans) => Some(omit_identity(
+                    tans,
+                    self.mesh.get_tangents(),
+                    SectionType::TangentTriangleSegment,
+                    DecodedSegment::AppendTriangleTangent,
+                )),
                 None => self.next(),
             },
             9..=usize::MAX => {
                 let index = self.item - 9;
-                let seg = self.mesh.custom_data.get(index)?;
-                Some(DecodedSegment::AppendCustom(seg.clone()))
+                let custom_data = self.mesh.custom_data();
+                match custom_data.get(index) {
+                    Some(seg) => Some(DecodedSegment::AppendCustom(Cow::Borrowed(seg))),
+                    None => {
+                        let raw = self
+                            .mesh
+                            .get_raw_segments()
+                            .get(index - custom_data.len())?;
+                        Some(DecodedSegment::Raw(Cow::Borrowed(raw)))
+                    }
+                }
             }
             //Should never happen.
             _ => todo!(),
@@ -199,19 +638,168 @@ impl<'a> std::iter::Iterator for MeshSegIter<'a> {
 #[cfg(feature = "obj_import")]
 fn rw_susan_tmf() {
     init_test_env();
-    let mut file = std::fs::File::open("testing/susan.obj").unwrap();
-    let (tmf_mesh, name) = TMFMesh::read_from_obj_one(&mut file).unwrap();
+    let (tmf_mesh, name) = crate::susan();
     tmf_mesh.verify().unwrap();
     assert!(name == "Suzanne", "Name should be Suzanne but is {name}");
     let prec = TMFPrecisionInfo::default();
     let mut out = Vec::new();
     {
-        futures::executor::block_on(write_tmf(&[(tmf_mesh, name)], &mut out, &prec)).unwrap();
+        futures::executor::block_on(write_tmf(
+            &[(TMFMeshRef::from(&tmf_mesh), name)],
+            &mut out,
+            &prec,
+            None,
+        ))
+        .unwrap();
     }
     let (r_mesh, name) = TMFMesh::read_tmf_one(&mut (&out as &[u8])).unwrap();
     assert!(name == "Suzanne", "Name should be Suzanne but is {name}");
     r_mesh.verify().unwrap();
 }
+#[test]
+fn omit_identity_triangles() {
+    let mut mesh = TMFMesh::empty();
+    mesh.set_vertices([
+        (0.0, 0.0, 0.0),
+        (1.0, 0.0, 0.0),
+        (0.0, 1.0, 0.0),
+        (1.0, 1.0, 0.0),
+    ]);
+    mesh.set_vertex_triangles([0, 1, 2, 2, 1, 3]);
+    let normal = (0.0, 0.0, 1.0);
+    mesh.set_normals([normal; 6]);
+    mesh.set_normal_triangles([0, 1, 2, 3, 4, 5]);
+    mesh.set_uvs([(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)]);
+    mesh.set_uv_triangles([0, 1, 2, 2, 1, 3]);
+    let omitted = MeshSegIter::tmf_segs((&mesh).into())
+        .filter(|seg| matches!(seg, DecodedSegment::OmittedTriangles(_)))
+        .count();
+    assert_eq!(omitted, 1);
+    let mut out = Vec::new();
+    mesh.write_tmf_one(&mut out, &TMFPrecisionInfo::default(), "quad")
+        .unwrap();
+    let (r_mesh, _) = TMFMesh::read_tmf_one(&mut (&out as &[u8])).unwrap();
+    r_mesh.verify().unwrap();
+    assert_eq!(mesh.get_normal_triangles(), r_mesh.get_normal_triangles());
+    assert_eq!(mesh.get_vertex_triangles(), r_mesh.get_vertex_triangles());
+    assert_eq!(mesh.get_uv_triangles(), r_mesh.get_uv_triangles());
+}
+#[test]
+fn require_reader_of_used_compression() {
+    use crate::{CompressionType, TMFWriter, VertexPrecisionMode};
+    let min_minor = |out: &[u8]| u16::from_le_bytes([out[9], out[10]]);
+    let mut mesh = TMFMesh::empty();
+    mesh.set_vertices([(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (0.0, 1.0, 0.0)]);
+    let prec = TMFPrecisionInfo::default();
+    let mut out = Vec::new();
+    mesh.write_tmf_one(&mut out, &prec, "plain").unwrap();
+    assert_eq!(min_minor(&out), MIN_TMF_MINOR);
+    // Triangles may be saved with any compression, so they require a reader of all of them.
+    let mut triangle = mesh.clone();
+    triangle.set_vertex_triangles([0, 1, 2]);
+    let mut out = Vec::new();
+    triangle.write_tmf_one(&mut out, &prec, "triangle").unwrap();
+    assert_eq!(min_minor(&out), CompressionType::Sequence.min_minor());
+    // Compressed segments can't be read by readers older than 0.3.
+    let lossless = TMFPrecisionInfo {
+        vertex_precision: VertexPrecisionMode::LOSSLESS,
+        ..Default::default()
+    };
+    let mut out = Vec::new();
+    mesh.write_tmf_one(&mut out, &lossless, "lossless").unwrap();
+    assert_eq!(min_minor(&out), CompressionType::Lossless.min_minor());
+    assert!(min_minor(&out) > MIN_TMF_MINOR);
+    // A writer can't know compression of meshes written after its header, so it always requires the newest reader.
+    let mut writer = TMFWriter::new(std::io::Cursor::new(Vec::new())).unwrap();
+    writer.write_mesh(&mesh, "plain", &prec).unwrap();
+    let out = writer.finish().unwrap().into_inner();
+    assert_eq!(min_minor(&out), TMF_MINOR);
+    let mut writer = TMFWriter::new(std::io::Cursor::new(Vec::new())).unwrap();
+    writer.write_mesh(&mesh, "plain", &prec).unwrap();
+    writer.write_mesh(&mesh, "lossless", &lossless).unwrap();
+    let out = writer.finish().unwrap().into_inner();
+    assert_eq!(min_minor(&out), CompressionType::Lossless.min_minor());
+    assert_eq!(TMFMesh::read_tmf(&mut &out[..]).unwrap().len(), 2);
+}
+#[test]
+fn split_vertices_with_huge_spans() {
+    use crate::VertexPrecisionMode;
+    let prec = TMFPrecisionInfo {
+        vertex_precision: VertexPrecisionMode::absolute(1.0),
+        ..Default::default()
+    };
+    let huge = 3e38;
+    let mut vertices: Vec<Vector3> = (0..64)
+        .map(|index| {
+            let sign = if index % 2 == 0 { 1.0 } else { -1.0 };
+            (sign * huge, -sign * huge, index as FloatType)
+        })
+        .collect();
+    let mut mesh = TMFMesh::empty();
+    mesh.set_vertices(&vertices[..]);
+    // Such a span can't be quantized, since it does not fit in a float.
+    let write = |mesh: &TMFMesh| mesh.write_tmf_one(&mut Vec::new(), &prec, "huge");
+    assert!(matches!(
+        write(&mesh),
+        Err(TMFExportError::VertexSpanNotFinite)
+    ));
+    // Halved, it fits, and bit counts of its parts must not overflow while looking for splits.
+    for vertex in &mut vertices {
+        vertex.0 /= 2.0;
+        vertex.1 /= 2.0;
+    }
+    mesh.set_vertices(&vertices[..]);
+    write(&mesh).unwrap();
+    vertices[3].1 = FloatType::INFINITY;
+    mesh.set_vertices(vertices);
+    assert!(matches!(
+        write(&mesh),
+        Err(TMFExportError::VertexSpanNotFinite)
+    ));
+    // Bit counts saturate instead of overflowing.
+    let span = (-huge, -huge, -huge)..(huge, huge, FloatType::INFINITY);
+    assert_eq!(range_to_vertex_bit_count(span, [1.0; 3]), u8::MAX);
+}
+#[test]
+fn reject_unsavable_meshes() {
+    let prec = TMFPrecisionInfo::default();
+    let write = |mesh: &TMFMesh, name: &str, prec: &TMFPrecisionInfo| {
+        let mut out = Vec::new();
+        let err = mesh.write_tmf_one(&mut out, prec, name).err().unwrap();
+        // Nothing is written for a mesh which can't be saved.
+        assert_eq!(out.len(), header_size_of(&out));
+        err
+    };
+    let mut mesh = TMFMesh::empty();
+    mesh.set_vertices(Vec::new());
+    assert!(matches!(
+        write(&mesh, "empty", &prec),
+        TMFExportError::NoVertices
+    ));
+    mesh.set_vertices([(0.0, 0.0, 0.0), (1.0, 0.0, 0.0)]);
+    // Not a single full triangle, so there are no edges.
+    mesh.set_vertex_triangles([0, 1]);
+    assert!(matches!(
+        write(&mesh, "edgeless", &prec),
+        TMFExportError::InfiniteShortestEdge(edge) if edge == FloatType::INFINITY
+    ));
+    let name = "n".repeat(u16::MAX as usize + 1);
+    assert!(matches!(
+        write(&TMFMesh::empty(), &name, &prec),
+        TMFExportError::NameTooLong(len) if len == name.len()
+    ));
+    let mut mesh = TMFMesh::empty();
+    mesh.set_vertices([(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (0.0, 1.0, 0.0)]);
+    mesh.set_vertex_triangles([0, 1, 3]);
+    let verify = TMFPrecisionInfo {
+        verify: true,
+        ..Default::default()
+    };
+    assert!(matches!(
+        write(&mesh, "invalid", &verify),
+        TMFExportError::InvalidMesh(crate::TMFIntegrityStatus::IndexOutsideVertexArray(3, 3))
+    ));
+}
 const TMF_SEG_SIZE: usize =
     std::mem::size_of::<u8>() + std::mem::size_of::<u32>() + std::mem::size_of::<u8>();
 fn calc_spilt_score(len: usize, delta_span: (IndexType, IndexType)) -> isize {
@@ -224,7 +812,54 @@ fn calc_spilt_score(len: usize, delta_span: (IndexType, IndexType)) -> isize {
         ((TMF_SEG_SIZE + std::mem::size_of::<u8>() + std::mem::size_of::<u32>()) * 8) as isize;
     gain - loss
 }
+/// Size of data stored in a sequence segment: its first index and its length.
+const SEQUENCE_SIZE: usize = 2 * std::mem::size_of::<u64>();
+/// Calculates how many bits are saved by storing a run of *len* consecutive indices as a separate sequence segment, instead of storing them with *index_prec* bits each.
+fn calc_sequence_score(len: usize, index_prec: u8) -> isize {
+    let gain = (len * index_prec as usize) as isize;
+    // Splitting out a sequence may also split the segment it was in into two.
+    let loss = ((2 * TMF_SEG_SIZE
+        + SEQUENCE_SIZE
+        + std::mem::size_of::<u8>()
+        + 2 * std::mem::size_of::<u64>())
+        * 8) as isize;
+    gain - loss
+}
+/// Checks if *triangles* are a sequence, with each index being greater by exactly one than the previous one.
+pub(crate) fn is_sequence(triangles: &[IndexType]) -> bool {
+    !triangles.is_empty()
+        && triangles
+            .windows(2)
+            .all(|pair| pair[0].checked_add(1) == Some(pair[1]))
+}
+/// Splits *triangles* into segments, separating out long runs of sequential indices, which can be saved as sequence segments.
 pub(crate) fn opt_tris(triangles: &[IndexType]) -> SmallVec<[&[IndexType]; 4]> {
+    use crate::vertices::calc_prec;
+    let index_prec = calc_prec(*triangles.iter().max().unwrap_or(&0) as usize);
+    let mut res = SmallVec::new();
+    let mut rest_start = 0;
+    let mut run_start = 0;
+    for index in 1..=triangles.len() {
+        let continues_run = index < triangles.len()
+            && triangles[index - 1].checked_add(1) == Some(triangles[index]);
+        if continues_run {
+            continue;
+        }
+        if calc_sequence_score(index - run_start, index_prec) > 0 {
+            if rest_start < run_start {
+                res.extend(opt_tris_spans(&triangles[rest_start..run_start]));
+            }
+            res.push(&triangles[run_start..index]);
+            rest_start = index;
+        }
+        run_start = index;
+    }
+    if rest_start < triangles.len() || res.is_empty() {
+        res.extend(opt_tris_spans(&triangles[rest_start..]));
+    }
+    res
+}
+fn opt_tris_spans(triangles: &[IndexType]) -> SmallVec<[&[IndexType]; 4]> {
     let mut best_score = isize::MIN;
     let mut best_index = usize::MIN;
     let mut max_index = IndexType::MIN;
@@ -240,11 +875,11 @@ pub(crate) fn opt_tris(triangles: &[IndexType]) -> SmallVec<[&[IndexType]; 4]> {
     if best_score > 0 {
         let mut res = SmallVec::new();
         let (s1, s2) = triangles.split_at(best_index);
-        let r_1 = opt_tris(s1);
+        let r_1 = opt_tris_spans(s1);
         for seg in r_1 {
             res.push(seg);
         }
-        let r_2 = opt_tris(s2);
+        let r_2 = opt_tris_spans(s2);
         for seg in r_2 {
             res.push(seg);
         }
@@ -265,11 +900,11 @@ pub(crate) fn opt_tris(triangles: &[IndexType]) -> SmallVec<[&[IndexType]; 4]> {
     if best_score > 0 {
         let mut res = SmallVec::new();
         let (s1, s2) = triangles.split_at(best_index);
-        let r_1 = opt_tris(s1);
+        let r_1 = opt_tris_spans(s1);
         for seg in r_1 {
             res.push(seg);
         }
-        let r_2 = opt_tris(s2);
+        let r_2 = opt_tris_spans(s2);
         for seg in r_2 {
             res.push(seg);
         }
@@ -278,17 +913,19 @@ pub(crate) fn opt_tris(triangles: &[IndexType]) -> SmallVec<[&[IndexType]; 4]> {
         smallvec![triangles]
     }
 }
-fn range_to_vertex_bit_count(span: std::ops::Range<Vector3>, shortest_edge: FloatType) -> u8 {
-    let dx = span.end.0 - span.start.0;
-    let dy = span.end.1 - span.start.1;
-    let _dz = span.end.2 - span.start.2;
-    let inc_x = shortest_edge / dx;
-    let inc_y = shortest_edge / dy;
-    let inc_z = shortest_edge / dy;
-    let prec_x = (1.0 / inc_x).log2().ceil() as u8;
-    let prec_y = (1.0 / inc_y).log2().ceil() as u8;
-    let prec_z = (1.0 / inc_z).log2().ceil() as u8;
-    prec_x + prec_y + prec_z
+/// Estimates how many bits a vertex inside *span* is saved with, if each coordinate may deviate by *max_deviations* along its axis.
+fn range_to_vertex_bit_count(span: std::ops::Range<Vector3>, max_deviations: [FloatType; 3]) -> u8 {
+    let spans = [
+        span.end.0 - span.start.0,
+        span.end.1 - span.start.1,
+        span.end.2 - span.start.2,
+    ];
+    // Float to int casts saturate, so huge or infinite spans can't overflow bit counts.
+    spans
+        .iter()
+        .zip(max_deviations)
+        .map(|(span, deviation)| (span / deviation).log2().ceil() as u8)
+        .fold(0, u8::saturating_add)
 }
 fn expand_vertex_span(span: std::ops::Range<Vector3>, point: Vector3) -> std::ops::Range<Vector3> {
     let min = span.start;
@@ -305,13 +942,13 @@ fn inside_span(span: &std::ops::Range<Vector3>, item: &Vector3) -> bool {
         && span.start.2 < item.2
         && item.2 < span.end.2
 }
-fn find_best_vertex_spilt(vertices: &[Vector3], shortest_edge: FloatType) -> Option<usize> {
+fn find_best_vertex_spilt(vertices: &[Vector3], max_deviations: [FloatType; 3]) -> Option<usize> {
     let mut total_span = (0.0, 0.0, 0.0)..(0.0, 0.0, 0.0);
     vertices.iter().for_each(|point| {
         total_span = expand_vertex_span(total_span.clone(), *point);
     });
     let total_span = total_span;
-    let total_per_vertex_bit_count = range_to_vertex_bit_count(total_span, shortest_edge);
+    let total_per_vertex_bit_count = range_to_vertex_bit_count(total_span, max_deviations);
     let mut best_split_score = isize::MIN;
     let mut best_split_index = usize::MIN;
     let mut min_span = (0.0, 0.0, 0.0)..(0.0, 0.0, 0.0);
@@ -319,23 +956,22 @@ fn find_best_vertex_spilt(vertices: &[Vector3], shortest_edge: FloatType) -> Opt
     for (index, vertex) in vertices.iter().enumerate() {
         if !inside_span(&min_span, vertex) {
             min_span = expand_vertex_span(min_span, *vertex);
-            per_vertex_bit_count = range_to_vertex_bit_count(min_span.clone(), shortest_edge);
-
+            per_vertex_bit_count = range_to_vertex_bit_count(min_span.clone(), max_deviations);
         } else {
-            let gain_per_vert = total_per_vertex_bit_count - per_vertex_bit_count;
+            let gain_per_vert = total_per_vertex_bit_count.saturating_sub(per_vertex_bit_count);
             let gain = index * (gain_per_vert as usize);
             let loss = TMF_SEG_SIZE
                 + std::mem::size_of::<u64>()
                 + 6 * std::mem::size_of::<f64>()
                 + 3 * std::mem::size_of::<u8>();
             let score = gain as isize - (loss as isize);
-      
+
             if score > best_split_score {
                 best_split_index = index;
                 best_split_score = score;
             }
         }
-    }    
+    }
     if best_split_score > 0 {
         assert!(best_split_index < vertices.len() - 1, "The best split index was the last one, but the gain from the split {best_split_score} was greater than 0, suggesting a logic bug");
         Some(best_split_index)
@@ -343,17 +979,20 @@ fn find_best_vertex_spilt(vertices: &[Vector3], shortest_edge: FloatType) -> Opt
         None
     }
 }
-pub(crate) fn opt_vertices(vertices: &[Vector3]) -> SmallVec<[&[Vector3]; 4]> {
-    let _len = vertices.len();
-    let split_pos = find_best_vertex_spilt(vertices, 0.01);
+/// Splits *vertices* into parts with smaller bounding boxes, if it makes them smaller when saved with *max_deviations*.
+pub(crate) fn opt_vertices(
+    vertices: &[Vector3],
+    max_deviations: [FloatType; 3],
+) -> SmallVec<[&[Vector3]; 4]> {
+    let split_pos = find_best_vertex_spilt(vertices, max_deviations);
     if let Some(split_pos) = split_pos {
         let (i0, i1) = vertices.split_at(split_pos);
         let mut res = SmallVec::new();
-        let r_0 = opt_vertices(i0);
+        let r_0 = opt_vertices(i0, max_deviations);
         for seg in r_0 {
             res.push(seg);
         }
-        let r_1 = opt_vertices(i1);
+        let r_1 = opt_vertices(i1, max_deviations);
         for seg in r_1 {
             res.push(seg);
         }
@@ -1,3 +1,4 @@
+use crate::inspect::TMFSegmentInfo;
 use crate::read_extension::ReadExt;
 use crate::tmf::CompressionType;
 use crate::tmf::{DecodedSegment, EncodedSegment, SectionType};
@@ -6,9 +7,14 @@ use crate::unaligned_rw::UnalignedReader;
 use crate::CustomDataSegment;
 use crate::IndexType;
 use crate::MAX_SEG_SIZE;
-use crate::{TMFImportError, TMFMesh, TMF_MAJOR, TMF_MINOR};
+use crate::{
+    ImportLimit, ImportLimits, TMFErrorLocation, TMFImportError, TMFImportOptions, TMFMesh,
+    TMFMeshBuffers, TMF_MAJOR, TMF_MINOR,
+};
 use futures::future::join_all;
-use std::io::Read;
+use futures::{AsyncRead, AsyncReadExt};
+use std::borrow::Cow;
+use std::io::{Read, Seek, SeekFrom};
 #[derive(Clone, Copy)]
 pub(crate) enum SegLenWidth {
     U32,
@@ -28,6 +34,13 @@ impl SegLenWidth {
             Self::U64 => src.read_u64()? as usize,
         })
     }
+    /// Size of segment length in bytes.
+    fn size(&self) -> usize {
+        match self {
+            Self::U32 => std::mem::size_of::<u32>(),
+            Self::U64 => std::mem::size_of::<u64>(),
+        }
+    }
 }
 #[derive(Clone, Copy)]
 pub(crate) enum SegTypeWidth {
@@ -42,26 +55,44 @@ impl SegTypeWidth {
             Self::U16
         }
     }
-    pub(crate) fn read<R: Read>(&self, src: &mut R) -> std::io::Result<SectionType> {
+    /// Reads type id of a segment. Use [`SectionType::from_u16`] to get its type.
+    pub(crate) fn read<R: Read>(&self, src: &mut R) -> std::io::Result<u16> {
         Ok(match self {
-            Self::U8 => SectionType::from_u8(src.read_u8()?),
-            Self::U16 => SectionType::from_u16(src.read_u16()?),
+            Self::U8 => src.read_u8()?.into(),
+            Self::U16 => src.read_u16()?,
         })
     }
+    /// Size of segment type in bytes.
+    fn size(&self) -> usize {
+        match self {
+            Self::U8 => std::mem::size_of::<u8>(),
+            Self::U16 => std::mem::size_of::<u16>(),
+        }
+    }
 }
 #[derive(Clone)]
 pub(crate) struct TMFImportContext {
     segment_length_width: SegLenWidth,
     segment_type_width: SegTypeWidth,
     should_read_min_index: bool,
+    /// Are segments saved with checksums of their data.
+    has_checksums: bool,
+}
+pub(crate) struct TMFHeader {
+    pub(crate) major: u16,
+    pub(crate) minor: u16,
+    pub(crate) min_major: u16,
+    pub(crate) min_minor: u16,
+    /// Amount of meshes in the file.
+    pub(crate) mesh_count: u32,
+    /// Are segments saved with checksums of their data.
+    pub(crate) checksums: bool,
 }
-// While some of those fileds are not read yet, they may be relevant in the future.
-#[allow(dead_code)]
-struct TMFHeader {
-    major: u16,
-    minor: u16,
-    min_major: u16,
-    min_minor: u16,
+impl TMFHeader {
+    /// Size of this header in bytes.
+    pub(crate) fn size(&self) -> u64 {
+        crate::tmf_exporter::tmf_header_size(self.min_minor)
+    }
 }
 pub(crate) fn read_string<R: Read>(src: &mut R) -> std::io::Result<String> {
     let byte_len = src.read_u16()?;
@@ -69,13 +100,13 @@ pub(crate) fn read_string<R: Read>(src: &mut R) -> std::io::Result<String> {
     src.read_exact(&mut bytes)?;
     match std::str::from_utf8(&bytes) {
         Ok(string) => Ok(string.to_owned()),
-        Err(_) => Err(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            "Mesh name not valid UTF-8",
-        )),
+        Err(_) => Err(std::io::Error::other("Mesh name not valid UTF-8")),
     }
 }
 async fn read_tmf_header<R: Read>(src: &mut R) -> Result<TMFHeader, TMFImportError> {
+    parse_tmf_header(src).map_err(|err| err.at(TMFErrorLocation::at_offset(0)))
+}
+fn parse_tmf_header<R: Read>(src: &mut R) -> Result<TMFHeader, TMFImportError> {
     let mut magic = [0; 3];
     src.read_exact(&mut magic)?;
     if magic != *b"TMF" {
@@ -88,15 +119,23 @@ async fn read_tmf_header<R: Read>(src: &mut R) -> Result<TMFHeader, TMFImportErr
     let min_minor = src.read_u16()?;
 
     if min_major > TMF_MAJOR || (min_major == TMF_MAJOR && min_minor > TMF_MINOR) {
-        Err(TMFImportError::NewerVersionRequired)
-    } else {
-        Ok(TMFHeader {
-            major,
-            minor,
-            min_major,
-            min_minor,
-        })
+        return Err(TMFImportError::NewerVersionRequired);
     }
+    let mesh_count = src.read_u32()?;
+    // Flags are only present in files requiring newer readers. Unknown flags are ignored, since features needing them require newer readers too.
+    let flags = if min_minor >= crate::FLAGS_TMF_MINOR {
+        src.read_u8()?
+    } else {
+        0
+    };
+    Ok(TMFHeader {
+        major,
+        minor,
+        min_major,
+        min_minor,
+        mesh_count,
+        checksums: flags & crate::CHECKSUMS_FLAG != 0,
+    })
 }
 impl TMFImportContext {
     pub(crate) fn segment_type_width(&self) -> &SegTypeWidth {
@@ -105,6 +144,23 @@ impl TMFImportContext {
     pub(crate) fn segment_length_width(&self) -> &SegLenWidth {
         &self.segment_length_width
     }
+    /// Reads the checksum of segment data, if segments have checksums.
+    pub(crate) fn read_checksum<R: Read>(&self, src: &mut R) -> std::io::Result<Option<u32>> {
+        if self.has_checksums {
+            src.read_u32().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+    /// Size of a segment header in bytes.
+    pub(crate) fn segment_header_size(&self) -> usize {
+        let checksum_size = if self.has_checksums {
+            std::mem::size_of::<u32>()
+        } else {
+            0
+        };
+        self.segment_type_width.size() + self.segment_length_width.size() + 1 + checksum_size
+    }
     pub(crate) fn read_traingle_min<R: Read>(&self, src: &mut R) -> std::io::Result<u64> {
         if self.should_read_min_index {
             src.read_u64()
@@ -112,147 +168,1070 @@ impl TMFImportContext {
             Ok(0)
         }
     }
-    fn init_header(hdr: TMFHeader) -> Self {
+    /// Context for reading data saved by this version of the format.
+    pub(crate) fn current() -> Self {
+        Self::init_header(&TMFHeader {
+            major: crate::TMF_MAJOR,
+            minor: crate::TMF_MINOR,
+            min_major: crate::MIN_TMF_MAJOR,
+            min_minor: crate::MIN_TMF_MINOR,
+            mesh_count: 0,
+            checksums: false,
+        })
+    }
+    fn init_header(hdr: &TMFHeader) -> Self {
         Self {
-            segment_length_width: SegLenWidth::from_header(&hdr),
-            segment_type_width: SegTypeWidth::from_header(&hdr),
+            segment_length_width: SegLenWidth::from_header(hdr),
+            segment_type_width: SegTypeWidth::from_header(hdr),
             should_read_min_index: (hdr.min_minor > 1),
+            has_checksums: hdr.checksums,
         }
     }
     async fn import_mesh<R: Read>(
         &self,
-        mut src: R,
+        src: R,
         ctx: &Self,
+        options: &TMFImportOptions,
+        pos: MeshPosition,
+        tracker: &mut LimitTracker,
     ) -> Result<(TMFMesh, String), TMFImportError> {
-        let name = read_string(&mut src)?;
-        let segment_count = src.read_u16()?;
+        let mut src = CountingReader::new(src);
+        let name = read_string(&mut src).map_err(|err| pos.error(err, None))?;
+        let segment_count = src.read_u16().map_err(|err| pos.error(err, Some(&name)))?;
+        tracker
+            .begin_mesh(segment_count)
+            .map_err(|err| err.at(pos.locate(Some(&name))))?;
         //self.segment_length_width.read(&mut src)?;
         let mut decoded_segs = Vec::with_capacity(segment_count as usize);
-        for _ in 0..segment_count {
-            let encoded = EncodedSegment::read(self, &mut src)?;
+        // Index, type and offset of each decoded segment, used to locate decoding errors.
+        let mut seg_positions = Vec::with_capacity(segment_count as usize);
+        for index in 0..segment_count as usize {
+            let offset = src.count;
+            let encoded = EncodedSegment::read_selected(self, &mut src, options, tracker)
+                .and_then(|encoded| check_intact(encoded, &name, index))
+                .and_then(|encoded| tracker.take_decoded(encoded, self))
+                .map_err(|err| err.at(pos.locate_segment(&name, index, None, offset)))?;
+            let Some(encoded) = encoded else {
+                continue;
+            };
+            seg_positions.push((index, encoded.seg_type(), offset));
             //println!("encoded_type:{:?}",encoded.seg_type());
             let ctx = ctx.clone();
             let decoded = async move { DecodedSegment::decode(encoded, &ctx).await };
 
             #[cfg(feature = "tokio_runtime")]
-            let decoded = { tokio::task::spawn(decoded) };
+            let decoded = spawn_if_in_runtime(decoded);
 
             decoded_segs.push(decoded);
         }
-        let mut res = TMFMesh::empty();
         let joined = join_all(decoded_segs).await;
         #[cfg(feature = "tokio_runtime")]
-        let joined = { joined.into_iter().collect::<Result<Vec<_>, _>>().unwrap() };
-        joined
+        let joined = {
+            joined
+                .into_iter()
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(std::io::Error::other)?
+        };
+        let segs = joined
             .into_iter()
-            .collect::<Result<Vec<_>, _>>()?
-            .iter()
-            .for_each(|seg| {
-                seg.apply(&mut res);
-            });
-        Ok((res, name))
+            .zip(seg_positions)
+            .map(|(seg, (index, seg_type, offset))| {
+                seg.map_err(|err| err.at(pos.locate_segment(&name, index, Some(seg_type), offset)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok((apply_segments(segs), name))
+    }
+    /// Version of [`Self::import_mesh`] decoding the mesh into *buffers*. Returns the name of the mesh.
+    fn import_mesh_into<R: Read>(
+        &self,
+        src: R,
+        options: &TMFImportOptions,
+        buffers: &mut TMFMeshBuffers,
+        pos: MeshPosition,
+        tracker: &mut LimitTracker,
+    ) -> Result<String, TMFImportError> {
+        let mut src = CountingReader::new(src);
+        let name = read_string(&mut src).map_err(|err| pos.error(err, None))?;
+        let segment_count = src.read_u16().map_err(|err| pos.error(err, Some(&name)))?;
+        tracker
+            .begin_mesh(segment_count)
+            .map_err(|err| err.at(pos.locate(Some(&name))))?;
+        let mut segs = Vec::with_capacity(segment_count as usize);
+        let mut seg_positions = Vec::with_capacity(segment_count as usize);
+        for index in 0..segment_count as usize {
+            let offset = src.count;
+            let encoded = EncodedSegment::read_selected(self, &mut src, options, tracker)
+                .and_then(|encoded| check_intact(encoded, &name, index))
+                .and_then(|encoded| tracker.take_decoded(encoded, self))
+                .map_err(|err| err.at(pos.locate_segment(&name, index, None, offset)))?;
+            if let Some(encoded) = encoded {
+                seg_positions.push((index, encoded.seg_type(), offset));
+                segs.push(encoded);
+            }
+        }
+        buffers.decode(&segs, self, |seg| {
+            let (index, seg_type, offset) = seg_positions[seg];
+            pos.locate_segment(&name, index, Some(seg_type), offset)
+        })?;
+        Ok(name)
+    }
+    /// Version of [`Self::import_mesh`] decoding segments directly from *src*, without copying their data.
+    async fn import_mesh_from_slice(
+        &self,
+        src: &mut &[u8],
+        options: &TMFImportOptions,
+        pos: MeshPosition,
+        tracker: &mut LimitTracker,
+        problems: &mut Problems,
+    ) -> Result<(TMFMesh, String), TMFImportError> {
+        let mesh_len = src.len();
+        let name = read_string(src).map_err(|err| pos.error(err, None))?;
+        let segment_count = src.read_u16().map_err(|err| pos.error(err, Some(&name)))?;
+        tracker
+            .begin_mesh(segment_count)
+            .map_err(|err| err.at(pos.locate(Some(&name))))?;
+        let mut decoded_segs = Vec::with_capacity(segment_count as usize);
+        let mut seg_positions = Vec::with_capacity(segment_count as usize);
+        // Were any vertex triangles of this mesh lost, making predicted vertices impossible to reconstruct.
+        let mut triangles_lost = false;
+        for index in 0..segment_count as usize {
+            let offset = (mesh_len - src.len()) as u64;
+            let encoded =
+                match EncodedSegment::read_selected_from_slice(self, src, options, tracker) {
+                    Ok(encoded) => encoded,
+                    Err(err) => {
+                        // Position of the next segment is unknown, so segments read so far are all that can be recovered.
+                        problems.report_damage(
+                            err.at(pos.locate_segment(&name, index, None, offset)),
+                        )?;
+                        // Unread segments may have stored vertex triangles.
+                        triangles_lost = true;
+                        break;
+                    }
+                };
+            let seg_type = encoded.as_ref().map(EncodedSegment::seg_type);
+            let encoded = match check_intact(encoded, &name, index)
+                .and_then(|encoded| tracker.take_decoded(encoded, self))
+            {
+                Ok(Some(encoded)) => encoded,
+                Ok(None) => continue,
+                Err(err) => {
+                    problems.report(err.at(pos.locate_segment(&name, index, None, offset)))?;
+                    triangles_lost |= seg_type.is_some_and(may_store_vertex_triangles);
+                    continue;
+                }
+            };
+            seg_positions.push((index, encoded.seg_type(), offset));
+            // Borrowed segments can't be moved to other tasks, so they are decoded in place.
+            decoded_segs.push(DecodedSegment::decode(encoded, self));
+        }
+        let mut loaded = Vec::with_capacity(decoded_segs.len());
+        let decoded_segs = join_all(decoded_segs).await;
+        for (seg, position) in decoded_segs.into_iter().zip(seg_positions) {
+            let (index, seg_type, offset) = position;
+            match seg {
+                Ok(seg) => loaded.push((seg, position)),
+                Err(err) => {
+                    problems.report(err.at(pos.locate_segment(
+                        &name,
+                        index,
+                        Some(seg_type),
+                        offset,
+                    )))?;
+                    triangles_lost |= may_store_vertex_triangles(seg_type);
+                }
+            }
+        }
+        let mut segs = Vec::with_capacity(loaded.len());
+        for (seg, (index, seg_type, offset)) in loaded {
+            // Reconstructing vertices against incomplete triangles would produce garbage, so they are dropped instead.
+            if triangles_lost && matches!(seg, DecodedSegment::PredictedVertices(_)) {
+                let location = pos.locate_segment(&name, index, Some(seg_type), offset);
+                problems.report(TMFImportError::MissingPredictionTriangles.at(location))?;
+                continue;
+            }
+            segs.push(seg);
+        }
+        Ok((apply_segments(segs), name))
+    }
+    pub(crate) async fn import_from_slice(
+        file: &[u8],
+        options: &TMFImportOptions,
+        problems: &mut Problems,
+    ) -> Result<Vec<(TMFMesh, String)>, TMFImportError> {
+        let mut src = file;
+        let (res, header) = Self::read_header(&mut src).await?;
+        let mut tracker = LimitTracker::new(options.limits);
+        let mesh_count = tracker.check_meshes(&header)?;
+        let mut meshes = Vec::with_capacity((u16::MAX as usize).min(mesh_count as usize));
+        for index in 0..mesh_count as usize {
+            let pos = MeshPosition {
+                index,
+                offset: (file.len() - src.len()) as u64,
+            };
+            let mesh = res.import_mesh_from_slice(&mut src, options, pos, &mut tracker, problems);
+            match mesh.await {
+                Ok(mesh) => meshes.push(mesh),
+                Err(err) => problems.report_damage(err)?,
+            }
+            if problems.damaged {
+                break;
+            }
+        }
+        Ok(meshes)
     }
-    async fn analize_mesh<R: Read>(&self, mut src: R, _ctx: &Self) -> Result<(), TMFImportError> {
-        let name = read_string(&mut src)?;
-        println!("############################\nMesh {name}:");
-        let segment_count = src.read_u16()?;
-        let mut results = [0; 256];
+    pub(crate) async fn import<R: Read>(
+        src: R,
+        options: &TMFImportOptions,
+    ) -> Result<Vec<(TMFMesh, String)>, TMFImportError> {
+        let mut src = CountingReader::new(src);
+        let header = read_tmf_header(&mut src).await?;
+        let res = Self::init_header(&header);
+        let mut tracker = LimitTracker::new(options.limits);
+        let mesh_count = tracker.check_meshes(&header)?;
+        let mut meshes = Vec::with_capacity((u16::MAX as usize).min(mesh_count as usize));
+        for index in 0..mesh_count as usize {
+            let pos = MeshPosition {
+                index,
+                offset: src.count,
+            };
+            let mesh = res.import_mesh(&mut src, &res, options, pos, &mut tracker);
+            meshes.push(mesh.await?);
+        }
+        Ok(meshes)
+    }
+    /// Reads all bytes making up the next mesh from *src* into *mesh*, without blocking, so that the mesh can be then imported from memory.
+    async fn read_mesh_bytes<R: AsyncRead + Unpin>(
+        &self,
+        src: &mut R,
+        mesh: &mut Vec<u8>,
+        tracker: &LimitTracker,
+    ) -> Result<(), TMFImportError> {
+        mesh.clear();
+        let name_len = append_exact(src, mesh, std::mem::size_of::<u16>())
+            .await?
+            .read_u16()?;
+        append_exact(src, mesh, name_len as usize).await?;
+        let segment_count = append_exact(src, mesh, std::mem::size_of::<u16>())
+            .await?
+            .read_u16()?;
+        tracker.check_segments(segment_count)?;
+        let header_size = self.segment_header_size();
+        // Size of data of segments of this mesh, which are not yet counted by *tracker*.
+        let mut data_size = 0;
         for _ in 0..segment_count {
-            let encoded = EncodedSegment::read(self, &mut src)?;
-            results[encoded.seg_type() as u8 as usize] += encoded.data().len();
-        }
-        let mut res = Vec::new();
-        let mut total = 0;
-        for (index, result) in results.iter().enumerate() {
-            let byte_len = *result;
-            total += byte_len;
-            if byte_len != 0 {
-                res.push((format!("{:?}", SectionType::from_u8(index as u8)), byte_len));
+            let mut header = append_exact(src, mesh, header_size).await?;
+            self.segment_type_width.read(&mut header)?;
+            let data_length = self.segment_length_width.read(&mut header)?;
+            if data_length > MAX_SEG_SIZE {
+                return Err(TMFImportError::SegmentTooLong);
             }
+            data_size += data_length as u64;
+            tracker.check_bytes(data_size)?;
+            append_exact(src, mesh, data_length).await?;
         }
-        res.sort_by(|a, b| a.1.cmp(&b.1));
-        println!("res:{res:?}, total_len:{total}");
         Ok(())
     }
-    pub(crate) async fn import<R: Read>(
+    pub(crate) async fn import_async<R: AsyncRead + Unpin>(
         mut src: R,
+        options: &TMFImportOptions,
     ) -> Result<Vec<(TMFMesh, String)>, TMFImportError> {
-        let header = read_tmf_header(&mut src).await?;
-        let res = Self::init_header(header);
-        let mesh_count = src.read_u32()?;
+        let mut header = vec![0; crate::tmf_exporter::TMF_HEADER_SIZE as usize];
+        src.read_exact(&mut header).await?;
+        // Size of the header depends on the version of reader it requires, stored right before mesh count.
+        let min_minor = u16::from_le_bytes([header[9], header[10]]);
+        if header.starts_with(b"TMF") {
+            let size = crate::tmf_exporter::tmf_header_size(min_minor) as usize;
+            let start = header.len();
+            append_exact(&mut src, &mut header, size - start).await?;
+        }
+        let (res, header) = Self::read_header(&mut &header[..]).await?;
+        let mut tracker = LimitTracker::new(options.limits);
+        let mesh_count = tracker.check_meshes(&header)?;
         let mut meshes = Vec::with_capacity((u16::MAX as usize).min(mesh_count as usize));
-        for _ in 0..mesh_count {
-            meshes.push(res.import_mesh(&mut src, &res).await?);
+        let mut mesh = Vec::new();
+        let mut offset = header.size();
+        for index in 0..mesh_count as usize {
+            let pos = MeshPosition { index, offset };
+            res.read_mesh_bytes(&mut src, &mut mesh, &tracker)
+                .await
+                .map_err(|err| err.at(pos.locate(None)))?;
+            let r_mesh = res.import_mesh(&mesh[..], &res, options, pos, &mut tracker);
+            meshes.push(r_mesh.await?);
+            offset += mesh.len() as u64;
         }
         Ok(meshes)
     }
-    #[allow(dead_code)]
-    pub(crate) async fn analize<R: Read>(mut src: R) -> Result<(), TMFImportError> {
-        let header = read_tmf_header(&mut src).await?;
-        let res = Self::init_header(header);
-        let mesh_count = src.read_u32()?;
-        for _ in 0..mesh_count {
-            res.analize_mesh(&mut src, &res).await?;
+    /// Reads the header of a .tmf file, returning it together with the context for reading the rest of the file.
+    pub(crate) async fn read_header<R: Read>(
+        src: &mut R,
+    ) -> Result<(Self, TMFHeader), TMFImportError> {
+        let header = read_tmf_header(src).await?;
+        let ctx = Self::init_header(&header);
+        Ok((ctx, header))
+    }
+}
+/// Returns an error if *encoded*, segment number *segment* of mesh named *mesh*, does not match its checksum.
+fn check_intact<'a>(
+    encoded: Option<EncodedSegment<'a>>,
+    mesh: &str,
+    segment: usize,
+) -> Result<Option<EncodedSegment<'a>>, TMFImportError> {
+    match encoded {
+        Some(encoded) if !encoded.is_intact() => Err(TMFImportError::ChecksumMismatch {
+            mesh: mesh.to_owned(),
+            segment,
+        }
+        .at(TMFErrorLocation::of_segment_type(encoded.seg_type()))),
+        encoded => Ok(encoded),
+    }
+}
+/// Position of a mesh within a .tmf file, used to locate errors.
+#[derive(Clone, Copy)]
+struct MeshPosition {
+    index: usize,
+    /// Offset of the mesh from the beginning of the file.
+    offset: u64,
+}
+impl MeshPosition {
+    /// Location of an error in this mesh, named *name* if its name was already read.
+    fn locate(&self, name: Option<&str>) -> TMFErrorLocation {
+        TMFErrorLocation {
+            mesh_index: Some(self.index),
+            mesh_name: name.map(str::to_owned),
+            offset: Some(self.offset),
+            ..Default::default()
+        }
+    }
+    /// Location of an error in segment number *index*, beginning *offset* bytes after the beginning of this mesh.
+    fn locate_segment(
+        &self,
+        name: &str,
+        index: usize,
+        seg_type: Option<SectionType>,
+        offset: u64,
+    ) -> TMFErrorLocation {
+        TMFErrorLocation {
+            segment_index: Some(index),
+            segment_type: seg_type,
+            offset: Some(self.offset + offset),
+            ..self.locate(Some(name))
+        }
+    }
+    /// Converts IO error *err*, which occurred while reading the beginning of this mesh, into a located error.
+    fn error(&self, err: std::io::Error, name: Option<&str>) -> TMFImportError {
+        TMFImportError::from(err).at(self.locate(name))
+    }
+}
+/// Reader counting bytes read from it, used to find offsets of meshes and segments.
+struct CountingReader<R> {
+    src: R,
+    count: u64,
+}
+impl<R: Read> CountingReader<R> {
+    fn new(src: R) -> Self {
+        Self { src, count: 0 }
+    }
+}
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.src.read(buf)?;
+        self.count += read as u64;
+        Ok(read)
+    }
+}
+/// Tracks resources used by a file being loaded, returning an error before limits set by [`ImportLimits`] are exceeded.
+pub(crate) struct LimitTracker {
+    limits: ImportLimits,
+    /// Total size of data of all loaded segments.
+    bytes: u64,
+    /// Total size of mesh data decoded from all loaded segments.
+    decoded_bytes: u64,
+    /// Amount of vertices in the mesh being loaded.
+    vertices: u64,
+}
+impl LimitTracker {
+    pub(crate) fn new(limits: ImportLimits) -> Self {
+        Self {
+            limits,
+            bytes: 0,
+            decoded_bytes: 0,
+            vertices: 0,
         }
+    }
+    fn check(exceeded: bool, limit: ImportLimit) -> Result<(), TMFImportError> {
+        if exceeded {
+            Err(TMFImportError::LimitExceeded(limit))
+        } else {
+            Ok(())
+        }
+    }
+    /// Checks the amount of meshes in a file, returning it.
+    fn check_meshes(&self, header: &TMFHeader) -> Result<u32, TMFImportError> {
+        // Mesh count is stored at the end of the header.
+        const MESH_COUNT_OFFSET: u64 =
+            crate::tmf_exporter::TMF_HEADER_SIZE - std::mem::size_of::<u32>() as u64;
+        Self::check(
+            header.mesh_count > self.limits.max_meshes,
+            ImportLimit::Meshes,
+        )
+        .map_err(|err| err.at(TMFErrorLocation::at_offset(MESH_COUNT_OFFSET)))?;
+        Ok(header.mesh_count)
+    }
+    fn check_segments(&self, segment_count: u16) -> Result<(), TMFImportError> {
+        Self::check(
+            segment_count > self.limits.max_segments,
+            ImportLimit::Segments,
+        )
+    }
+    /// Prepares for loading of a mesh made of *segment_count* segments.
+    fn begin_mesh(&mut self, segment_count: u16) -> Result<(), TMFImportError> {
+        self.vertices = 0;
+        self.check_segments(segment_count)
+    }
+    /// Checks if *length* more bytes of segment data can be loaded.
+    fn check_bytes(&self, length: u64) -> Result<(), TMFImportError> {
+        Self::check(
+            self.bytes.saturating_add(length) > self.limits.max_total_bytes,
+            ImportLimit::TotalBytes,
+        )
+    }
+    /// Counts *length* bytes of segment data, which are about to be loaded.
+    pub(crate) fn take_bytes(&mut self, length: usize) -> Result<(), TMFImportError> {
+        self.check_bytes(length as u64)?;
+        self.bytes += length as u64;
         Ok(())
     }
+    /// Counts vertices stored in *seg*, and the size of data decoded from it, before it is decoded.
+    fn take_decoded<'a>(
+        &mut self,
+        seg: Option<EncodedSegment<'a>>,
+        ctx: &TMFImportContext,
+    ) -> Result<Option<EncodedSegment<'a>>, TMFImportError> {
+        if let Some(seg) = &seg {
+            let info = TMFSegmentInfo::from_encoded(seg, ctx);
+            if seg.seg_type() == SectionType::VertexSegment {
+                let count = info.element_count.unwrap_or(0);
+                self.vertices = self.vertices.saturating_add(count);
+                Self::check(
+                    self.vertices > self.limits.max_vertices,
+                    ImportLimit::Vertices,
+                )?;
+            }
+            let size = info.decoded_size().unwrap_or(0);
+            self.decoded_bytes = self.decoded_bytes.saturating_add(size);
+            Self::check(
+                self.decoded_bytes > self.limits.max_total_bytes,
+                ImportLimit::TotalBytes,
+            )?;
+        }
+        Ok(seg)
+    }
+}
+/// Checks if a segment of type *seg_type* may store vertex triangles, which predicted vertices depend on.
+fn may_store_vertex_triangles(seg_type: SectionType) -> bool {
+    matches!(
+        seg_type,
+        SectionType::VertexTriangleSegment | SectionType::SharedTriangleSegment
+    )
+}
+/// Creates a mesh out of decoded segments.
+fn apply_segments(mut segs: Vec<DecodedSegment<'_>>) -> TMFMesh {
+    let mut res = TMFMesh::empty();
+    // Some segments are derived from other mesh data, so they must be applied after segments they depend on.
+    segs.sort_by_key(DecodedSegment::apply_order);
+    segs.iter().for_each(|seg| {
+        seg.apply(&mut res);
+    });
+    res
+}
+/// Appends exactly *len* bytes read from *src* to *buf*, and returns them.
+async fn append_exact<'a, R: AsyncRead + Unpin>(
+    src: &mut R,
+    buf: &'a mut Vec<u8>,
+    len: usize,
+) -> std::io::Result<&'a [u8]> {
+    let start = buf.len();
+    buf.resize(start + len, 0);
+    src.read_exact(&mut buf[start..]).await?;
+    Ok(&buf[start..])
+}
+/// Runs *future* as a separate task if called within a tokio runtime, so segments can be decoded in parallel. Outside of one (e.g. when driven by a non-tokio executor), *future* is polled in place instead.
+#[cfg(feature = "tokio_runtime")]
+fn spawn_if_in_runtime<F>(
+    future: F,
+) -> impl std::future::Future<Output = std::io::Result<F::Output>>
+where
+    F: std::future::Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    use futures::future::{Either, FutureExt};
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) => Either::Left(
+            handle
+                .spawn(future)
+                .map(|res| res.map_err(std::io::Error::other)),
+        ),
+        Err(_) => Either::Right(future.map(Ok)),
+    }
+}
+/// Adapts a tokio reader, so it can be used in place of a [`futures::AsyncRead`] one.
+#[cfg(feature = "tokio_runtime")]
+pub(crate) struct TokioReader<R>(pub(crate) R);
+#[cfg(feature = "tokio_runtime")]
+impl<R: tokio::io::AsyncRead + Unpin> AsyncRead for TokioReader<R> {
+    fn poll_read(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut [u8],
+    ) -> std::task::Poll<std::io::Result<usize>> {
+        let mut buf = tokio::io::ReadBuf::new(buf);
+        std::pin::Pin::new(&mut self.get_mut().0)
+            .poll_read(cx, &mut buf)
+            .map_ok(|()| buf.filled().len())
+    }
+}
+/// Reader of .tmf files, which reads meshes one by one, only keeping one mesh in memory at a time.
+/// # Example
+/// ```
+/// # use std::fs::File;
+/// # use tmf::{TMFMesh, TMFReader};
+/// # fn do_something(_:TMFMesh,_:String){}
+/// let file = File::open("testing/susan.tmf").expect("Could not open .tmf file");
+/// let reader = TMFReader::new(file).expect("Could not read .tmf header!");
+/// for mesh in reader {
+///     let (mesh, name) = mesh.expect("Could not load .tmf mesh!");
+///     do_something(mesh, name);
+/// }
+/// ```
+pub struct TMFReader<R: Read> {
+    src: R,
+    ctx: TMFImportContext,
+    options: TMFImportOptions,
+    remaining: u32,
+    /// Position of the next mesh.
+    next: MeshPosition,
+    tracker: LimitTracker,
+}
+impl<R: Read> TMFReader<R> {
+    /// Reads header of a .tmf file from *src*, preparing to read meshes.
+    /// # Errors
+    /// Returns: an IO error if it occurs, `NotTMFFile` if not tmf file, `NewerVersionRequired` if a newer importer is required for importing the file.
+    pub fn new(src: R) -> Result<Self, TMFImportError> {
+        Self::with_options(src, TMFImportOptions::default())
+    }
+    /// Reads header of a .tmf file from *src*, preparing to read meshes. Only data selected by *options* will be loaded.
+    /// # Errors
+    /// Returns: an IO error if it occurs, `NotTMFFile` if not tmf file, `NewerVersionRequired` if a newer importer is required for importing the file.
+    pub fn with_options(mut src: R, options: TMFImportOptions) -> Result<Self, TMFImportError> {
+        let header = runtime_agnostic_block_on!(read_tmf_header(&mut src))?;
+        let ctx = TMFImportContext::init_header(&header);
+        let tracker = LimitTracker::new(options.limits);
+        let remaining = tracker.check_meshes(&header)?;
+        Ok(Self {
+            src,
+            ctx,
+            options,
+            remaining,
+            tracker,
+            next: MeshPosition {
+                index: 0,
+                offset: header.size(),
+            },
+        })
+    }
+    /// Returns the amount of meshes which were not read yet.
+    pub fn remaining(&self) -> u32 {
+        self.remaining
+    }
+    /// Reads the next mesh and its name. Returns `None` if all meshes were read.
+    /// # Errors
+    /// Returns an IO error if it occurs, and other errors for malformed tmf files. After an error, no more meshes are returned.
+    pub fn read_mesh(&mut self) -> Option<Result<(TMFMesh, String), TMFImportError>> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let mut src = CountingReader::new(&mut self.src);
+        let mesh = runtime_agnostic_block_on!(self.ctx.import_mesh(
+            &mut src,
+            &self.ctx,
+            &self.options,
+            self.next,
+            &mut self.tracker
+        ));
+        let length = src.count;
+        self.advance(length, mesh.is_ok());
+        Some(mesh.map_err(TMFImportError::located))
+    }
+    /// Reads the next mesh, decoding its data into caller-owned *buffers* instead of allocating a new mesh. Returns the name of the mesh, or `None` if all meshes were read.
+    /// Previous contents of *buffers* are removed. Custom data is not loaded.
+    /// # Errors
+    /// Returns an IO error if it occurs, and other errors for malformed tmf files. After an error, no more meshes are returned.
+    pub fn read_mesh_into(
+        &mut self,
+        buffers: &mut TMFMeshBuffers,
+    ) -> Option<Result<String, TMFImportError>> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let mut src = CountingReader::new(&mut self.src);
+        let name = self.ctx.import_mesh_into(
+            &mut src,
+            &self.options,
+            buffers,
+            self.next,
+            &mut self.tracker,
+        );
+        let length = src.count;
+        self.advance(length, name.is_ok());
+        Some(name.map_err(TMFImportError::located))
+    }
+    /// Moves past a mesh *length* bytes long, which was read successfully if *read*.
+    fn advance(&mut self, length: u64, read: bool) {
+        // Position within the file is unknown after an error, so next meshes can't be read.
+        self.remaining = if read { self.remaining - 1 } else { 0 };
+        self.next.index += 1;
+        self.next.offset += length;
+    }
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.src
+    }
+}
+impl<R: Read> Iterator for TMFReader<R> {
+    type Item = Result<(TMFMesh, String), TMFImportError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_mesh()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining as usize))
+    }
+}
+/// Location of a mesh within a .tmf file.
+struct MeshEntry {
+    name: String,
+    /// Offset of the mesh from the beginning of the file.
+    offset: u64,
+    length: u64,
+}
+/// A .tmf file, allowing meshes to be loaded by name, without reading meshes before them.
+/// If the file has a table of contents (see [`crate::TMFWriter::with_table_of_contents`]), meshes are located using it. Otherwise, the file is indexed when opened, by skipping over segments of all meshes without decoding them.
+/// # Example
+/// ```
+/// # use std::fs::File;
+/// # use tmf::TMFFile;
+/// let file = File::open("testing/susan.tmf").expect("Could not open .tmf file");
+/// let mut tmf_file = TMFFile::open(file).expect("Could not read .tmf file!");
+/// let names: Vec<String> = tmf_file.mesh_names().map(str::to_owned).collect();
+/// for name in names {
+///     let mesh = tmf_file
+///         .load_mesh(&name)
+///         .expect("Could not load .tmf mesh!")
+///         .expect("Mesh is present");
+/// }
+/// ```
+pub struct TMFFile<R: Read + Seek> {
+    src: R,
+    /// Position of the header within *src*.
+    start: u64,
+    ctx: TMFImportContext,
+    meshes: Vec<MeshEntry>,
+    /// Tracks resources used by all meshes loaded from this file.
+    tracker: LimitTracker,
+}
+impl<R: Read + Seek> TMFFile<R> {
+    /// Opens a .tmf file beginning at the current position of *src*, and locates all of its meshes.
+    /// # Errors
+    /// Returns: an IO error if it occurs, `NotTMFFile` if not tmf file, `NewerVersionRequired` if a newer importer is required for importing the file, and other errors for malformed tmf files.
+    pub fn open(src: R) -> Result<Self, TMFImportError> {
+        Self::open_with_limits(src, ImportLimits::default())
+    }
+    /// Opens a .tmf file beginning at the current position of *src*, like [`Self::open`], but refuses to locate or load more data than allowed by *limits*.
+    /// Limits are shared by all meshes loaded from the returned file, so its total size is capped no matter how many meshes are loaded.
+    /// # Errors
+    /// Returns the same errors as [`Self::open`], and `LimitExceeded` if the file exceeds *limits*.
+    /// # Example
+    /// ```
+    /// # use std::fs::File;
+    /// # use tmf::{ImportLimits, TMFFile};
+    /// let file = File::open("testing/susan.tmf").expect("Could not open .tmf file");
+    /// let limits = ImportLimits {
+    ///     max_meshes: 16,
+    ///     max_total_bytes: 1 << 24,
+    ///     ..Default::default()
+    /// };
+    /// let tmf_file = TMFFile::open_with_limits(file, limits).expect("Could not read .tmf file!");
+    /// assert_eq!(tmf_file.mesh_count(), 1);
+    /// ```
+    pub fn open_with_limits(src: R, limits: ImportLimits) -> Result<Self, TMFImportError> {
+        Self::locate_meshes(src, LimitTracker::new(limits)).map_err(TMFImportError::located)
+    }
+    fn locate_meshes(mut src: R, tracker: LimitTracker) -> Result<Self, TMFImportError> {
+        let start = src.stream_position()?;
+        let header = runtime_agnostic_block_on!(read_tmf_header(&mut src))?;
+        let ctx = TMFImportContext::init_header(&header);
+        let mesh_count = tracker.check_meshes(&header)?;
+        let header_end = start + header.size();
+        // A missing, damaged or outdated table of contents is not an error, since the file can be still indexed.
+        let meshes = match Self::read_toc(&mut src, start, header_end, mesh_count, &tracker) {
+            Ok(Some(meshes)) => meshes,
+            Err(err) if matches!(err.cause(), TMFImportError::LimitExceeded(_)) => return Err(err),
+            _ => Self::index(&mut src, start, header_end, &ctx, mesh_count, &tracker)?,
+        };
+        Ok(Self {
+            src,
+            start,
+            ctx,
+            meshes,
+            tracker,
+        })
+    }
+    fn read_toc(
+        src: &mut R,
+        start: u64,
+        header_end: u64,
+        mesh_count: u32,
+        tracker: &LimitTracker,
+    ) -> Result<Option<Vec<MeshEntry>>, TMFImportError> {
+        const FOOTER_SIZE: u64 = (std::mem::size_of::<u64>() + crate::TOC_MAGIC.len()) as u64;
+        let end = src.seek(SeekFrom::End(0))?;
+        if end < header_end + FOOTER_SIZE {
+            return Ok(None);
+        }
+        src.seek(SeekFrom::Start(end - FOOTER_SIZE))?;
+        let toc_offset = src.read_u64()?;
+        let mut magic = [0; 4];
+        src.read_exact(&mut magic)?;
+        if magic != crate::TOC_MAGIC
+            || toc_offset < header_end - start
+            || toc_offset > end - FOOTER_SIZE - start
+        {
+            return Ok(None);
+        }
+        src.seek(SeekFrom::Start(start + toc_offset))?;
+        if src.read_u32()? != mesh_count {
+            return Ok(None);
+        }
+        let mut meshes = Vec::with_capacity((u16::MAX as usize).min(mesh_count as usize));
+        for index in 0..mesh_count as usize {
+            let name = read_string(src)?;
+            let offset = src.read_u64()?;
+            let length = src.read_u64()?;
+            if offset.saturating_add(length) > toc_offset {
+                return Ok(None);
+            }
+            // Segments of the mesh are only read when it is loaded, but a mesh longer than any mesh within limits can be rejected right away.
+            if length > max_mesh_size(&tracker.limits) {
+                let location = TMFErrorLocation {
+                    mesh_index: Some(index),
+                    mesh_name: Some(name),
+                    offset: Some(offset),
+                    ..Default::default()
+                };
+                return Err(TMFImportError::LimitExceeded(ImportLimit::TotalBytes).at(location));
+            }
+            meshes.push(MeshEntry {
+                name,
+                offset,
+                length,
+            });
+        }
+        Ok(Some(meshes))
+    }
+    /// Locates meshes by skipping over their segments.
+    fn index(
+        src: &mut R,
+        start: u64,
+        header_end: u64,
+        ctx: &TMFImportContext,
+        mesh_count: u32,
+        tracker: &LimitTracker,
+    ) -> Result<Vec<MeshEntry>, TMFImportError> {
+        src.seek(SeekFrom::Start(header_end))?;
+        let mut meshes = Vec::with_capacity((u16::MAX as usize).min(mesh_count as usize));
+        for index in 0..mesh_count as usize {
+            let mesh_start = src.stream_position()?;
+            let pos = MeshPosition {
+                index,
+                offset: mesh_start - start,
+            };
+            let name = read_string(src).map_err(|err| pos.error(err, None))?;
+            let segment_count = src.read_u16().map_err(|err| pos.error(err, Some(&name)))?;
+            tracker
+                .check_segments(segment_count)
+                .map_err(|err| err.at(pos.locate(Some(&name))))?;
+            // Size of data of segments of this mesh, which would be counted by *tracker* when it is loaded.
+            let mut data_size = 0;
+            for index in 0..segment_count as usize {
+                let seg_start = src.stream_position()?;
+                Self::skip_segment(src, ctx)
+                    .and_then(|data_length| {
+                        data_size += data_length as u64;
+                        tracker.check_bytes(data_size)
+                    })
+                    .map_err(|err| {
+                        err.at(pos.locate_segment(&name, index, None, seg_start - mesh_start))
+                    })?;
+            }
+            let mesh_end = src.stream_position()?;
+            meshes.push(MeshEntry {
+                name,
+                offset: mesh_start - start,
+                length: mesh_end - mesh_start,
+            });
+        }
+        Ok(meshes)
+    }
+    /// Skips over the next segment, without reading its data, and returns the length of that data.
+    fn skip_segment(src: &mut R, ctx: &TMFImportContext) -> Result<usize, TMFImportError> {
+        let seg_type = SectionType::from_u16(ctx.segment_type_width().read(src)?);
+        let data_length = ctx.segment_length_width().read(src)?;
+        if data_length > MAX_SEG_SIZE {
+            return Err(
+                TMFImportError::SegmentTooLong.at(TMFErrorLocation::of_segment_type(seg_type))
+            );
+        }
+        let _compresion_type = src.read_u8()?;
+        let _checksum = ctx.read_checksum(src)?;
+        src.seek(SeekFrom::Current(data_length as i64))?;
+        Ok(data_length)
+    }
+    /// Returns the amount of meshes in this file.
+    pub fn mesh_count(&self) -> usize {
+        self.meshes.len()
+    }
+    /// Returns names of all meshes in this file, in order they are saved in.
+    pub fn mesh_names(&self) -> impl Iterator<Item = &str> {
+        self.meshes.iter().map(|mesh| mesh.name.as_str())
+    }
+    /// Loads the first mesh named *name*. Returns `None` if no such mesh is present.
+    /// # Errors
+    /// Returns an IO error if it occurs, and other errors for malformed tmf files.
+    pub fn load_mesh(&mut self, name: &str) -> Result<Option<TMFMesh>, TMFImportError> {
+        self.load_mesh_with_options(name, &TMFImportOptions::default())
+    }
+    /// Loads the first mesh named *name*, only loading data selected by *options*. Returns `None` if no such mesh is present.
+    /// Limits of this file are set when it is opened (see [`Self::open_with_limits`]), so `options.limits` are ignored.
+    /// # Errors
+    /// Returns an IO error if it occurs, `LimitExceeded` if loading the mesh would exceed limits of this file, and other errors for malformed tmf files.
+    pub fn load_mesh_with_options(
+        &mut self,
+        name: &str,
+        options: &TMFImportOptions,
+    ) -> Result<Option<TMFMesh>, TMFImportError> {
+        let Some((index, mesh)) = self
+            .meshes
+            .iter()
+            .enumerate()
+            .find(|(_, mesh)| mesh.name == name)
+        else {
+            return Ok(None);
+        };
+        let pos = MeshPosition {
+            index,
+            offset: mesh.offset,
+        };
+        self.src
+            .seek(SeekFrom::Start(self.start + mesh.offset))
+            .map_err(|err| pos.error(err, None))?;
+        let src = (&mut self.src).take(mesh.length);
+        let (mesh, _) = runtime_agnostic_block_on!(self.ctx.import_mesh(
+            src,
+            &self.ctx,
+            options,
+            pos,
+            &mut self.tracker
+        ))
+        .map_err(TMFImportError::located)?;
+        Ok(Some(mesh))
+    }
 }
 pub(crate) fn import_sync<R: std::io::Read>(
     src: R,
+    options: &TMFImportOptions,
 ) -> Result<Vec<(TMFMesh, String)>, TMFImportError> {
-    runtime_agnostic_block_on!(TMFImportContext::import(src))
+    runtime_agnostic_block_on!(TMFImportContext::import(src, options))
+        .map_err(TMFImportError::located)
+}
+pub(crate) fn import_from_slice(
+    src: &[u8],
+    options: &TMFImportOptions,
+) -> Result<Vec<(TMFMesh, String)>, TMFImportError> {
+    let mut problems = Problems::default();
+    runtime_agnostic_block_on!(TMFImportContext::import_from_slice(
+        src,
+        options,
+        &mut problems
+    ))
+    .map_err(TMFImportError::located)
+}
+pub(crate) fn import_lenient<R: Read>(
+    mut src: R,
+    options: &TMFImportOptions,
+) -> Result<TMFPartialImport, TMFImportError> {
+    let mut data = Vec::new();
+    let max_size = max_file_size(&options.limits);
+    // Data read before an error is kept, so it can be still recovered.
+    let read = (&mut src)
+        .take(max_size.saturating_add(1))
+        .read_to_end(&mut data);
+    if data.len() as u64 > max_size {
+        let location = TMFErrorLocation::at_offset(max_size);
+        return Err(TMFImportError::LimitExceeded(ImportLimit::TotalBytes).at(location));
+    }
+    let mut problems = Problems {
+        lenient: true,
+        ..Default::default()
+    };
+    if let Err(err) = read {
+        let location = TMFErrorLocation {
+            offset: Some(data.len() as u64),
+            ..Default::default()
+        };
+        problems.report(TMFImportError::from(err).at(location))?;
+    }
+    let meshes = runtime_agnostic_block_on!(TMFImportContext::import_from_slice(
+        &data,
+        options,
+        &mut problems
+    ))
+    .map_err(TMFImportError::located)?;
+    Ok(TMFPartialImport {
+        meshes,
+        problems: problems.found,
+    })
+}
+/// Size of the longest name of a mesh, together with its length.
+const MAX_NAME_SIZE: u64 = (std::mem::size_of::<u16>() + u16::MAX as usize) as u64;
+/// Returns the size of the name, segment count and segment headers of the biggest mesh within *limits*.
+fn max_mesh_headers_size(limits: &ImportLimits) -> u64 {
+    use std::mem::size_of;
+    // Type, length, compression and checksum.
+    const MAX_SEGMENT_HEADER_SIZE: u64 = (2 * size_of::<u8>() + 2 * size_of::<u32>()) as u64;
+    (MAX_NAME_SIZE + size_of::<u16>() as u64)
+        .saturating_add(MAX_SEGMENT_HEADER_SIZE * u64::from(limits.max_segments))
+}
+/// Returns the size of the biggest mesh within *limits*, including all of its headers.
+fn max_mesh_size(limits: &ImportLimits) -> u64 {
+    max_mesh_headers_size(limits).saturating_add(limits.max_total_bytes)
+}
+/// Returns the size of the biggest file within *limits*: of all data counted by them, together with headers of all meshes and segments, and a table of contents.
+fn max_file_size(limits: &ImportLimits) -> u64 {
+    use std::mem::size_of;
+    let mesh_size = max_mesh_headers_size(limits);
+    let toc_entry_size = MAX_NAME_SIZE + 2 * size_of::<u64>() as u64;
+    let toc_size = (size_of::<u32>() + size_of::<u64>() + crate::TOC_MAGIC.len()) as u64;
+    (crate::tmf_exporter::MAX_TMF_HEADER_SIZE + toc_size)
+        .saturating_add((mesh_size + toc_entry_size).saturating_mul(u64::from(limits.max_meshes)))
+        .saturating_add(limits.max_total_bytes)
+}
+/// Meshes recovered from a possibly damaged file by [`TMFMesh::read_tmf_lenient`].
+pub struct TMFPartialImport {
+    /// Meshes which could be read, and their names. If the file is damaged, the last mesh may be missing some of its data.
+    pub meshes: Vec<(TMFMesh, String)>,
+    /// Problems found in the file, in order they were found. Empty if the file was read without problems.
+    pub problems: Vec<TMFImportError>,
+}
+impl TMFPartialImport {
+    /// Checks if the whole file was read without any problems.
+    pub fn is_complete(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+/// Problems found in a file. Unless the import is lenient, the first problem stops it.
+#[derive(Default)]
+pub(crate) struct Problems {
+    lenient: bool,
+    found: Vec<TMFImportError>,
+    /// Was the rest of the file made unreadable by a problem.
+    damaged: bool,
+}
+impl Problems {
+    /// Records *err* if the import is lenient, otherwise returns it. Exceeded limits are always returned, since files exceeding them must not be loaded.
+    fn report(&mut self, err: TMFImportError) -> Result<(), TMFImportError> {
+        if self.lenient && !matches!(err.cause(), TMFImportError::LimitExceeded(_)) {
+            self.found.push(err.located());
+            Ok(())
+        } else {
+            Err(err)
+        }
+    }
+    /// Version of [`Self::report`] for problems after which the rest of the file can't be read.
+    fn report_damage(&mut self, err: TMFImportError) -> Result<(), TMFImportError> {
+        self.damaged = true;
+        self.report(err)
+    }
 }
 pub(crate) async fn decode_vertex_seg(
-    seg: EncodedSegment,
-) -> Result<DecodedSegment, TMFImportError> {
+    seg: EncodedSegment<'_>,
+) -> Result<DecodedSegment<'static>, TMFImportError> {
     if SectionType::VertexSegment == seg.seg_type() {
         let mut data: &[u8] = seg.data();
-        Ok(DecodedSegment::AppendVertex(
-            crate::vertices::read_tmf_vertices(&mut data)?,
-        ))
+        match seg.compresion_type() {
+            CompressionType::None => Ok(DecodedSegment::AppendVertex(
+                crate::vertices::read_tmf_vertices(&mut data)?
+                    .into_vec()
+                    .into(),
+            )),
+            CompressionType::Parallelogram => Ok(DecodedSegment::PredictedVertices(
+                crate::vertices::read_predicted_vertices(&mut data)?,
+            )),
+            CompressionType::Lossless => Ok(DecodedSegment::AppendVertex(
+                crate::lossless::read_floats(&mut data)?.into_vec().into(),
+            )),
+            compresion_type => Err(TMFImportError::UnsuportedCompressionType(
+                compresion_type as u8,
+            )),
+        }
     } else {
-        panic!("Unreachable condition reached!");
+        Err(TMFImportError::UnexpectedSegmentType(seg.seg_type()))
     }
 }
-pub(crate) async fn decode_uv_seg(seg: EncodedSegment) -> Result<DecodedSegment, TMFImportError> {
+pub(crate) async fn decode_uv_seg(
+    seg: EncodedSegment<'_>,
+) -> Result<DecodedSegment<'static>, TMFImportError> {
     if SectionType::UvSegment == seg.seg_type() {
         let mut data: &[u8] = seg.data();
-        Ok(DecodedSegment::AppendUV(crate::uv::read_uvs(&mut data)?))
+        let uvs = match seg.compresion_type() {
+            CompressionType::Lossless => crate::lossless::read_floats(&mut data)?,
+            _ => crate::uv::read_uvs(&mut data)?,
+        };
+        Ok(DecodedSegment::AppendUV(uvs.into_vec().into()))
     } else {
-        panic!("Unreachable condition reached!");
+        Err(TMFImportError::UnexpectedSegmentType(seg.seg_type()))
     }
 }
 pub(crate) async fn decode_normal_seg(
-    seg: EncodedSegment,
-) -> Result<DecodedSegment, TMFImportError> {
+    seg: EncodedSegment<'_>,
+) -> Result<DecodedSegment<'static>, TMFImportError> {
     if SectionType::NormalSegment == seg.seg_type() {
         let mut data: &[u8] = seg.data();
-        Ok(DecodedSegment::AppendNormal(
-            crate::normals::read_normal_array(&mut data)?,
-        ))
+        let normals = match seg.compresion_type() {
+            CompressionType::Lossless => crate::lossless::read_floats(&mut data)?,
+            _ => crate::normals::read_normal_array(&mut data)?,
+        };
+        Ok(DecodedSegment::AppendNormal(normals.into_vec().into()))
     } else {
-        panic!("Unreachable condition reached!");
+        Err(TMFImportError::UnexpectedSegmentType(seg.seg_type()))
     }
 }
 pub(crate) async fn decode_custom_seg(
-    seg: EncodedSegment,
+    seg: EncodedSegment<'_>,
     ctx: &crate::tmf_importer::TMFImportContext,
-) -> Result<DecodedSegment, TMFImportError> {
+) -> Result<DecodedSegment<'static>, TMFImportError> {
     if matches!(
         seg.seg_type(),
         SectionType::CustomIndexSegment
             | SectionType::CustomFloatSegment
-            | SectionType::CustomIntigerSegment
+            | SectionType::CustomIntegerSegment
             | SectionType::CustomColorRGBASegment
     ) {
         let mut data: &[u8] = seg.data();
-        Ok(DecodedSegment::AppendCustom(CustomDataSegment::read(
-            &mut data,
-            seg.seg_type(),
-            ctx,
-        )?))
+        Ok(DecodedSegment::AppendCustom(Cow::Owned(
+            CustomDataSegment::read(&mut data, seg.seg_type(), seg.compresion_type(), ctx)?,
+        )))
     } else {
-        panic!("Unreachable condition reached!");
+        Err(TMFImportError::UnexpectedSegmentType(seg.seg_type()))
     }
 }
 pub(crate) fn read_default_triangles<R: std::io::Read>(
@@ -266,59 +1245,73 @@ pub(crate) fn read_default_triangles<R: std::io::Read>(
     if length > MAX_SEG_SIZE as u64 {
         return Err(TMFImportError::SegmentTooLong);
     }
-    data.reserve(length as usize);
-    let buf = data.spare_capacity_mut();
+    // Indices are appended after ones already in *data*.
     let precision = UnalignedRWMode::precision_bits(precision_bits);
     let mut reader = UnalignedReader::new(src);
     if precision_bits == 0 {
-        use std::mem::MaybeUninit;
-        buf.fill(MaybeUninit::new(0));
+        data.resize(data.len() + length as usize, 0);
     } else {
-        for index in 0..(length as usize) / 2 {
+        crate::reserve_declared(data, length as usize);
+        for _ in 0..length / 2 {
             let (i1, i2) = reader.read2_unaligned(precision)?;
-            buf[index * 2].write((i1 + min) as IndexType);
-            buf[index * 2 + 1].write((i2 + min) as IndexType);
+            data.push((i1 + min) as IndexType);
+            data.push((i2 + min) as IndexType);
         }
         if length % 2 != 0 {
             let i = reader.read_unaligned(precision)?;
-            buf[(length - 1) as usize].write((i + min) as IndexType);
+            data.push((i + min) as IndexType);
         }
     }
-    unsafe { data.set_len(length as usize) }
     Ok(())
 }
-#[allow(dead_code)]
-fn read_triangle_sequence<R: std::io::Read>(
-    _src: R,
-    _data: &mut Vec<IndexType>,
+pub(crate) fn read_triangle_sequence<R: std::io::Read>(
+    mut src: R,
+    data: &mut Vec<IndexType>,
 ) -> Result<(), TMFImportError> {
-    todo!();
+    let start = src.read_u64()?;
+    let length = src.read_u64()?;
+    if length > MAX_SEG_SIZE as u64 {
+        return Err(TMFImportError::SegmentTooLong);
+    }
+    let last = start.checked_add(length.saturating_sub(1));
+    if last.is_none_or(|last| last > IndexType::MAX as u64) {
+        return Err(TMFImportError::IO(std::io::Error::other(
+            "Index sequence exceeds the range of index type.",
+        )));
+    }
+    data.extend((0..length).map(|offset| (start + offset) as IndexType));
+    Ok(())
+}
+/// Reads triangles compressed using *compresion_type* from *data*, appending them to *indices*.
+pub(crate) fn read_triangles_into(
+    data: &[u8],
+    compresion_type: CompressionType,
+    ctx: &TMFImportContext,
+    indices: &mut Vec<IndexType>,
+) -> Result<(), TMFImportError> {
+    match compresion_type {
+        CompressionType::None => read_default_triangles(data, indices, ctx),
+        CompressionType::Sequence => read_triangle_sequence(data, indices),
+        CompressionType::UnalignedLZZ => crate::lz77::read_triangles_lzz(data, indices, ctx),
+        CompressionType::RangeCoded => crate::range_coder::read_triangles_rc(data, indices),
+        CompressionType::Edgebreaker => crate::edgebreaker::read_triangles_eb(data, indices),
+        CompressionType::Omitted | CompressionType::Parallelogram | CompressionType::Lossless => {
+            Err(TMFImportError::UnsuportedCompressionType(
+                compresion_type as u8,
+            ))
+        }
+    }
 }
 pub(crate) async fn decode_triangle_seg(
-    seg: EncodedSegment,
+    seg: EncodedSegment<'_>,
     ctx: &crate::tmf_importer::TMFImportContext,
-) -> Result<DecodedSegment, TMFImportError> {
+) -> Result<DecodedSegment<'static>, TMFImportError> {
     if seg.seg_type().is_triangle() {
-        let data: &[u8] = seg.data();
+        if seg.compresion_type() == CompressionType::Omitted {
+            return Ok(DecodedSegment::OmittedTriangles(seg.seg_type()));
+        }
         let mut indices = Vec::new();
-        match seg.compresion_type() {
-            CompressionType::None => read_default_triangles(data, &mut indices, ctx)?,
-            CompressionType::Sequence => {
-                return Err(TMFImportError::UnsuportedCompressionType(
-                    CompressionType::Ommited as u8,
-                ))
-            } //read_triangle_sequence(data, &mut indices)?,
-            CompressionType::Ommited => {
-                return Err(TMFImportError::UnsuportedCompressionType(
-                    CompressionType::Ommited as u8,
-                ))
-            }
-            CompressionType::UnalignedLZZ => {
-                return Err(TMFImportError::UnsuportedCompressionType(
-                    CompressionType::UnalignedLZZ as u8,
-                ))
-            }
-        };
+        read_triangles_into(seg.data(), seg.compresion_type(), ctx, &mut indices)?;
         Ok(match seg.seg_type() {
             SectionType::VertexTriangleSegment => {
                 DecodedSegment::AppendTriangleVertex(indices.into())
@@ -330,10 +1323,10 @@ pub(crate) async fn decode_triangle_seg(
             SectionType::TangentTriangleSegment => {
                 DecodedSegment::AppendTriangleTangent(indices.into())
             }
-            _ => panic!("Unsupported section type {:?}", seg.seg_type()),
+            seg_type => return Err(TMFImportError::UnexpectedSegmentType(seg_type)),
         })
     } else {
-        panic!("Unreachable condition reached!");
+        Err(TMFImportError::UnexpectedSegmentType(seg.seg_type()))
     }
 }
 #[cfg(test)]
@@ -342,11 +1335,792 @@ fn init_test_env() {
 }
 #[cfg(test)]
 #[test]
+#[cfg(feature = "obj_import")]
+fn load_meshes_by_name() {
+    use crate::{TMFPrecisionInfo, TMFWriter};
+    use std::io::Cursor;
+    let (tmf_mesh, _) = crate::susan();
+    let names = ["first", "empty", "last"];
+    let meshes = [tmf_mesh.clone(), TMFMesh::empty(), tmf_mesh];
+    let prec = TMFPrecisionInfo::default();
+    for with_toc in [true, false] {
+        // Data before the file should not affect offsets.
+        let mut target = Cursor::new(vec![0; 7]);
+        target.set_position(7);
+        let mut writer = if with_toc {
+            TMFWriter::with_table_of_contents(target).unwrap()
+        } else {
+            TMFWriter::new(target).unwrap()
+        };
+        for (mesh, name) in meshes.iter().zip(names) {
+            writer.write_mesh(mesh, name, &prec).unwrap();
+        }
+        let out = writer.finish().unwrap().into_inner();
+        // Readers unaware of the table of contents still load all meshes.
+        assert_eq!(TMFMesh::read_tmf(&mut &out[7..]).unwrap().len(), 3);
+        let mut src = Cursor::new(out);
+        src.set_position(7);
+        let mut file = TMFFile::open(src).unwrap();
+        assert_eq!(file.mesh_names().collect::<Vec<_>>(), names);
+        for (mesh, name) in meshes.iter().zip(names).rev() {
+            let r_mesh = file.load_mesh(name).unwrap().unwrap();
+            assert_eq!(mesh.get_vertex_triangles(), r_mesh.get_vertex_triangles());
+        }
+        assert!(file.load_mesh("missing").unwrap().is_none());
+    }
+}
+#[cfg(test)]
+#[test]
+#[cfg(feature = "obj_import")]
+fn load_selected_segments() {
+    use crate::{CustomDataFilter, IndexType, TMFPrecisionInfo};
+    let (mut tmf_mesh, name) = crate::susan();
+    let indices: [IndexType; 4] = [0, 1, 2, 3];
+    tmf_mesh
+        .add_custom_data(indices[..].into(), "kept")
+        .unwrap();
+    tmf_mesh
+        .add_custom_data(indices[..].into(), "skipped")
+        .unwrap();
+    let mut unified = tmf_mesh.clone();
+    unified.unify_index_data();
+    let prec = TMFPrecisionInfo::default();
+    for mesh in [tmf_mesh, unified] {
+        let mut out = Vec::new();
+        mesh.write_tmf_one(&mut out, &prec, name.as_str()).unwrap();
+        let (full, _) = TMFMesh::read_tmf_one(&mut &out[..]).unwrap();
+        let options = TMFImportOptions {
+            normals: false,
+            uvs: false,
+            custom_data: CustomDataFilter::Named(vec!["kept".to_owned()]),
+            ..Default::default()
+        };
+        let (r_mesh, _) = TMFMesh::read_tmf_with_options(&mut &out[..], &options)
+            .unwrap()
+            .pop()
+            .unwrap();
+        r_mesh.verify().unwrap();
+        assert_eq!(r_mesh.get_vertices(), full.get_vertices());
+        assert_eq!(r_mesh.get_vertex_triangles(), full.get_vertex_triangles());
+        assert!(r_mesh.get_normals().is_none() && r_mesh.get_normal_triangles().is_none());
+        assert!(r_mesh.get_uvs().is_none() && r_mesh.get_uv_triangles().is_none());
+        assert!(r_mesh.lookup_custom_data("kept").is_some());
+        assert!(r_mesh.lookup_custom_data("skipped").is_none());
+        // Skipped segments must not affect reading of next meshes.
+        let mut reader = TMFReader::with_options(&out[..], options).unwrap();
+        assert!(reader.read_mesh().unwrap().is_ok());
+        assert!(reader.read_mesh().is_none());
+    }
+}
+#[cfg(test)]
+#[test]
+#[cfg(feature = "obj_import")]
+fn pass_unknown_segments_through() {
+    use crate::TMFPrecisionInfo;
+    let (mut out, tmf_mesh, name) = crate::susan_tmf();
+    // Append a segment of unknown type, with unknown compression, to the only mesh in the file.
+    let data = [1, 2, 3, 4, 5];
+    let count_pos =
+        crate::tmf_exporter::header_size_of(&out) + std::mem::size_of::<u16>() + name.len();
+    let segment_count = u16::from_le_bytes([out[count_pos], out[count_pos + 1]]);
+    out[count_pos..count_pos + 2].copy_from_slice(&(segment_count + 1).to_le_bytes());
+    out.push(200);
+    out.extend((data.len() as u32).to_le_bytes());
+    out.push(77);
+    out.extend(data);
+    let (r_mesh, _) = TMFMesh::read_tmf_one(&mut &out[..]).unwrap();
+    assert_eq!(
+        r_mesh.get_vertices().unwrap().len(),
+        tmf_mesh.get_vertices().unwrap().len()
+    );
+    let raw = r_mesh.get_raw_segments();
+    assert_eq!(raw.len(), 1);
+    assert_eq!((raw[0].type_id(), raw[0].compression()), (200, 77));
+    assert_eq!(raw[0].data(), data);
+    // Unknown segments are written back unchanged.
+    let mut rewritten = Vec::new();
+    r_mesh
+        .write_tmf_one(&mut rewritten, &TMFPrecisionInfo::default(), name.as_str())
+        .unwrap();
+    let (r_mesh, _) = TMFMesh::read_tmf_one(&mut &rewritten[..]).unwrap();
+    assert_eq!(r_mesh.get_raw_segments(), raw);
+    let info = crate::inspect(&rewritten[..]).unwrap();
+    assert!(info.meshes[0]
+        .segments
+        .iter()
+        .any(|seg| seg.segment_type == SectionType::Invalid && seg.byte_size == data.len() as u64));
+}
+#[cfg(test)]
+#[test]
+#[cfg(feature = "obj_import")]
+fn detect_corrupted_segments() {
+    use crate::{TMFPrecisionInfo, TMFWriter};
+    let (tmf_mesh, name) = crate::susan();
+    let prec = TMFPrecisionInfo {
+        checksums: true,
+        ..Default::default()
+    };
+    let mut out = Vec::new();
+    tmf_mesh
+        .write_tmf_one(&mut out, &prec, name.as_str())
+        .unwrap();
+    let min_minor = u16::from_le_bytes([out[9], out[10]]);
+    assert_eq!(min_minor, crate::CHECKSUM_TMF_MINOR);
+    // Checksums are flagged in header flags, so all bits of the mesh count can be used.
+    let mesh_count = u32::from_le_bytes(out[11..15].try_into().unwrap());
+    assert_eq!(mesh_count, 1);
+    assert_eq!(out[15], crate::CHECKSUMS_FLAG);
+    let mut unchecked = Vec::new();
+    let plain = TMFPrecisionInfo::default();
+    tmf_mesh
+        .write_tmf_one(&mut unchecked, &plain, name.as_str())
+        .unwrap();
+    // Only the flag decides if segments have checksums, not the version of reader required.
+    assert_eq!(
+        u16::from_le_bytes([unchecked[9], unchecked[10]]),
+        crate::CHECKSUM_TMF_MINOR
+    );
+    assert_eq!(unchecked[15], 0);
+    assert!(TMFMesh::read_tmf_one(&mut &unchecked[..]).is_ok());
+    // Files readable by older readers have no flags.
+    let mut point = TMFMesh::empty();
+    point.set_vertices([(0.0, 0.0, 0.0)]);
+    let mut old = Vec::new();
+    point.write_tmf_one(&mut old, &plain, "point").unwrap();
+    assert_eq!(u16::from_le_bytes([old[9], old[10]]), crate::MIN_TMF_MINOR);
+    let name_len = crate::tmf_exporter::TMF_HEADER_SIZE as usize;
+    assert_eq!(
+        u16::from_le_bytes([old[name_len], old[name_len + 1]]),
+        "point".len() as u16
+    );
+    assert!(TMFMesh::read_tmf_one(&mut &old[..]).is_ok());
+    let (r_mesh, _) = TMFMesh::read_tmf_one(&mut &out[..]).unwrap();
+    let (expected, _) = TMFMesh::read_tmf_one(&mut &unchecked[..]).unwrap();
+    assert_eq!(r_mesh.get_vertices(), expected.get_vertices());
+    assert!(TMFMesh::read_tmf_from_slice(&out).is_ok());
+    assert!(futures::executor::block_on(TMFMesh::read_tmf_async(&mut &out[..])).is_ok());
+    let info = crate::inspect(&out[..]).unwrap();
+    let segment_count = info.meshes[0].segments.len();
+    // Corrupt the last byte of the last segment.
+    let last = out.len() - 1;
+    out[last] ^= 0x10;
+    for res in [
+        TMFMesh::read_tmf(&mut &out[..]).map(|_| ()),
+        TMFMesh::read_tmf_from_slice(&out).map(|_| ()),
+        TMFReader::new(&out[..])
+            .unwrap()
+            .read_mesh_into(&mut TMFMeshBuffers::default())
+            .unwrap()
+            .map(|_| ()),
+    ] {
+        let err = res.expect_err("Corrupted segment should not be read");
+        let TMFImportError::ChecksumMismatch { mesh, segment } = err.cause() else {
+            panic!("Expected a checksum mismatch, got {err}");
+        };
+        assert_eq!(
+            (mesh.as_str(), *segment),
+            (name.as_str(), segment_count - 1)
+        );
+        let location = err.location().unwrap();
+        assert_eq!(location.mesh_name.as_deref(), Some(name.as_str()));
+        assert_eq!(location.segment_index, Some(segment_count - 1));
+    }
+    // Checksums are used for the whole file written by a writer, so all meshes must enable them.
+    let mut writer = TMFWriter::with_table_of_contents(std::io::Cursor::new(Vec::new())).unwrap();
+    writer.write_mesh(&tmf_mesh, "checked", &prec).unwrap();
+    assert!(matches!(
+        writer.write_mesh(&tmf_mesh, "unchecked", &plain),
+        Err(crate::TMFExportError::ChecksumsMismatch)
+    ));
+    writer.write_mesh(&tmf_mesh, "also_checked", &prec).unwrap();
+    let out = writer.finish().unwrap().into_inner();
+    assert_eq!(
+        u16::from_le_bytes([out[9], out[10]]),
+        crate::CHECKSUM_TMF_MINOR
+    );
+    let mut tmf_file = TMFFile::open(std::io::Cursor::new(&out[..])).unwrap();
+    assert!(tmf_file.load_mesh("also_checked").unwrap().is_some());
+    assert_eq!(TMFMesh::read_tmf(&mut &out[..]).unwrap().len(), 2);
+}
+#[cfg(test)]
+#[test]
+#[cfg(feature = "obj_import")]
+fn locate_errors() {
+    use crate::{TMFPrecisionInfo, TMFWriter};
+    let (tmf_mesh, name) = crate::susan();
+    let mut writer = TMFWriter::new(std::io::Cursor::new(Vec::new())).unwrap();
+    let prec = TMFPrecisionInfo::default();
+    writer
+        .write_mesh(&TMFMesh::empty(), "empty", &prec)
+        .unwrap();
+    writer.write_mesh(&tmf_mesh, &name, &prec).unwrap();
+    let mut out = writer.finish().unwrap().into_inner();
+    let info = crate::inspect(&out[..]).unwrap();
+    let seg_type = info.meshes[1].segments[1].segment_type;
+    // Offset of the second segment of the second mesh.
+    let mesh_offset = crate::tmf_exporter::header_size_of(&out) + 2 + "empty".len() + 2;
+    let first_seg = mesh_offset + 2 + name.len() + 2;
+    let offset = first_seg + 6 + info.meshes[1].segments[0].byte_size as usize;
+    let expected = TMFErrorLocation {
+        mesh_index: Some(1),
+        mesh_name: Some(name.clone()),
+        segment_index: Some(1),
+        segment_type: Some(seg_type),
+        offset: Some(offset as u64),
+    };
+    let truncated = &out[..offset + 10];
+    let mut too_long = out.clone();
+    too_long[offset + 1..offset + 5].copy_from_slice(&u32::MAX.to_le_bytes());
+    for (data, is_cause) in [
+        (
+            truncated,
+            (|err| matches!(err, TMFImportError::IO(_))) as fn(&TMFImportError) -> bool,
+        ),
+        (&too_long[..], |err| {
+            matches!(err, TMFImportError::SegmentTooLong)
+        }),
+    ] {
+        let mut reader = TMFReader::new(data).unwrap();
+        assert!(reader.read_mesh().unwrap().is_ok());
+        for res in [
+            TMFMesh::read_tmf(&mut &data[..]).map(|_| ()),
+            TMFMesh::read_tmf_from_slice(data).map(|_| ()),
+            reader.read_mesh().unwrap().map(|_| ()),
+        ] {
+            let err = res.expect_err("Malformed file should not be read");
+            assert!(is_cause(err.cause()), "Unexpected error {err}");
+            assert_eq!(err.location(), Some(&expected));
+        }
+        // Meshes read asynchronously are loaded into memory before being decoded, so only the mesh is known.
+        let err = futures::executor::block_on(TMFMesh::read_tmf_async(&mut &data[..]))
+            .err()
+            .unwrap();
+        assert!(is_cause(err.cause()), "Unexpected error {err}");
+        let location = err.location().unwrap();
+        assert_eq!(location.mesh_index, Some(1));
+        assert_eq!(location.offset, Some(mesh_offset as u64));
+    }
+    let err = TMFFile::open(std::io::Cursor::new(&too_long))
+        .err()
+        .unwrap();
+    assert_eq!(err.location().unwrap().segment_index, Some(1));
+    // Errors which occur while decoding segments are located too.
+    out[offset + 5] = CompressionType::Parallelogram as u8;
+    for res in [
+        TMFMesh::read_tmf(&mut &out[..]).map(|_| ()),
+        TMFMesh::read_tmf_from_slice(&out).map(|_| ()),
+        TMFReader::new(&out[..])
+            .unwrap()
+            .nth(1)
+            .unwrap()
+            .map(|_| ()),
+    ] {
+        let err = res.expect_err("Malformed file should not be read");
+        assert!(matches!(
+            err.cause(),
+            TMFImportError::UnsuportedCompressionType(_)
+        ));
+        assert_eq!(err.location(), Some(&expected));
+    }
+    let mut reader = TMFReader::new(&out[..]).unwrap();
+    let mut buffers = TMFMeshBuffers::default();
+    reader.read_mesh_into(&mut buffers).unwrap().unwrap();
+    let err = reader.read_mesh_into(&mut buffers).unwrap().unwrap_err();
+    assert_eq!(err.location(), Some(&expected));
+}
+#[cfg(test)]
+#[test]
+#[cfg(feature = "obj_import")]
+fn enforce_import_limits() {
+    use crate::{ImportLimit, ImportLimits};
+    let (out, _, _) = crate::susan_tmf();
+    let info = crate::inspect(&out[..]).unwrap();
+    let decoded_size = info.meshes[0]
+        .segments
+        .iter()
+        .filter_map(TMFSegmentInfo::decoded_size)
+        .sum::<u64>();
+    let exact = ImportLimits {
+        max_total_bytes: info.meshes[0].byte_size().max(decoded_size),
+        max_vertices: info.meshes[0].sizes().vertices as u64,
+        max_meshes: 1,
+        max_segments: info.meshes[0].segments.len() as u16,
+    };
+    let read = |limits: ImportLimits| -> Vec<Result<(), TMFImportError>> {
+        let options = TMFImportOptions {
+            limits,
+            ..Default::default()
+        };
+        let mut buffers = TMFMeshBuffers::default();
+        vec![
+            TMFMesh::read_tmf_with_options(&mut &out[..], &options).map(|_| ()),
+            crate::tmf_importer::import_from_slice(&out, &options).map(|_| ()),
+            futures::executor::block_on(TMFImportContext::import_async(&out[..], &options))
+                .map(|_| ()),
+            TMFReader::with_options(&out[..], options.clone())
+                .and_then(|mut reader| reader.read_mesh_into(&mut buffers).unwrap().map(|_| ())),
+        ]
+    };
+    assert!(read(exact).iter().all(Result::is_ok));
+    for (limits, limit) in [
+        (
+            ImportLimits {
+                max_total_bytes: exact.max_total_bytes - 1,
+                ..exact
+            },
+            ImportLimit::TotalBytes,
+        ),
+        (
+            ImportLimits {
+                max_vertices: exact.max_vertices - 1,
+                ..exact
+            },
+            ImportLimit::Vertices,
+        ),
+        (
+            ImportLimits {
+                max_meshes: 0,
+                ..exact
+            },
+            ImportLimit::Meshes,
+        ),
+        (
+            ImportLimits {
+                max_segments: exact.max_segments - 1,
+                ..exact
+            },
+            ImportLimit::Segments,
+        ),
+    ] {
+        for res in read(limits) {
+            let err = res.expect_err("Limit should be exceeded");
+            assert!(
+                matches!(err.cause(), TMFImportError::LimitExceeded(exceeded) if *exceeded == limit),
+                "Expected {limit:?} to be exceeded, got {err}"
+            );
+        }
+    }
+    // Lenient imports do not recover from exceeded limits.
+    let lenient = |file: &[u8], limits: ImportLimits| {
+        let options = TMFImportOptions {
+            limits,
+            ..Default::default()
+        };
+        TMFMesh::read_tmf_lenient(&mut &file[..], &options)
+    };
+    assert!(lenient(&out, exact).is_ok_and(|recovered| recovered.is_complete()));
+    let limits = ImportLimits {
+        max_vertices: exact.max_vertices - 1,
+        ..exact
+    };
+    let Err(err) = lenient(&out, limits) else {
+        panic!("Limit should be exceeded");
+    };
+    assert!(matches!(
+        err.cause(),
+        TMFImportError::LimitExceeded(ImportLimit::Vertices)
+    ));
+    // Data past the biggest file within limits is not read.
+    let mut long = out.clone();
+    long.resize(out.len() + max_file_size(&exact) as usize, 0);
+    let Err(err) = lenient(&long, exact) else {
+        panic!("Limit should be exceeded");
+    };
+    assert!(matches!(
+        err.cause(),
+        TMFImportError::LimitExceeded(ImportLimit::TotalBytes)
+    ));
+    // Counts are checked before memory is allocated for them.
+    let mut huge = out[..crate::tmf_exporter::header_size_of(&out)].to_vec();
+    let count_pos = crate::tmf_exporter::TMF_HEADER_SIZE as usize - std::mem::size_of::<u32>();
+    huge[count_pos..count_pos + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+    let options = TMFImportOptions {
+        limits: ImportLimits {
+            max_meshes: 16,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let Err(err) = TMFMesh::read_tmf_with_options(&mut &huge[..], &options) else {
+        panic!("Limit should be exceeded");
+    };
+    assert!(matches!(
+        err.cause(),
+        TMFImportError::LimitExceeded(ImportLimit::Meshes)
+    ));
+    assert_eq!(err.location().unwrap().offset, Some(count_pos as u64));
+}
+#[cfg(test)]
+#[test]
+#[cfg(feature = "obj_import")]
+fn limit_opened_files() {
+    use crate::{ImportLimit, ImportLimits, TMFPrecisionInfo, TMFWriter};
+    use std::io::Cursor;
+    let (tmf_mesh, _) = crate::susan();
+    let prec = TMFPrecisionInfo::default();
+    let expect_exceeded = |err: Option<TMFImportError>, limit: ImportLimit| {
+        let err = err.unwrap_or_else(|| panic!("Expected {limit:?} to be exceeded"));
+        assert!(
+            matches!(err.cause(), TMFImportError::LimitExceeded(exceeded) if *exceeded == limit),
+            "Expected {limit:?} to be exceeded, got {err}"
+        );
+    };
+    for with_toc in [true, false] {
+        let target = Cursor::new(Vec::new());
+        let mut writer = if with_toc {
+            TMFWriter::with_table_of_contents(target).unwrap()
+        } else {
+            TMFWriter::new(target).unwrap()
+        };
+        writer.write_mesh(&tmf_mesh, "first", &prec).unwrap();
+        writer.write_mesh(&tmf_mesh, "second", &prec).unwrap();
+        let out = writer.finish().unwrap().into_inner();
+        let info = crate::inspect(&out[..]).unwrap();
+        let mesh = &info.meshes[0];
+        let decoded_size = mesh
+            .segments
+            .iter()
+            .filter_map(TMFSegmentInfo::decoded_size)
+            .sum::<u64>();
+        // Enough to load exactly one of the meshes.
+        let one_mesh = ImportLimits {
+            max_total_bytes: mesh.byte_size().max(decoded_size),
+            max_meshes: 2,
+            max_segments: mesh.segments.len() as u16,
+            ..Default::default()
+        };
+        let open = |limits| TMFFile::open_with_limits(Cursor::new(&out[..]), limits);
+        let mut file = open(one_mesh).unwrap();
+        assert!(file.load_mesh("second").unwrap().is_some());
+        // Limits are shared by all loaded meshes.
+        expect_exceeded(file.load_mesh("first").err(), ImportLimit::TotalBytes);
+        expect_exceeded(
+            open(ImportLimits {
+                max_meshes: 1,
+                ..one_mesh
+            })
+            .err(),
+            ImportLimit::Meshes,
+        );
+        // Meshes longer than allowed are rejected when located, whether a table of contents is present or not.
+        expect_exceeded(
+            open(ImportLimits {
+                max_total_bytes: 16,
+                ..one_mesh
+            })
+            .err(),
+            ImportLimit::TotalBytes,
+        );
+        // Segments are only counted while indexing, since a table of contents does not store them.
+        let fewer_segments = ImportLimits {
+            max_segments: one_mesh.max_segments - 1,
+            ..one_mesh
+        };
+        if with_toc {
+            let mut file = open(fewer_segments).unwrap();
+            expect_exceeded(file.load_mesh("first").err(), ImportLimit::Segments);
+        } else {
+            expect_exceeded(open(fewer_segments).err(), ImportLimit::Segments);
+        }
+    }
+}
+#[cfg(test)]
+#[test]
+fn limit_memory_of_decoded_segments() {
+    use crate::{ImportLimit, ImportLimits};
+    // A tiny file, with a sequence segment claiming to store 2^31 - 1 vertex indices.
+    let mut file = Vec::new();
+    crate::tmf_exporter::write_tmf_header(
+        &mut file,
+        1,
+        false,
+        CompressionType::Sequence.min_minor(),
+    )
+    .unwrap();
+    crate::tmf_exporter::write_mesh_name(&mut file, "").unwrap();
+    file.extend(1_u16.to_le_bytes());
+    file.push(SectionType::VertexTriangleSegment as u8);
+    file.extend((2 * std::mem::size_of::<u64>() as u32).to_le_bytes());
+    file.push(CompressionType::Sequence as u8);
+    file.extend(0_u64.to_le_bytes());
+    file.extend((i32::MAX as u64).to_le_bytes());
+    let options = TMFImportOptions {
+        limits: ImportLimits {
+            max_total_bytes: 1024,
+            max_vertices: 1000,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut buffers = TMFMeshBuffers::default();
+    for res in [
+        TMFMesh::read_tmf_with_options(&mut &file[..], &options).map(|_| ()),
+        import_from_slice(&file, &options).map(|_| ()),
+        TMFReader::with_options(&file[..], options.clone())
+            .and_then(|mut reader| reader.read_mesh_into(&mut buffers).unwrap().map(|_| ())),
+    ] {
+        let err = res.expect_err("Limit should be exceeded");
+        assert!(
+            matches!(
+                err.cause(),
+                TMFImportError::LimitExceeded(ImportLimit::TotalBytes)
+            ),
+            "Expected limit to be exceeded, got {err}"
+        );
+    }
+}
+#[cfg(test)]
+#[test]
+#[cfg(feature = "obj_import")]
+fn recover_damaged_files() {
+    use crate::{TMFPrecisionInfo, TMFWriter};
+    let (tmf_mesh, name) = crate::susan();
+    let prec = TMFPrecisionInfo {
+        checksums: true,
+        ..Default::default()
+    };
+    let mut writer = TMFWriter::new(std::io::Cursor::new(Vec::new())).unwrap();
+    writer.write_mesh(&tmf_mesh, "first", &prec).unwrap();
+    writer.write_mesh(&tmf_mesh, &name, &prec).unwrap();
+    let mut out = writer.finish().unwrap().into_inner();
+    let options = TMFImportOptions::default();
+    let expected = TMFMesh::read_tmf(&mut &out[..]).unwrap();
+    let recovered = TMFMesh::read_tmf_lenient(&mut &out[..], &options).unwrap();
+    assert!(recovered.is_complete());
+    assert_eq!(recovered.meshes.len(), 2);
+    // A download cut off in the middle of the second mesh.
+    let info = crate::inspect(&out[..]).unwrap();
+    let first_len = 2 + "first".len() + 2 + info.meshes[0].segments.len() * 10;
+    let first_len = first_len + info.meshes[0].byte_size() as usize;
+    let first_seg = crate::tmf_exporter::header_size_of(&out) + first_len + 2 + name.len() + 2;
+    let cut = first_seg + 10 + info.meshes[1].segments[0].byte_size as usize + 20;
+    assert!(TMFMesh::read_tmf(&mut &out[..cut]).is_err());
+    let recovered = TMFMesh::read_tmf_lenient(&mut &out[..cut], &options).unwrap();
+    assert_eq!(recovered.meshes.len(), 2);
+    assert_eq!(recovered.meshes[1].1, name);
+    assert_eq!(
+        recovered.meshes[0].0.get_vertex_triangles(),
+        expected[0].0.get_vertex_triangles()
+    );
+    // Only the first segment of the damaged mesh was read completely.
+    let partial = &recovered.meshes[1].0;
+    let first_type = info.meshes[1].segments[0].segment_type;
+    assert_eq!(
+        partial.get_vertices().is_some(),
+        first_type == SectionType::VertexSegment
+    );
+    assert!(partial.get_uvs().is_none());
+    assert_eq!(recovered.problems.len(), 1);
+    let location = recovered.problems[0].location().unwrap();
+    assert_eq!(
+        (location.mesh_index, location.segment_index),
+        (Some(1), Some(1))
+    );
+    // A corrupted segment is skipped, but the rest of the file is still read.
+    out[first_seg + 10] ^= 0xFF;
+    let recovered = TMFMesh::read_tmf_lenient(&mut &out[..], &options).unwrap();
+    assert_eq!(recovered.meshes.len(), 2);
+    if first_type != SectionType::UvSegment {
+        assert_eq!(recovered.meshes[1].0.get_uvs(), expected[1].0.get_uvs());
+    }
+    assert_eq!(recovered.problems.len(), 1);
+    let location = recovered.problems[0].location().unwrap();
+    assert_eq!(
+        (location.mesh_index, location.segment_index),
+        (Some(1), Some(0))
+    );
+    assert!(matches!(
+        recovered.problems[0].cause(),
+        TMFImportError::ChecksumMismatch { segment: 0, .. }
+    ));
+    // Data read before an IO error is recovered too.
+    struct FailingReader<'a>(&'a [u8]);
+    impl Read for FailingReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.0.is_empty() {
+                return Err(std::io::Error::other("Connection lost"));
+            }
+            Read::read(&mut self.0, buf)
+        }
+    }
+    let mut src = FailingReader(&out[..cut]);
+    let recovered = TMFMesh::read_tmf_lenient(&mut src, &options).unwrap();
+    assert_eq!(recovered.meshes.len(), 2);
+    assert!(matches!(
+        recovered.problems[0].cause(),
+        TMFImportError::IO(_)
+    ));
+    assert!(TMFMesh::read_tmf_lenient(&mut &out[..10], &options).is_err());
+}
+#[cfg(test)]
+#[test]
+#[cfg(feature = "obj_import")]
+fn drop_predicted_vertices_of_lost_triangles() {
+    use crate::{TMFPrecisionInfo, VertexPredictionMode};
+    let (tmf_mesh, name) = crate::susan();
+    let prec = TMFPrecisionInfo {
+        checksums: true,
+        vertex_prediction: VertexPredictionMode::Parallelogram,
+        ..Default::default()
+    };
+    let mut out = Vec::new();
+    tmf_mesh
+        .write_tmf_one(&mut out, &prec, name.as_str())
+        .unwrap();
+    let info = crate::inspect(&out[..]).unwrap();
+    let segments = &info.meshes[0].segments;
+    let is_type = |seg_type| move |seg: &&TMFSegmentInfo| seg.segment_type == seg_type;
+    let predicted = segments
+        .iter()
+        .filter(is_type(SectionType::VertexSegment))
+        .count();
+    let triangles = segments
+        .iter()
+        .position(|seg| seg.segment_type == SectionType::VertexTriangleSegment)
+        .unwrap();
+    // Corrupt data of the first segment storing vertex triangles.
+    let mut offset = crate::tmf_exporter::header_size_of(&out) + 2 + name.len() + 2;
+    for seg in &segments[..triangles] {
+        offset += 10 + seg.byte_size as usize;
+    }
+    out[offset + 10] ^= 0xFF;
+    let recovered = TMFMesh::read_tmf_lenient(&mut &out[..], &TMFImportOptions::default()).unwrap();
+    let (mesh, _) = &recovered.meshes[0];
+    assert!(mesh.get_vertices().is_none());
+    assert!(mesh.get_normals().is_some());
+    assert!(matches!(
+        recovered.problems[0].cause(),
+        TMFImportError::ChecksumMismatch { .. }
+    ));
+    let missing: Vec<_> = recovered.problems[1..]
+        .iter()
+        .filter(|err| matches!(err.cause(), TMFImportError::MissingPredictionTriangles))
+        .collect();
+    assert_eq!(missing.len(), predicted);
+    let location = missing[0].location().unwrap();
+    assert_eq!(location.segment_type, Some(SectionType::VertexSegment));
+}
+#[cfg(test)]
+#[test]
+#[cfg(feature = "obj_import")]
+fn read_meshes_async() {
+    use crate::TMFPrecisionInfo;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+    /// Reader returning one byte at a time, and only every other time it is polled.
+    struct Trickle<'a> {
+        data: &'a [u8],
+        is_ready: bool,
+    }
+    impl AsyncRead for Trickle<'_> {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<std::io::Result<usize>> {
+            self.is_ready = !self.is_ready;
+            if !self.is_ready {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            let len = buf.len().min(self.data.len()).min(1);
+            buf[..len].copy_from_slice(&self.data[..len]);
+            self.data = &self.data[len..];
+            Poll::Ready(Ok(len))
+        }
+    }
+    let (tmf_mesh, name) = crate::susan();
+    let mut out = Vec::new();
+    let meshes = [(tmf_mesh.clone(), name.clone()), (tmf_mesh, name)];
+    TMFMesh::write_tmf(&meshes, &mut out, &TMFPrecisionInfo::default()).unwrap();
+    let expected = TMFMesh::read_tmf(&mut &out[..]).unwrap();
+    let mut src = Trickle {
+        data: &out,
+        is_ready: false,
+    };
+    let r_meshes = futures::executor::block_on(TMFMesh::read_tmf_async(&mut src)).unwrap();
+    assert_eq!(r_meshes.len(), expected.len());
+    for ((r_mesh, r_name), (mesh, name)) in r_meshes.iter().zip(&expected) {
+        assert_eq!(r_name, name);
+        assert_eq!(r_mesh.get_vertices(), mesh.get_vertices());
+        assert_eq!(r_mesh.get_vertex_triangles(), mesh.get_vertex_triangles());
+    }
+    // Truncated files are reported as errors, instead of waiting for more data.
+    let mut src = &out[..(out.len() - 16)];
+    assert!(futures::executor::block_on(TMFMesh::read_tmf_async(&mut src)).is_err());
+}
+#[cfg(test)]
+#[test]
+#[cfg(feature = "obj_import")]
+fn read_meshes_from_slice() {
+    use crate::{TMFPrecisionInfo, TMFWriter};
+    let (tmf_mesh, name) = crate::susan();
+    let mut unified = tmf_mesh.clone();
+    unified.unify_index_data();
+    let mut writer = TMFWriter::new(std::io::Cursor::new(Vec::new())).unwrap();
+    for mesh in [&tmf_mesh, &unified] {
+        writer
+            .write_mesh(mesh, &name, &TMFPrecisionInfo::default())
+            .unwrap();
+    }
+    let out = writer.finish().unwrap().into_inner();
+    let expected = TMFMesh::read_tmf(&mut &out[..]).unwrap();
+    let r_meshes = TMFMesh::read_tmf_from_slice(&out).unwrap();
+    assert_eq!(r_meshes.len(), expected.len());
+    for ((r_mesh, r_name), (mesh, name)) in r_meshes.iter().zip(&expected) {
+        assert_eq!(r_name, name);
+        assert_eq!(r_mesh.get_vertices(), mesh.get_vertices());
+        assert_eq!(r_mesh.get_normals(), mesh.get_normals());
+        assert_eq!(r_mesh.get_uv_triangles(), mesh.get_uv_triangles());
+    }
+    // Shared segments with some kinds of triangles skipped.
+    let options = TMFImportOptions {
+        normals: false,
+        ..Default::default()
+    };
+    let (r_mesh, _) = import_from_slice(&out, &options).unwrap().pop().unwrap();
+    r_mesh.verify().unwrap();
+    assert!(r_mesh.get_normal_triangles().is_none());
+    assert_eq!(r_mesh.get_uv_triangles(), expected[1].0.get_uv_triangles());
+    assert!(TMFMesh::read_tmf_from_slice(&out[..(out.len() - 16)]).is_err());
+}
+#[cfg(test)]
+#[test]
+#[cfg(feature = "obj_import")]
+fn read_meshes_one_by_one() {
+    use crate::TMFPrecisionInfo;
+    let (tmf_mesh, name) = crate::susan();
+    let meshes = [
+        (tmf_mesh.clone(), format!("{name}0")),
+        (TMFMesh::empty(), format!("{name}1")),
+        (tmf_mesh, format!("{name}2")),
+    ];
+    let mut out = Vec::new();
+    TMFMesh::write_tmf(&meshes, &mut out, &TMFPrecisionInfo::default()).unwrap();
+    let all = TMFMesh::read_tmf(&mut &out[..]).unwrap();
+    let mut reader = TMFReader::new(&out[..]).unwrap();
+    assert_eq!(reader.remaining(), 3);
+    for (mesh, name) in &all {
+        let (r_mesh, r_name) = reader.next().unwrap().unwrap();
+        assert_eq!(*name, r_name);
+        assert_eq!(mesh.get_vertex_triangles(), r_mesh.get_vertex_triangles());
+        assert_eq!(mesh.get_vertices(), r_mesh.get_vertices());
+    }
+    assert!(reader.next().is_none());
+    // Truncated file stops the reader after the first error.
+    let mut reader = TMFReader::new(&out[..(out.len() - 16)]).unwrap();
+    assert!(reader.next().unwrap().is_ok());
+    assert!(reader.next().unwrap().is_ok());
+    assert!(reader.next().unwrap().is_err());
+    assert!(reader.next().is_none());
+}
+#[cfg(test)]
+#[test]
+#[cfg(feature = "obj_import")]
 fn test() {
     use crate::TMFPrecisionInfo;
     init_test_env();
-    let mut file = std::fs::File::open("testing/susan.obj").unwrap();
-    let (tmf_mesh, name) = TMFMesh::read_from_obj_one(&mut file).unwrap();
+    let (tmf_mesh, name) = crate::susan();
     tmf_mesh.verify().unwrap();
     assert!(name == "Suzanne", "Name should be Suzanne but is {name}");
     let prec = TMFPrecisionInfo::default();
@@ -354,7 +2128,11 @@ fn test() {
     {
         tmf_mesh.write_tmf_one(&mut out, &prec, name).unwrap();
     }
-    let _imported = runtime_agnostic_block_on!(TMFImportContext::import(&out[..])).unwrap();
+    let _imported = runtime_agnostic_block_on!(TMFImportContext::import(
+        &out[..],
+        &TMFImportOptions::default()
+    ))
+    .unwrap();
 }
 #[cfg(test)]
 #[test]
@@ -366,7 +2144,10 @@ fn test_triangles_opt() {
         tmp.push(i);
     }
     let tris = DecodedSegment::AppendTriangleVertex(tmp.into());
-    let tris = runtime_agnostic_block_on!(tris.optimize());
+    let tris = runtime_agnostic_block_on!(tris.optimize(
+        &TMFPrecisionInfo::default(),
+        &crate::tmf_exporter::EncodeInfo::default()
+    ));
     let tris: Vec<EncodedSegment> = tris
         .into_iter()
         .map(|seg| {
@@ -376,12 +2157,7 @@ fn test_triangles_opt() {
             .unwrap()
         })
         .collect();
-    let ctx = TMFImportContext::init_header(TMFHeader {
-        major: crate::TMF_MAJOR,
-        minor: crate::TMF_MINOR,
-        min_major: crate::MIN_TMF_MAJOR,
-        min_minor: crate::MIN_TMF_MINOR,
-    });
+    let ctx = TMFImportContext::current();
     let tris: Vec<DecodedSegment> = tris
         .into_iter()
         .map(|seg| {
@@ -403,3 +2179,43 @@ fn test_triangles_opt() {
         }
     }
 }
+#[cfg(test)]
+#[test]
+fn test_triangle_sequences() {
+    use crate::tmf_exporter::EncodeInfo;
+    use crate::TMFPrecisionInfo;
+    let mut tmp: Vec<IndexType> = (0..100).map(|i| (i * 7919) % 1000).collect();
+    tmp.extend(1000..3000);
+    tmp.extend((0..100).map(|i| (i * 104_729) % 1000));
+    tmp.extend(500..2500);
+    let tris = DecodedSegment::AppendTriangleVertex(tmp.clone().into());
+    let tris = runtime_agnostic_block_on!(tris.optimize(
+        &TMFPrecisionInfo::default(),
+        &crate::tmf_exporter::EncodeInfo::default()
+    ));
+    let tris: Vec<EncodedSegment> = tris
+        .into_iter()
+        .map(|seg| {
+            runtime_agnostic_block_on!(
+                seg.encode(&TMFPrecisionInfo::default(), &EncodeInfo::default())
+            )
+            .unwrap()
+        })
+        .collect();
+    let sequence_count = tris
+        .iter()
+        .filter(|seg| seg.compresion_type() == CompressionType::Sequence)
+        .count();
+    assert_eq!(sequence_count, 2);
+    let ctx = TMFImportContext::current();
+    let mut decoded = Vec::new();
+    for seg in tris {
+        let seg = runtime_agnostic_block_on!(DecodedSegment::decode(seg, &ctx)).unwrap();
+        if let DecodedSegment::AppendTriangleVertex(vals) = seg {
+            decoded.extend_from_slice(&vals);
+        } else {
+            panic!()
+        }
+    }
+    assert_eq!(tmp, decoded);
+}
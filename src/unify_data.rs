@@ -17,8 +17,8 @@ fn unfiy_data_common<const DATA_COUNT: usize>(
     let mut new_indices = Vec::with_capacity(indices.len());
     for index in 0..len {
         let mut curr_combo: ComboType<DATA_COUNT> = [0; DATA_COUNT];
-        for sub_index in 0..DATA_COUNT {
-            curr_combo[sub_index] = indices[sub_index][index];
+        for (combo_entry, index_arr) in curr_combo.iter_mut().zip(indices) {
+            *combo_entry = index_arr[index];
         }
         let curr_id = *combos.entry(curr_combo).or_insert_with(|| {
             let id = combo_id;
@@ -137,13 +137,13 @@ pub(crate) fn smart_merge_data_3<A: Copy, B: Copy, C: Copy>(
     if !is_merge_needed(&indices) {
         return (None, None, None, None);
     }
-    if !a.is_some_and(|data| !data.is_empty()) || indices[0].is_none() {
+    if a.is_none_or(|data| data.is_empty()) || indices[0].is_none() {
         let (b, c, indices) = smart_merge_data_2(b, c, [indices[1], indices[2]]);
         (None, b, c, indices)
-    } else if !b.is_some_and(|data| !data.is_empty()) || indices[1].is_none() {
+    } else if b.is_none_or(|data| data.is_empty()) || indices[1].is_none() {
         let (a, c, indices) = smart_merge_data_2(a, c, [indices[0], indices[2]]);
         (a, None, c, indices)
-    } else if !c.is_some_and(|data| !data.is_empty()) || indices[2].is_none() {
+    } else if c.is_none_or(|data| data.is_empty()) || indices[2].is_none() {
         let (a, b, indices) = smart_merge_data_2(a, b, [indices[0], indices[1]]);
         (a, b, None, indices)
     } else if let Some((((a, indices_a), (b, indices_b)), (c, indices_c))) = a
@@ -173,16 +173,16 @@ pub(crate) fn smart_merge_data_4<A: Copy, B: Copy, C: Copy, D: Copy>(
     if !is_merge_needed(&indices) {
         return (None, None, None, None, None);
     }
-    if !a.is_some_and(|data| !data.is_empty()) || indices[0].is_none() {
+    if a.is_none_or(|data| data.is_empty()) || indices[0].is_none() {
         let (b, c, d, indices) = smart_merge_data_3(b, c, d, [indices[1], indices[2], indices[3]]);
         (None, b, c, d, indices)
-    } else if !b.is_some_and(|data| !data.is_empty()) || indices[1].is_none() {
+    } else if b.is_none_or(|data| data.is_empty()) || indices[1].is_none() {
         let (a, c, d, indices) = smart_merge_data_3(a, c, d, [indices[0], indices[2], indices[3]]);
         (a, None, c, d, indices)
-    } else if !c.is_some_and(|data| !data.is_empty()) || indices[2].is_none() {
+    } else if c.is_none_or(|data| data.is_empty()) || indices[2].is_none() {
         let (a, b, d, indices) = smart_merge_data_3(a, b, d, [indices[0], indices[1], indices[3]]);
         (a, b, None, d, indices)
-    } else if !d.is_some_and(|data| !data.is_empty()) || indices[3].is_none() {
+    } else if d.is_none_or(|data| data.is_empty()) || indices[3].is_none() {
         let (a, b, c, indices) = smart_merge_data_3(a, b, c, [indices[0], indices[1], indices[2]]);
         (a, b, c, None, indices)
     } else if let Some((((a, indices_a), (b, indices_b)), ((c, indices_c), (d, indices_d)))) = a
@@ -212,7 +212,7 @@ fn read_susan_obj() {
     tmf_mesh.unify_index_data();
     tmf_mesh.verify().unwrap();
     let mut out = std::fs::File::create("target/test_res/susan_unified.obj").unwrap();
-    tmf_mesh.write_obj_one(&mut out, &"SUSAN").unwrap();
+    tmf_mesh.write_obj_one(&mut out, "SUSAN").unwrap();
     assert_eq!(
         tmf_mesh.get_vertex_triangles().unwrap(),
         tmf_mesh.get_normal_triangles().unwrap()
@@ -222,9 +222,9 @@ fn read_susan_obj() {
         .write_tmf_one(&mut out, &TMFPrecisionInfo::default(), "SUSAN")
         .unwrap();
     let mut out = std::fs::File::open("target/test_res/susan_unified.tmf").unwrap();
-    let (r_mesh, name) = TMFMesh::read_tmf_one(&mut out).unwrap();
+    let (r_mesh, _name) = TMFMesh::read_tmf_one(&mut out).unwrap();
     let mut out = std::fs::File::create("target/test_res/suan_unified_ftmf.obj").unwrap();
-    r_mesh.write_obj_one(&mut out, &"SUSAN").unwrap();
+    r_mesh.write_obj_one(&mut out, "SUSAN").unwrap();
 }
 #[test]
 #[cfg(feature = "obj_import")]
@@ -243,7 +243,7 @@ fn hand_optimized() {
     let mut out = std::fs::File::create("target/test_res/susan_ho.tmf").unwrap();
     let tmf_prec = TMFPrecisionInfo {
         normal_precision: NormalPrecisionMode::from_deg_dev(5.0),
-        vertex_precision: VertexPrecisionMode(0.5),
+        vertex_precision: VertexPrecisionMode::relative(0.5),
         uv_prec: UvPrecisionMode::form_texture_resolution(1024.0, 1.0),
         ..TMFPrecisionInfo::default()
     };
@@ -251,7 +251,7 @@ fn hand_optimized() {
         .write_tmf_one(&mut out, &tmf_prec, "SUSAN")
         .unwrap();
     let mut out = std::fs::File::open("target/test_res/susan_ho.tmf").unwrap();
-    let (r_mesh, name) = TMFMesh::read_tmf_one(&mut out).unwrap();
+    let (r_mesh, _name) = TMFMesh::read_tmf_one(&mut out).unwrap();
     let mut out = std::fs::File::create("target/test_res/suan_ho_ftmf.obj").unwrap();
-    r_mesh.write_obj_one(&mut out, &"SUSAN").unwrap();
+    r_mesh.write_obj_one(&mut out, "SUSAN").unwrap();
 }
@@ -6,15 +6,51 @@ use crate::{FloatType, Vector2};
 use std::io::{Read, Write};
 /// Setting dictating how precisely the UV coordinates should be saved.
 #[derive(Clone, Copy, PartialEq)]
-pub struct UvPrecisionMode(u8);
+pub struct UvPrecisionMode {
+    precision: UvPrecision,
+}
+/// Kinds of [`UvPrecisionMode`].
+#[derive(Clone, Copy, PartialEq)]
+enum UvPrecision {
+    /// UVs are quantized, with this many bits per coordinate.
+    Bits(u8),
+    /// UVs are saved exactly, as floats.
+    Lossless,
+}
+/// Most bits UVs can be saved with.
+const MAX_UV_BITS: u8 = u64::BITS as u8 - 1;
 impl UvPrecisionMode {
+    /// UVs are saved exactly, so they are read back bit for bit the same.
+    /// ```
+    /// # use tmf::{TMFPrecisionInfo, UvPrecisionMode};
+    /// let prec = TMFPrecisionInfo {
+    ///     uv_prec: UvPrecisionMode::LOSSLESS,
+    ///     ..TMFPrecisionInfo::default()
+    /// };
+    /// ```
+    pub const LOSSLESS: Self = Self {
+        precision: UvPrecision::Lossless,
+    };
+    pub(crate) fn is_lossless(&self) -> bool {
+        self.precision == UvPrecision::Lossless
+    }
     /// Creates a new [`UvPrecisionMode`] form texture resolution and maximal allowed deviation in pixels.
     /// ```
     /// # use tmf::UvPrecisionMode;
     /// let mode = UvPrecisionMode::form_texture_resolution(1024.0,0.1);
     /// ```
     pub fn form_texture_resolution(resolution: f32, pixel_dev: f32) -> Self {
-        Self((resolution / pixel_dev).log2().ceil() as u8)
+        let bits = ((resolution / pixel_dev).log2().ceil() as u8).min(MAX_UV_BITS);
+        Self {
+            precision: UvPrecision::Bits(bits),
+        }
+    }
+    /// Returns the amount of bits each quantized coordinate is saved with. Lossless UVs are not quantized, so they use the most bits quantized UVs can have.
+    fn bits(&self) -> u8 {
+        match self.precision {
+            UvPrecision::Bits(bits) => bits,
+            UvPrecision::Lossless => MAX_UV_BITS,
+        }
     }
 }
 impl Default for UvPrecisionMode {
@@ -35,7 +71,7 @@ pub fn save_uvs<W: Write>(
     writer: &mut W,
     precision: UvPrecisionMode,
 ) -> std::io::Result<()> {
-    let precision = precision.0;
+    let precision = precision.bits();
     let multpiler = ((1 << precision) - 1) as FloatType;
     writer.write_all(&[precision])?;
     writer.write_all(&(uvs.len() as u64).to_le_bytes())?;
@@ -51,18 +87,28 @@ pub fn save_uvs<W: Write>(
     Ok(())
 }
 pub fn read_uvs<R: Read>(reader: &mut R) -> Result<Box<[Vector2]>, TMFImportError> {
+    let mut uvs = Vec::new();
+    read_uvs_into(reader, &mut uvs)?;
+    Ok(uvs.into())
+}
+/// Reads UVs from *reader*, appending them to *uvs*.
+pub(crate) fn read_uvs_into<R: Read>(
+    reader: &mut R,
+    uvs: &mut Vec<Vector2>,
+) -> Result<(), TMFImportError> {
     let precision = reader.read_u8()?;
     let count = reader.read_u64()?;
     if count > MAX_SEG_SIZE as u64 {
         return Err(TMFImportError::SegmentTooLong);
     }
     if precision == 0 {
-        return Ok(vec![(0.0, 0.0); count as usize].into());
+        uvs.resize(uvs.len() + count as usize, (0.0, 0.0));
+        return Ok(());
     }
     if precision >= u64::BITS as u8 {
         return Err(TMFImportError::InvalidPrecision(precision));
     }
-    let mut uvs = Vec::with_capacity(count as usize);
+    crate::reserve_declared(uvs, count as usize);
     let divisor = ((1_u64 << precision) - 1) as FloatType;
     let precision = UnalignedRWMode::precision_bits(precision);
     let mut reader = UnalignedReader::new(reader);
@@ -72,7 +118,7 @@ pub fn read_uvs<R: Read>(reader: &mut R) -> Result<Box<[Vector2]>, TMFImportErro
         let y = (y as FloatType) / divisor;
         uvs.push((x, y));
     }
-    Ok(uvs.into())
+    Ok(())
 }
 #[cfg(test)]
 mod test {
@@ -1,5 +1,5 @@
 use crate::utilis::*;
-use crate::{FloatType, IndexType, TMFMesh};
+use crate::{FloatType, IndexType, TMFMeshRef};
 /// Enum representing the result of integrity check.
 #[derive(Clone)]
 pub enum TMFIntegrityStatus {
@@ -61,13 +61,13 @@ impl std::fmt::Debug for TMFIntegrityStatus {
 /// Checks if indices fit inside the array of *length*, returned None if so or Some(index) if not.
 fn indices_inside_array(array: &[IndexType], length: IndexType) -> Option<IndexType> {
     for index in array {
-        if *index > length as IndexType {
+        if *index >= length {
             return Some(*index);
         }
     }
     None
 }
-fn verify_vertices(mesh: &TMFMesh) -> Result<(), TMFIntegrityStatus> {
+fn verify_vertices(mesh: &TMFMeshRef<'_>) -> Result<(), TMFIntegrityStatus> {
     match mesh.get_vertices() {
         None => {
             if mesh.get_vertex_triangles().is_some() {
@@ -88,7 +88,7 @@ fn verify_vertices(mesh: &TMFMesh) -> Result<(), TMFIntegrityStatus> {
         },
     }
 }
-fn verify_uvs(mesh: &TMFMesh) -> Result<(), TMFIntegrityStatus> {
+fn verify_uvs(mesh: &TMFMeshRef<'_>) -> Result<(), TMFIntegrityStatus> {
     match mesh.get_uvs() {
         None => {
             if mesh.get_uv_triangles().is_some() {
@@ -117,7 +117,7 @@ fn verify_uvs(mesh: &TMFMesh) -> Result<(), TMFIntegrityStatus> {
     }
 }
 //TODO: handle multiple errors
-fn verify_normals(mesh: &TMFMesh) -> Result<(), TMFIntegrityStatus> {
+fn verify_normals(mesh: &TMFMeshRef<'_>) -> Result<(), TMFIntegrityStatus> {
     match mesh.get_normals() {
         None => {
             if mesh.get_normal_triangles().is_some() {
@@ -147,7 +147,7 @@ fn verify_normals(mesh: &TMFMesh) -> Result<(), TMFIntegrityStatus> {
         }
     }
 }
-pub(crate) fn verify_tmf_mesh(mesh: &TMFMesh) -> Result<(), TMFIntegrityStatus> {
+pub(crate) fn verify_tmf_mesh(mesh: &TMFMeshRef<'_>) -> Result<(), TMFIntegrityStatus> {
     let mut errors = Vec::new();
     // Verify vertices
     let vertices_status = verify_vertices(mesh);
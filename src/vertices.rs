@@ -1,161 +1,521 @@
+use crate::range_coder::{RangeDecoder, RangeEncoder, ValueModel};
 use crate::read_extension::ReadExt;
 use crate::unaligned_rw::{UnalignedRWMode, UnalignedReader, UnalignedWriter};
 use crate::TMFImportError;
 use crate::MAX_SEG_SIZE;
 use crate::{FloatType, IndexType, Vector3};
 use std::io::{Read, Write};
-#[derive(Clone, Copy, PartialEq)]
-/// Setting dictating how much the length of any edge can change because of vertex precision loss during saving. This is expressed as a fraction of the length of the shortest edge.
+#[derive(Clone, Copy, PartialEq, Debug)]
+/// Setting dictating how much can positions of vertices deviate because of precision loss during saving.
 ///```
 /// # use tmf::VertexPrecisionMode;
 /// // No edge will deviate by more than 0.1(10%) of the shortest edge in the model,
 /// // eg. for edges 10, 234, and 1, their length will not change by more than  1*0.1 = 0.1
-/// let ten_percent = VertexPrecisionMode(0.1);
-/// // No edge will deviate by more than 0.025(2.5%) of the shortest edge in the model,
-/// // eg. for edges 10, 234, and 1, their length will not change by more than  1*0.025 = 0.025
-/// let two_and_half_percent = VertexPrecisionMode(0.025);
+/// let ten_percent = VertexPrecisionMode::relative(0.1);
+/// // No vertex will move by more than 0.0001 along any axis, eg. 0.1 mm for a model in meters.
+/// let tenth_of_mm = VertexPrecisionMode::absolute(0.0001);
+/// // Vertices may move by 0.01 horizontally, but only by 0.001 vertically.
+/// let terrain = VertexPrecisionMode::absolute_per_axis((0.01, 0.001, 0.01));
+/// // Vertices are read back bit for bit the same.
+/// let exact = VertexPrecisionMode::LOSSLESS;
 ///```
-pub struct VertexPrecisionMode(pub FloatType);
+pub struct VertexPrecisionMode {
+    precision: VertexPrecision,
+}
+/// Kinds of [`VertexPrecisionMode`], kept private so new ones can be added without breaking its users.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum VertexPrecision {
+    /// How much can the length of any edge change, expressed as a fraction of the length of the shortest edge in the mesh.
+    Relative(FloatType),
+    /// How much can a vertex move along the x, y and z axis, in units of the model.
+    Absolute(Vector3),
+    /// Vertices are saved exactly, as floats.
+    Lossless,
+}
+/// Creates a mode in which the length of no edge changes by more than *fraction* of the length of the shortest edge in the mesh.
+/// Kept so code written when [`VertexPrecisionMode`] was a tuple struct still compiles. Equivalent to [`VertexPrecisionMode::relative`].
+///```
+/// # use tmf::VertexPrecisionMode;
+/// assert!(VertexPrecisionMode(0.1) == VertexPrecisionMode::relative(0.1));
+///```
+#[allow(non_snake_case)]
+pub const fn VertexPrecisionMode(fraction: FloatType) -> VertexPrecisionMode {
+    VertexPrecisionMode::relative(fraction)
+}
+impl VertexPrecisionMode {
+    /// Vertices are saved exactly, as floats, so they are read back bit for bit the same. Takes much more space than other modes.
+    pub const LOSSLESS: Self = Self {
+        precision: VertexPrecision::Lossless,
+    };
+    /// Creates a mode in which the length of no edge changes by more than *fraction* of the length of the shortest edge in the mesh.
+    /// Meshes without edges, like point clouds, are saved as if their shortest edge was 0.1 long.
+    pub const fn relative(fraction: FloatType) -> Self {
+        Self {
+            precision: VertexPrecision::Relative(fraction),
+        }
+    }
+    /// Creates a mode in which no vertex moves by more than *deviation* along any axis, in units of the model. Does not depend on the shape of the mesh.
+    ///```
+    /// # use tmf::VertexPrecisionMode;
+    /// assert!(VertexPrecisionMode::absolute(0.5) == VertexPrecisionMode::absolute_per_axis((0.5, 0.5, 0.5)));
+    ///```
+    pub const fn absolute(deviation: FloatType) -> Self {
+        Self::absolute_per_axis((deviation, deviation, deviation))
+    }
+    /// Creates a mode in which no vertex moves by more than *deviations* along the x, y and z axis, in units of the model.
+    pub const fn absolute_per_axis(deviations: Vector3) -> Self {
+        Self {
+            precision: VertexPrecision::Absolute(deviations),
+        }
+    }
+    /// Returns `true` if this mode depends on the length of the shortest edge of the mesh.
+    pub(crate) fn is_relative(&self) -> bool {
+        matches!(self.precision, VertexPrecision::Relative(_))
+    }
+    /// Returns `true` if vertices are saved exactly.
+    pub(crate) fn is_lossless(&self) -> bool {
+        self.precision == VertexPrecision::Lossless
+    }
+    /// Returns the largest deviations of a vertex along the x, y and z axis, for a mesh with *shortest_edge*.
+    pub(crate) fn max_deviations(&self, shortest_edge: FloatType) -> [FloatType; 3] {
+        match self.precision {
+            // A vertex deviating by `d` along each axis moves by at most `d*sqrt(3)`, and an edge changes by at most the sum of movements of its ends.
+            VertexPrecision::Relative(fraction) => {
+                [shortest_edge * fraction / (2.0 * (3.0 as FloatType).sqrt()); 3]
+            }
+            VertexPrecision::Absolute((x, y, z)) => [x, y, z],
+            VertexPrecision::Lossless => [0.0; 3],
+        }
+    }
+}
 impl Default for VertexPrecisionMode {
     /// Default maximum edge deviation is 0.1 or 10%
     ///```
     ///# use tmf::VertexPrecisionMode;
-    /// let mode = VertexPrecisionMode(0.1);
+    /// let mode = VertexPrecisionMode::relative(0.1);
     /// let default_mode =  VertexPrecisionMode::default();
     /// // The same
     /// assert!(mode == default_mode);
     fn default() -> Self {
-        Self(0.1)
+        Self::relative(0.1)
+    }
+}
+/// Most bits a coordinate is saved with. Coordinates are decoded as [`FloatType`], so more bits would not make them more precise.
+const MAX_VERTEX_BITS: u8 = FloatType::MANTISSA_DIGITS as u8;
+/// Quantization of coordinates along one axis, as a fraction of the span between the smallest and the biggest coordinate.
+#[derive(Clone, Copy, Debug)]
+struct AxisQuantizer {
+    min: FloatType,
+    max: FloatType,
+    bits: u8,
+}
+impl AxisQuantizer {
+    /// Creates a quantizer for *coords*, with just enough bits for no coordinate to deviate by more than *max_deviation*.
+    fn new(coords: impl Iterator<Item = FloatType>, max_deviation: FloatType) -> Self {
+        let (min, max) = coords.fold(
+            (FloatType::INFINITY, FloatType::NEG_INFINITY),
+            |(min, max), coord| (min.min(coord), max.max(coord)),
+        );
+        let span = f64::from(max - min);
+        // Rounding to the nearest of `2^bits - 1` steps, so coordinates deviate by at most half of a step.
+        let steps = span / (2.0 * f64::from(max_deviation));
+        let bits = ((steps + 1.0).log2().ceil() as u8).clamp(1, MAX_VERTEX_BITS);
+        Self { min, max, bits }
+    }
+    fn span(&self) -> FloatType {
+        self.max - self.min
+    }
+    fn max_quantized(&self) -> u64 {
+        (1_u64 << self.bits) - 1
+    }
+    /// Decodes *quantized* coordinate, exactly as readers do.
+    fn dequantize(&self, quantized: u64) -> FloatType {
+        (quantized as FloatType) / (self.max_quantized() as FloatType) * self.span() + self.min
+    }
+    /// Returns the quantized value which decodes closest to *coord*.
+    fn quantize(&self, coord: FloatType) -> u64 {
+        let max_quantized = self.max_quantized();
+        let fraction = (f64::from(coord) - f64::from(self.min)) / f64::from(self.span());
+        // Casts saturate, so NaN(from a span of 0) and negative fractions become 0.
+        let quantized = ((fraction * max_quantized as f64).round() as u64).min(max_quantized);
+        // Readers decode using `FloatType`, whose rounding may make a neighbouring value decode closer.
+        let error = |quantized: u64| (self.dequantize(quantized) - coord).abs();
+        [
+            quantized,
+            quantized.saturating_sub(1),
+            (quantized + 1).min(max_quantized),
+        ]
+        .into_iter()
+        .min_by(|a, b| error(*a).total_cmp(&error(*b)))
+        .unwrap_or(quantized)
     }
 }
+/// Returns the coordinate of *vertex* along *axis*.
+fn coord(vertex: &Vector3, axis: usize) -> FloatType {
+    [vertex.0, vertex.1, vertex.2][axis]
+}
+/// Checks if the bounding box of *vertices* has a finite size along all axes, which quantizing them requires.
+pub(crate) fn has_finite_span(vertices: &[Vector3]) -> bool {
+    (0..3).all(|axis| {
+        let (min, max) = vertices.iter().map(|vertex| coord(vertex, axis)).fold(
+            (FloatType::INFINITY, FloatType::NEG_INFINITY),
+            |(min, max), coord| (min.min(coord), max.max(coord)),
+        );
+        (max - min).is_finite()
+    })
+}
+/// Saves *vertices* so that none of them deviates by more than *precision* allows, rounding each coordinate to the nearest step inside the bounding box.
 pub fn save_tmf_vertices<W: Write>(
     vertices: &[Vector3],
     precision: VertexPrecisionMode,
     writer: &mut W,
     shortest_edge: FloatType,
 ) -> std::io::Result<()> {
-    let mut min_x = vertices[0].0;
-    let mut max_x = vertices[0].0;
-    let mut min_y = vertices[0].1;
-    let mut max_y = vertices[0].1;
-    let mut min_z = vertices[0].2;
-    let mut max_z = vertices[0].2;
+    let deviations = precision.max_deviations(shortest_edge);
+    let axes = [0, 1, 2].map(|axis| {
+        AxisQuantizer::new(
+            vertices.iter().map(|vertex| coord(vertex, axis)),
+            deviations[axis],
+        )
+    });
     writer.write_all(&(vertices.len() as u64).to_le_bytes())?;
-    //Get min and max x,y,z coords
-    for vertex in vertices {
-        min_x = min_x.min(vertex.0);
-        max_x = max_y.max(vertex.1);
-        min_y = min_z.min(vertex.2);
-        max_y = max_x.max(vertex.0);
-        min_z = min_y.min(vertex.1);
-        max_z = max_z.max(vertex.2);
-    }
-    writer.write_all(&(min_x as f64).to_le_bytes())?;
-    writer.write_all(&(max_x as f64).to_le_bytes())?;
-    writer.write_all(&(min_y as f64).to_le_bytes())?;
-    writer.write_all(&(max_y as f64).to_le_bytes())?;
-    writer.write_all(&(min_z as f64).to_le_bytes())?;
-    writer.write_all(&(max_z as f64).to_le_bytes())?;
-    //Calculate size of the model
-    let sx = max_x - min_x;
-    let sy = max_y - min_y;
-    let sz = max_z - min_z;
-    // Ensure model size is not 0(prevents bugs)
-    let sx = sx.max(0.000_001);
-    let sy = sy.max(0.000_001);
-    let sz = sz.max(0.000_001);
-    //Calculate minimal increment
-    let inc_x = (shortest_edge / sx) * precision.0;
-    let inc_y = (shortest_edge / sy) * precision.0;
-    let inc_z = (shortest_edge / sy) * precision.0;
-    //Calculate write precision
-    let prec_x = (1.0 / inc_x).log2().ceil() as u8;
-    let prec_y = (1.0 / inc_y).log2().ceil() as u8;
-    let prec_z = (1.0 / inc_z).log2().ceil() as u8;
-    //Ensure precision is never below 1
-    let prec_x = prec_x.max(1);
-    let prec_y = prec_y.max(1);
-    let prec_z = prec_z.max(1);
+    // Write bounding box
+    for axis in &axes {
+        writer.write_all(&f64::from(axis.min).to_le_bytes())?;
+        writer.write_all(&f64::from(axis.max).to_le_bytes())?;
+    }
     // Write precision info
-    writer.write_all(&[prec_x])?;
-    writer.write_all(&[prec_y])?;
-    writer.write_all(&[prec_z])?;
-    // Calculate float save multiplier
-    let mul_x = ((1 << prec_x) - 1) as FloatType;
-    let mul_y = ((1 << prec_y) - 1) as FloatType;
-    let mul_z = ((1 << prec_z) - 1) as FloatType;
-    // Create unaligned rw modes
-    let prec_x = UnalignedRWMode::precision_bits(prec_x);
-    let prec_y = UnalignedRWMode::precision_bits(prec_y);
-    let prec_z = UnalignedRWMode::precision_bits(prec_z);
-    // Create writer
+    for axis in &axes {
+        writer.write_all(&[axis.bits])?;
+    }
+    let modes = axes.map(|axis| UnalignedRWMode::precision_bits(axis.bits));
     let mut writer = UnalignedWriter::new(writer);
     for vertex in vertices {
-        let x = (((vertex.0 - min_x) / sx) * mul_x) as u64;
-        let y = (((vertex.1 - min_y) / sy) * mul_y) as u64;
-        let z = (((vertex.2 - min_z) / sz) * mul_z) as u64;
-
-        writer.write_unaligned(prec_x, x)?;
-        writer.write_unaligned(prec_y, y)?;
-        writer.write_unaligned(prec_z, z)?;
+        for (axis, (quantizer, mode)) in axes.iter().zip(modes).enumerate() {
+            writer.write_unaligned(mode, quantizer.quantize(coord(vertex, axis)))?;
+        }
     }
     writer.flush()?;
     Ok(())
 }
+/// Panics if any of *decoded* vertices deviates from the original *vertices* along some axis by more than *max_deviations* allow. Used to check encoders in debug builds.
+/// Decoded coordinates are also allowed to be off by rounding of floating-point numbers, and vertices with coordinates which are not finite are not checked.
+pub(crate) fn check_deviations(
+    vertices: &[Vector3],
+    decoded: &[Vector3],
+    max_deviations: [FloatType; 3],
+) {
+    assert_eq!(
+        vertices.len(),
+        decoded.len(),
+        "Decoded vertex count differs!"
+    );
+    let finite = |vertex: &&Vector3| (0..3).all(|axis| coord(vertex, axis).is_finite());
+    let magnitude = vertices
+        .iter()
+        .filter(finite)
+        .flat_map(|vertex| (0..3).map(|axis| coord(vertex, axis).abs()))
+        .fold(0.0, FloatType::max);
+    // Decoding rounds a couple of times, by no more than an epsilon of the biggest coordinate each time.
+    let rounding = 4.0 * FloatType::EPSILON * magnitude;
+    for (vertex, r_vertex) in vertices.iter().zip(decoded) {
+        if !finite(&vertex) {
+            continue;
+        }
+        for (axis, max_deviation) in max_deviations.iter().enumerate() {
+            let deviation = (coord(vertex, axis) - coord(r_vertex, axis)).abs();
+            assert!(
+                deviation <= max_deviation + rounding,
+                "Vertex {vertex:?} was saved as {r_vertex:?}, deviating by {deviation} along axis {axis}, but at most {max_deviation} was allowed!"
+            );
+        }
+    }
+}
 pub fn read_tmf_vertices<R: Read>(reader: &mut R) -> Result<Box<[Vector3]>, TMFImportError> {
+    let mut vertices = Vec::new();
+    read_tmf_vertices_into(reader, &mut vertices)?;
+    Ok(vertices.into())
+}
+/// Reads vertices from *reader*, appending them to *vertices*.
+pub(crate) fn read_tmf_vertices_into<R: Read>(
+    reader: &mut R,
+    vertices: &mut Vec<Vector3>,
+) -> Result<(), TMFImportError> {
     let vertex_count = reader.read_u64()? as usize;
     // Read data bounding box
-    let min_x = reader.read_f64()? as FloatType;
-    let max_x = reader.read_f64()? as FloatType;
-    let min_y = reader.read_f64()? as FloatType;
-    let max_y = reader.read_f64()? as FloatType;
-    let min_z = reader.read_f64()? as FloatType;
-    let max_z = reader.read_f64()? as FloatType;
+    let mut bounds = [(0.0, 0.0); 3];
+    for (min, max) in &mut bounds {
+        *min = reader.read_f64()? as FloatType;
+        *max = reader.read_f64()? as FloatType;
+    }
     // Read precision
-    let prec_x = reader.read_u8()?;
-    if prec_x >= u64::BITS as u8 {
-        return Err(TMFImportError::InvalidPrecision(prec_x));
-    }
-    let prec_y = reader.read_u8()?;
-    if prec_y >= u64::BITS as u8 {
-        return Err(TMFImportError::InvalidPrecision(prec_y));
-    }
-    let prec_z = reader.read_u8()?;
-    if prec_z >= u64::BITS as u8 {
-        return Err(TMFImportError::InvalidPrecision(prec_z));
-    }
-    // Calculate float read divisor
-    let div_x = ((1_u64 << prec_x) - 1) as FloatType;
-    let div_y = ((1_u64 << prec_y) - 1) as FloatType;
-    let div_z = ((1_u64 << prec_z) - 1) as FloatType;
-    // Create unaligned rw modes
-    let prec_x = UnalignedRWMode::precision_bits(prec_x);
-    let prec_y = UnalignedRWMode::precision_bits(prec_y);
-    let prec_z = UnalignedRWMode::precision_bits(prec_z);
-    //Calculate size of the model
-    let sx = max_x - min_x;
-    let sy = max_y - min_y;
-    let sz = max_z - min_z;
+    let mut axes = [AxisQuantizer {
+        min: 0.0,
+        max: 0.0,
+        bits: 0,
+    }; 3];
+    for (axis, (min, max)) in axes.iter_mut().zip(bounds) {
+        let bits = reader.read_u8()?;
+        if bits >= u64::BITS as u8 {
+            return Err(TMFImportError::InvalidPrecision(bits));
+        }
+        *axis = AxisQuantizer { min, max, bits };
+    }
+    let modes = axes.map(|axis| UnalignedRWMode::precision_bits(axis.bits));
     //Create arrays to write data into
     if vertex_count > MAX_SEG_SIZE {
         return Err(TMFImportError::SegmentTooLong);
     }
-    let mut vertices = Vec::with_capacity(vertex_count);
+    crate::reserve_declared(vertices, vertex_count);
     let mut reader = UnalignedReader::new(reader);
     for _ in 0..vertex_count {
-        //let (x,y) = reader.read_pair_unaligned(prec_x,prec_y)?;
-        let x = reader.read_unaligned(prec_x)?;
-        let y = reader.read_unaligned(prec_y)?;
-        let z = reader.read_unaligned(prec_z)?;
-        let x = (x as FloatType) / div_x;
-        let y = (y as FloatType) / div_y;
-        let z = (z as FloatType) / div_z;
-        let x = x * sx + min_x;
-        let y = y * sy + min_y;
-        let z = z * sz + min_z;
+        let x = axes[0].dequantize(reader.read_unaligned(modes[0])?);
+        let y = axes[1].dequantize(reader.read_unaligned(modes[1])?);
+        let z = axes[2].dequantize(reader.read_unaligned(modes[2])?);
         vertices.push((x, y, z));
     }
-    Ok(vertices.into())
+    Ok(())
+}
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+/// Setting dictating if positions of vertices are predicted from already saved vertices, with only differences from predictions being saved.
+///```
+/// # use tmf::{TMFPrecisionInfo, VertexPredictionMode};
+/// let prec = TMFPrecisionInfo {
+///     vertex_prediction: VertexPredictionMode::Parallelogram,
+///     ..TMFPrecisionInfo::default()
+/// };
+///```
+pub enum VertexPredictionMode {
+    /// Each vertex is saved on its own, inside the bounding box of the mesh.
+    Disabled,
+    /// Vertices are visited in order of triangles using them, and their positions are predicted using the parallelogram rule. Differences from predictions are entropy coded.
+    /// Vertices are rounded to the nearest step, which is the same along all axes and small enough for all of them, so no vertex deviates by more than [`VertexPrecisionMode`] allows.
+    /// Predicted vertices are only saved if they are smaller than vertices saved without prediction.
+    Parallelogram,
+}
+impl Default for VertexPredictionMode {
+    /// Vertex prediction is disabled by default.
+    ///```
+    /// # use tmf::VertexPredictionMode;
+    /// assert!(VertexPredictionMode::default() == VertexPredictionMode::Disabled);
+    ///```
+    fn default() -> Self {
+        Self::Disabled
+    }
+}
+/// Biggest amount of quantization steps along an axis, above which steps can't be represented exactly by a `f64`.
+const MAX_PREDICTED_STEPS: f64 = (1_u64 << 52) as f64;
+/// Vertices used to predict position of a vertex.
+#[derive(Clone, Copy, Debug)]
+enum Prediction {
+    /// Predicted as `a + b - c`, completing a parallelogram with a neighbouring triangle.
+    Parallelogram(usize, usize, usize),
+    /// Predicted as the middle of an edge of the triangle.
+    Midpoint(usize, usize),
+    /// Predicted as the position of another vertex of the triangle.
+    Neighbour(usize),
+    /// Predicted as the position of previously visited vertex, or the origin if no vertex was visited yet.
+    Previous,
+}
+/// Returns vertices in order of their first use by *triangles*, and vertices used to predict their positions. Indices in *triangles* are offset by *offset*, and triangles using vertices outside of `0..count` are ignored.
+fn prediction_order(
+    triangles: &[IndexType],
+    offset: usize,
+    count: usize,
+) -> Vec<(usize, Prediction)> {
+    let triangles: Vec<[usize; 3]> = triangles
+        .chunks_exact(3)
+        .filter_map(|triangle| {
+            let mut local = [0; 3];
+            for (local, index) in local.iter_mut().zip(triangle) {
+                *local = (*index as usize)
+                    .checked_sub(offset)
+                    .filter(|index| *index < count)?;
+            }
+            Some(local)
+        })
+        .collect();
+    let mut edges: std::collections::HashMap<(usize, usize), Vec<usize>> =
+        std::collections::HashMap::with_capacity(triangles.len() * 3);
+    for (tri_index, triangle) in triangles.iter().enumerate() {
+        for k in 0..3 {
+            edges
+                .entry((triangle[k], triangle[(k + 1) % 3]))
+                .or_default()
+                .push(tri_index);
+        }
+    }
+    let mut visited = vec![false; count];
+    let mut order = Vec::with_capacity(count);
+    for triangle in &triangles {
+        for k in 0..3 {
+            let (vertex, a, b) = (triangle[k], triangle[(k + 1) % 3], triangle[(k + 2) % 3]);
+            if visited[vertex] {
+                continue;
+            }
+            let prediction = match (visited[a], visited[b]) {
+                (true, true) => edges
+                    .get(&(b, a))
+                    .and_then(|neighbours| {
+                        neighbours.iter().find_map(|neighbour| {
+                            let opposite = triangles[*neighbour]
+                                .into_iter()
+                                .find(|index| *index != a && *index != b)?;
+                            visited[opposite].then_some(opposite)
+                        })
+                    })
+                    .map_or(Prediction::Midpoint(a, b), |opposite| {
+                        Prediction::Parallelogram(a, b, opposite)
+                    }),
+                (true, false) => Prediction::Neighbour(a),
+                (false, true) => Prediction::Neighbour(b),
+                (false, false) => Prediction::Previous,
+            };
+            visited[vertex] = true;
+            order.push((vertex, prediction));
+        }
+    }
+    for (vertex, visited) in visited.iter().enumerate() {
+        if !visited {
+            order.push((vertex, Prediction::Previous));
+        }
+    }
+    order
+}
+fn predict(quantized: &[[i64; 3]], prediction: Prediction, previous: Option<usize>) -> [i64; 3] {
+    let mut res = [0; 3];
+    for (axis, res) in res.iter_mut().enumerate() {
+        *res = match prediction {
+            Prediction::Parallelogram(a, b, c) => quantized[a][axis]
+                .wrapping_add(quantized[b][axis])
+                .wrapping_sub(quantized[c][axis]),
+            Prediction::Midpoint(a, b) => quantized[a][axis]
+                .wrapping_add(quantized[b][axis])
+                .wrapping_div(2),
+            Prediction::Neighbour(a) => quantized[a][axis],
+            Prediction::Previous => previous.map_or(0, |previous| quantized[previous][axis]),
+        };
+    }
+    res
+}
+/// Saves *vertices*, visiting them in order of *triangles* and storing only differences from their predicted positions. Returns `false` and writes nothing if the vertices can't be quantized with the required precision.
+pub(crate) fn save_predicted_vertices<W: Write>(
+    vertices: &[Vector3],
+    triangles: &[IndexType],
+    precision: VertexPrecisionMode,
+    shortest_edge: FloatType,
+    writer: &mut W,
+) -> std::io::Result<bool> {
+    // Rounding to nearest step, so no vertex moves by more than half of a step.
+    // Only one step is saved, so it must be small enough for all axes.
+    let max_deviation = precision
+        .max_deviations(shortest_edge)
+        .into_iter()
+        .fold(FloatType::INFINITY, FloatType::min);
+    let step = 2.0 * f64::from(max_deviation);
+    if vertices.is_empty() || !(step.is_finite() && step > 0.0) {
+        return Ok(false);
+    }
+    let mut min = [f64::INFINITY; 3];
+    let mut max = [f64::NEG_INFINITY; 3];
+    for vertex in vertices {
+        let vertex = [vertex.0, vertex.1, vertex.2].map(f64::from);
+        for axis in 0..3 {
+            min[axis] = min[axis].min(vertex[axis]);
+            max[axis] = max[axis].max(vertex[axis]);
+        }
+    }
+    let is_too_big = |axis: usize| {
+        let steps = (max[axis] - min[axis]) / step;
+        !steps.is_finite() || steps >= MAX_PREDICTED_STEPS
+    };
+    if (0..3).any(is_too_big) {
+        return Ok(false);
+    }
+    let quantized: Box<[[i64; 3]]> = vertices
+        .iter()
+        .map(|vertex| {
+            let vertex = [vertex.0, vertex.1, vertex.2].map(f64::from);
+            [0, 1, 2].map(|axis| ((vertex[axis] - min[axis]) / step).round() as i64)
+        })
+        .collect();
+    writer.write_all(&(vertices.len() as u64).to_le_bytes())?;
+    for origin in min {
+        writer.write_all(&origin.to_le_bytes())?;
+    }
+    writer.write_all(&step.to_le_bytes())?;
+    let mut encoder = RangeEncoder::new(&mut *writer);
+    let mut models = [ValueModel::new(), ValueModel::new(), ValueModel::new()];
+    let mut previous = None;
+    for (vertex, prediction) in prediction_order(triangles, 0, vertices.len()) {
+        let predicted = predict(&quantized, prediction, previous);
+        for axis in 0..3 {
+            models[axis].encode_delta(
+                &mut encoder,
+                quantized[vertex][axis] as u64,
+                predicted[axis] as u64,
+            )?;
+        }
+        previous = Some(vertex);
+    }
+    encoder.finish()?;
+    Ok(true)
+}
+/// Vertices saved as differences from their predicted positions. Positions can only be reconstructed once triangles using the vertices are known.
+#[derive(Clone, Debug)]
+pub(crate) struct PredictedVertices {
+    origin: [f64; 3],
+    step: f64,
+    residuals: Box<[[i64; 3]]>,
+}
+impl PredictedVertices {
+    /// Reconstructs positions of vertices, which start at *offset* in the vertex array used by *triangles*.
+    pub(crate) fn reconstruct(&self, triangles: &[IndexType], offset: usize) -> Box<[Vector3]> {
+        let mut quantized = vec![[0; 3]; self.residuals.len()];
+        let mut previous = None;
+        let order = prediction_order(triangles, offset, self.residuals.len());
+        for ((vertex, prediction), residual) in order.into_iter().zip(self.residuals.iter()) {
+            let predicted = predict(&quantized, prediction, previous);
+            for axis in 0..3 {
+                quantized[vertex][axis] = predicted[axis].wrapping_add(residual[axis]);
+            }
+            previous = Some(vertex);
+        }
+        quantized
+            .iter()
+            .map(|vertex| {
+                let [x, y, z] = [0, 1, 2]
+                    .map(|axis| (self.origin[axis] + vertex[axis] as f64 * self.step) as FloatType);
+                (x, y, z)
+            })
+            .collect()
+    }
+}
+pub(crate) fn read_predicted_vertices<R: Read>(
+    reader: &mut R,
+) -> Result<PredictedVertices, TMFImportError> {
+    let vertex_count = reader.read_u64()?;
+    if vertex_count > MAX_SEG_SIZE as u64 {
+        return Err(TMFImportError::SegmentTooLong);
+    }
+    let origin = [reader.read_f64()?, reader.read_f64()?, reader.read_f64()?];
+    let step = reader.read_f64()?;
+    let mut decoder = RangeDecoder::new(reader)?;
+    let mut models = [ValueModel::new(), ValueModel::new(), ValueModel::new()];
+    let mut residuals = Vec::new();
+    crate::reserve_declared(&mut residuals, vertex_count as usize);
+    for _ in 0..vertex_count {
+        let mut residual = [0; 3];
+        for (residual, model) in residual.iter_mut().zip(models.iter_mut()) {
+            *residual = model.decode_delta(&mut decoder, 0)? as i64;
+        }
+        residuals.push(residual);
+    }
+    Ok(PredictedVertices {
+        origin,
+        step,
+        residuals: residuals.into(),
+    })
 }
 //Those issues wont happen.
 #[allow(clippy::cast_possible_truncation)]
@@ -203,7 +563,8 @@ pub(crate) fn read_triangles<R: Read>(
     if length > MAX_SEG_SIZE as u64 {
         return Err(TMFImportError::SegmentTooLong);
     }
-    let mut res = Vec::with_capacity(length as usize);
+    let mut res = Vec::new();
+    crate::reserve_declared(&mut res, length as usize);
     let precision = UnalignedRWMode::precision_bits(precision);
     let mut reader = UnalignedReader::new(reader);
     for _ in 0..(length / 2) {
@@ -247,6 +608,38 @@ mod testing {
         }
     }*/
     #[test]
+    #[cfg(feature = "obj_import")]
+    fn rw_predicted_vertices() {
+        let (mut tmf_mesh, _) = crate::susan();
+        tmf_mesh.reorder_data();
+        let vertices = tmf_mesh.get_vertices().unwrap();
+        let triangles = tmf_mesh.get_vertex_triangles().unwrap();
+        let fraction = 0.1;
+        let precision = VertexPrecisionMode::relative(fraction);
+        let shortest_edge = 0.01;
+        let mut predicted = Vec::new();
+        assert!(save_predicted_vertices(
+            vertices,
+            triangles,
+            precision,
+            shortest_edge,
+            &mut predicted
+        )
+        .unwrap());
+        let mut plain = Vec::new();
+        save_tmf_vertices(vertices, precision, &mut plain, shortest_edge).unwrap();
+        assert!(predicted.len() < plain.len());
+        let r_vertices = read_predicted_vertices(&mut (&predicted as &[u8]))
+            .unwrap()
+            .reconstruct(triangles, 0);
+        assert_eq!(r_vertices.len(), vertices.len());
+        // Vertices move by at most half of the allowed edge deviation.
+        let max_dst = (shortest_edge * fraction) * 0.5 * 1.001;
+        for (r_vertex, vertex) in r_vertices.iter().zip(vertices) {
+            assert!(dst(*r_vertex, *vertex) <= max_dst);
+        }
+    }
+    #[test]
     fn rw_vertices() {
         use rand::{thread_rng, Rng};
         let mut rng = thread_rng();
@@ -260,7 +653,13 @@ mod testing {
         }
         let mut res = Vec::with_capacity(vertex_count as usize);
         {
-            save_tmf_vertices(&vertices, VertexPrecisionMode(0.01), &mut res, 0.1).unwrap();
+            save_tmf_vertices(
+                &vertices,
+                VertexPrecisionMode::relative(0.01),
+                &mut res,
+                0.1,
+            )
+            .unwrap();
         }
         let r_vertices = read_tmf_vertices(&mut (&res as &[u8])).unwrap();
         assert!(r_vertices.len() == vertices.len());
@@ -274,4 +673,124 @@ mod testing {
             );
         }
     }
+    #[test]
+    fn rw_absolute_precision() {
+        use crate::{TMFExportError, TMFMesh, TMFPrecisionInfo};
+        use rand::{thread_rng, Rng};
+        let mut rng = thread_rng();
+        // A point cloud, which has no edges precision could be relative to.
+        let vertices: Vec<Vector3> = (0..0x1000)
+            .map(|_| {
+                let mut coord = || (rng.gen::<FloatType>() - 0.5) * 2.0;
+                (coord(), coord(), coord())
+            })
+            .collect();
+        let mut mesh = TMFMesh::empty();
+        mesh.set_vertices(&vertices[..]);
+        let deviation = (0.01, 0.001, 0.1);
+        let prec = TMFPrecisionInfo {
+            vertex_precision: VertexPrecisionMode::absolute_per_axis(deviation),
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        mesh.write_tmf_one(&mut out, &prec, "points").unwrap();
+        let (r_mesh, _) = TMFMesh::read_tmf_one(&mut &out[..]).unwrap();
+        let r_vertices = r_mesh.get_vertices().unwrap();
+        assert_eq!(r_vertices.len(), vertices.len());
+        for (r_vertex, vertex) in r_vertices.iter().zip(&vertices) {
+            assert!((r_vertex.0 - vertex.0).abs() <= deviation.0);
+            assert!((r_vertex.1 - vertex.1).abs() <= deviation.1);
+            assert!((r_vertex.2 - vertex.2).abs() <= deviation.2);
+        }
+        // Meshes without a single full triangle can't be saved with relative precision, but can be saved with absolute one.
+        mesh.set_vertex_triangles([0, 1]);
+        let mut out = Vec::new();
+        mesh.write_tmf_one(&mut out, &prec, "edgeless").unwrap();
+        let zero = TMFPrecisionInfo {
+            vertex_precision: VertexPrecisionMode::absolute_per_axis((0.01, 0.0, 0.01)),
+            ..Default::default()
+        };
+        assert!(matches!(
+            mesh.write_tmf_one(&mut Vec::new(), &zero, "zero"),
+            Err(TMFExportError::InvalidVertexPrecision(_))
+        ));
+    }
+    #[test]
+    fn vertex_deviations_are_bounded() {
+        use rand::{thread_rng, Rng};
+        let mut rng = thread_rng();
+        // Axes with very different spans and offsets, which must not be mixed up.
+        let vertices: Vec<Vector3> = (0..0x1000)
+            .map(|_| {
+                let x = rng.gen::<FloatType>() * 100.0 + 3.0;
+                let y = rng.gen::<FloatType>() * 0.001 - 7.0;
+                let z = (rng.gen::<FloatType>() - 0.5) * 1000.0;
+                (x, y, z)
+            })
+            .collect();
+        for deviations in [(0.1, 0.000_1, 1.0), (0.001, 0.000_01, 0.05)] {
+            let precision = VertexPrecisionMode::absolute_per_axis(deviations);
+            let mut res = Vec::new();
+            save_tmf_vertices(&vertices, precision, &mut res, 0.1).unwrap();
+            let r_vertices = read_tmf_vertices(&mut &res[..]).unwrap();
+            check_deviations(&vertices, &r_vertices, precision.max_deviations(0.1));
+        }
+        // Coordinates along an axis may all be the same.
+        let flat: Vec<Vector3> = vertices.iter().map(|v| (v.0, v.1, 2.5)).collect();
+        let mut res = Vec::new();
+        save_tmf_vertices(&flat, VertexPrecisionMode::absolute(0.01), &mut res, 0.1).unwrap();
+        let r_vertices = read_tmf_vertices(&mut &res[..]).unwrap();
+        assert!(r_vertices.iter().all(|vertex| vertex.2 == 2.5));
+    }
+    #[test]
+    #[should_panic(expected = "deviating by")]
+    fn detect_exceeded_deviations() {
+        let vertices = [(0.0, 0.0, 0.0), (1.0, 1.0, 1.0)];
+        let decoded = [(0.0, 0.0, 0.0), (1.0, 1.1, 1.0)];
+        check_deviations(&vertices, &decoded, [0.05; 3]);
+    }
+    #[test]
+    #[cfg(feature = "obj_import")]
+    fn relative_precision_bounds_edges() {
+        let (mut tmf_mesh, _) = crate::susan();
+        tmf_mesh.reorder_data();
+        let vertices = tmf_mesh.get_vertices().unwrap();
+        let triangles = tmf_mesh.get_vertex_triangles().unwrap();
+        let edges = |vertices: &[Vector3]| -> Vec<FloatType> {
+            triangles
+                .chunks_exact(3)
+                .flat_map(|tri| (0..3).map(move |k| (tri[k] as usize, tri[(k + 1) % 3] as usize)))
+                .map(|(a, b)| dst(vertices[a], vertices[b]))
+                .collect()
+        };
+        let lengths = edges(vertices);
+        let shortest_edge = lengths
+            .iter()
+            .copied()
+            .fold(FloatType::INFINITY, FloatType::min);
+        let fraction = 0.1;
+        let precision = VertexPrecisionMode::relative(fraction);
+        let mut plain = Vec::new();
+        save_tmf_vertices(vertices, precision, &mut plain, shortest_edge).unwrap();
+        let mut predicted = Vec::new();
+        save_predicted_vertices(
+            vertices,
+            triangles,
+            precision,
+            shortest_edge,
+            &mut predicted,
+        )
+        .unwrap();
+        let decoded = [
+            read_tmf_vertices(&mut &plain[..]).unwrap(),
+            read_predicted_vertices(&mut &predicted[..])
+                .unwrap()
+                .reconstruct(triangles, 0),
+        ];
+        for r_vertices in decoded {
+            for (r_length, length) in edges(&r_vertices).iter().zip(&lengths) {
+                assert!((r_length - length).abs() <= shortest_edge * fraction * 1.001);
+            }
+        }
+    }
 }